@@ -1 +1,2 @@
-pub mod keyword;
\ No newline at end of file
+pub mod keyword;
+pub mod query;
\ No newline at end of file
@@ -0,0 +1,1024 @@
+use crate::builder::keyword::KeywordBuilder;
+use crate::io::fuzzy::{rank_score, term_quality, token_spans, TokenSpan, QUALITY_EXACT};
+use crate::io::search::{
+    clipboard_search_text, fold_search_text, html_to_text, rtf_to_text, search_tokens,
+};
+use crate::types::enums::{ClipboardTextType, ClipboardType, Language};
+use crate::types::orm_query::{FullClipboardDto, MatchField, MatchRange, SearchMatch};
+use chrono::{NaiveDate, NaiveDateTime};
+use entity::{clipboard, clipboard_file, clipboard_image, clipboard_text};
+use regex::{Regex, RegexBuilder};
+use sea_orm::sea_query::{Expr, Func, LikeExpr, Query};
+use sea_orm::{ColumnTrait, Condition, Iden, Iterable};
+
+/// A `type:` filter, either a top level clipboard type or a text sub type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeFilter {
+    Clipboard(ClipboardType),
+    Text(ClipboardTextType),
+}
+
+/// A word (prefix match) or a double-quoted phrase (exact token sequence).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextTerm {
+    pub text: String,
+    pub phrase: bool,
+}
+
+/// Parsed search box input, e.g.
+/// `type:image ext:png after:2026-01-01 star:true "exact phrase" -excluded /regex/`.
+/// `app:`, `title:` and `url:` match part of where a clipboard was copied.
+///
+/// Filters of the same kind are OR-ed (`type:image type:file`), everything else is
+/// AND-ed. The same query compiles to a SeaORM condition for the database path and
+/// to an in-memory predicate for the decrypted cache, so both match the same rows.
+/// That's why `ext:`, `app:`, `title:` and `url:` ignore the case of ASCII letters
+/// only, SQLite's `LOWER()` and `LIKE` don't fold anything else.
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub types: Vec<TypeFilter>,
+    pub extensions: Vec<String>,
+    pub after: Option<NaiveDateTime>,
+    pub before: Option<NaiveDateTime>,
+    pub star: Option<bool>,
+    pub apps: Vec<String>,
+    pub titles: Vec<String>,
+    pub urls: Vec<String>,
+    pub include: Vec<TextTerm>,
+    pub exclude: Vec<TextTerm>,
+    pub regexes: Vec<Regex>,
+    pub exclude_regexes: Vec<Regex>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str, language: &Language) -> Self {
+        let mut query = SearchQuery::default();
+        let tokens = tokenize(input);
+
+        // Backwards compatible: a lone localized type keyword ("image", "bild", ...)
+        // filters by type like the old single-string search did.
+        if let [RawToken::Word {
+            text,
+            negated: false,
+        }] = tokens.as_slice()
+        {
+            if let Some(filter) = find_keyword_type(text, language) {
+                query.types.push(filter);
+                return query;
+            }
+        }
+
+        for token in tokens {
+            match token {
+                RawToken::Phrase { text, negated } => query.push_text(text, true, negated),
+                RawToken::Regex {
+                    pattern,
+                    flags,
+                    negated,
+                } => match build_regex(&pattern, &flags) {
+                    Some(regex) if negated => query.exclude_regexes.push(regex),
+                    Some(regex) => query.regexes.push(regex),
+                    None => query.push_text(pattern, false, negated),
+                },
+                RawToken::Filter {
+                    key,
+                    value,
+                    negated,
+                } => {
+                    if negated || !query.apply_filter(&key, &value, language) {
+                        query.push_text(format!("{}:{}", key, value), false, negated);
+                    }
+                }
+                RawToken::Word { text, negated } => query.push_text(text, false, negated),
+            }
+        }
+
+        query
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+            && self.extensions.is_empty()
+            && self.after.is_none()
+            && self.before.is_none()
+            && self.star.is_none()
+            && self.apps.is_empty()
+            && self.titles.is_empty()
+            && self.urls.is_empty()
+            && self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.regexes.is_empty()
+            && self.exclude_regexes.is_empty()
+    }
+
+    /// FTS5 MATCH expression for the positive words and phrases, used to rank
+    /// results. Terms the tokenizer can't index are handled by `to_condition`.
+    pub fn fts_query(&self) -> Option<String> {
+        let terms: Vec<String> = self
+            .include
+            .iter()
+            .filter(|t| is_indexable(&t.text))
+            .map(|t| fts_term(t, !t.phrase))
+            .collect();
+
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" "))
+        }
+    }
+
+    /// Type, extension, date, star and source filters only, without any text matching. Used
+    /// to narrow the candidates of a fuzzy search, which can't use the FTS5 index.
+    pub fn filter_condition(&self) -> Condition {
+        let mut condition = Condition::all();
+
+        if !self.types.is_empty() {
+            let mut any_type = Condition::any();
+            for filter in &self.types {
+                any_type = any_type.add(match filter {
+                    TypeFilter::Clipboard(t) => clipboard::Column::Types.contains(t.to_string()),
+                    TypeFilter::Text(t) => clipboard::Column::Id.in_subquery(
+                        Query::select()
+                            .column(clipboard_text::Column::ClipboardId)
+                            .from(clipboard_text::Entity)
+                            .and_where(clipboard_text::Column::Type.eq(t.to_string()))
+                            .to_owned(),
+                    ),
+                });
+            }
+            condition = condition.add(any_type);
+        }
+
+        if !self.extensions.is_empty() {
+            condition = condition.add(
+                Condition::any()
+                    .add(
+                        clipboard::Column::Id.in_subquery(
+                            Query::select()
+                                .column(clipboard_image::Column::ClipboardId)
+                                .from(clipboard_image::Entity)
+                                .and_where(
+                                    Expr::expr(Func::lower(Expr::col(
+                                        clipboard_image::Column::Extension,
+                                    )))
+                                    .is_in(self.extensions.clone()),
+                                )
+                                .to_owned(),
+                        ),
+                    )
+                    .add(
+                        clipboard::Column::Id.in_subquery(
+                            Query::select()
+                                .column(clipboard_file::Column::ClipboardId)
+                                .from(clipboard_file::Entity)
+                                .and_where(
+                                    Expr::expr(Func::lower(Expr::col(
+                                        clipboard_file::Column::Extension,
+                                    )))
+                                    .is_in(self.extensions.clone()),
+                                )
+                                .to_owned(),
+                        ),
+                    ),
+            );
+        }
+
+        if let Some(after) = self.after {
+            condition = condition.add(clipboard::Column::CreatedAt.gte(after));
+        }
+
+        if let Some(before) = self.before {
+            condition = condition.add(clipboard::Column::CreatedAt.lt(before));
+        }
+
+        if let Some(star) = self.star {
+            condition = condition.add(clipboard::Column::Star.eq(star));
+        }
+
+        for (column, values) in self.source_filters() {
+            if !values.is_empty() {
+                let mut any_value = Condition::any();
+                // LIKE ignores the case of ASCII letters
+                for value in values {
+                    any_value = any_value.add(
+                        Expr::col((clipboard::Entity, column))
+                            .like(LikeExpr::new(like_pattern(value)).escape('\\')),
+                    );
+                }
+                condition = condition.add(any_value);
+            }
+        }
+
+        condition
+    }
+
+    /// Every filter except the FTS5 MATCH from `fts_query`, as a condition on
+    /// `clipboard`. Text filters run against the `clipboard_search` content table.
+    pub fn to_condition(&self) -> Condition {
+        let mut condition = self.filter_condition();
+
+        for term in self.include.iter().filter(|t| !is_indexable(&t.text)) {
+            condition = condition.add(Expr::cust_with_values(
+                "clipboard.id IN (SELECT clipboard_id FROM clipboard_search \
+                 WHERE content LIKE ? ESCAPE '\\')",
+                [like_pattern(&term.text)],
+            ));
+        }
+
+        for term in &self.exclude {
+            condition = condition.add(if is_indexable(&term.text) {
+                Expr::cust_with_values(
+                    "clipboard.id NOT IN (SELECT clipboard_search.clipboard_id FROM clipboard_search \
+                     INNER JOIN clipboard_fts ON clipboard_fts.rowid = clipboard_search.id \
+                     WHERE clipboard_fts MATCH ?)",
+                    [fts_term(term, false)],
+                )
+            } else {
+                Expr::cust_with_values(
+                    "clipboard.id NOT IN (SELECT clipboard_id FROM clipboard_search \
+                     WHERE content LIKE ? ESCAPE '\\')",
+                    [like_pattern(&term.text)],
+                )
+            });
+        }
+
+        // REGEXP is registered on every pooled connection (see `connect_and_migrate`)
+        for regex in &self.regexes {
+            condition = condition.add(Expr::cust_with_values(
+                "clipboard.id IN (SELECT clipboard_id FROM clipboard_search WHERE content REGEXP ?)",
+                [regex.as_str().to_string()],
+            ));
+        }
+
+        for regex in &self.exclude_regexes {
+            condition = condition.add(Expr::cust_with_values(
+                "clipboard.id NOT IN (SELECT clipboard_id FROM clipboard_search WHERE content REGEXP ?)",
+                [regex.as_str().to_string()],
+            ));
+        }
+
+        condition
+    }
+
+    /// In-memory equivalent of `fts_query` + `to_condition`, for decrypted clipboards.
+    pub fn matches(&self, clipboard: &FullClipboardDto) -> bool {
+        if !self.matches_filters(clipboard) {
+            return false;
+        }
+
+        if !self.has_text_terms() {
+            return true;
+        }
+
+        // Encrypted clipboards are never in the FTS index, mirror that here
+        if clipboard.clipboard.encrypted {
+            return false;
+        }
+
+        let content = clipboard_search_text(clipboard);
+        let folded = fold_search_text(&content);
+        let tokens = search_tokens(&folded);
+
+        self.include
+            .iter()
+            .all(|term| term_matches(term, !term.phrase, &folded, &tokens))
+            && self.matches_patterns(&content, &folded, &tokens)
+    }
+
+    /// Typo tolerant variant of `matches`: include words may match with a few edits
+    /// (see `fuzzy::max_typos`), every other filter stays exact. Returns the rank of
+    /// the clipboard (match quality, recency, star) and the matched ranges.
+    pub fn fuzzy_match(
+        &self,
+        clipboard: &FullClipboardDto,
+        now: NaiveDateTime,
+    ) -> Option<SearchMatch> {
+        if !self.matches_filters(clipboard) {
+            return None;
+        }
+
+        let mut quality = QUALITY_EXACT;
+        let mut ranges = Vec::new();
+
+        if self.has_text_terms() {
+            if clipboard.clipboard.encrypted {
+                return None;
+            }
+
+            let content = clipboard_search_text(clipboard);
+            let folded = fold_search_text(&content);
+            let tokens = search_tokens(&folded);
+
+            if !self.matches_patterns(&content, &folded, &tokens) {
+                return None;
+            }
+
+            if !self.include.is_empty() {
+                let sources = fuzzy_sources(clipboard);
+                let mut total = 0.0;
+
+                for term in &self.include {
+                    total += if is_indexable(&term.text) {
+                        fuzzy_term(term, &sources, &mut ranges)?
+                    } else if term_matches(term, false, &folded, &tokens) {
+                        QUALITY_EXACT
+                    } else {
+                        return None;
+                    };
+                }
+
+                quality = total / self.include.len() as f32;
+            }
+        }
+
+        ranges.sort();
+        ranges.dedup();
+
+        Some(SearchMatch {
+            score: rank_score(
+                quality,
+                clipboard.clipboard.created_at,
+                clipboard.clipboard.star,
+                now,
+            ),
+            ranges,
+        })
+    }
+
+    fn has_text_terms(&self) -> bool {
+        !self.include.is_empty()
+            || !self.exclude.is_empty()
+            || !self.regexes.is_empty()
+            || !self.exclude_regexes.is_empty()
+    }
+
+    /// The `app:`, `title:` and `url:` values, each with the column they match.
+    fn source_filters(&self) -> [(clipboard::Column, &Vec<String>); 3] {
+        [
+            (clipboard::Column::SourceApp, &self.apps),
+            (clipboard::Column::SourceWindowTitle, &self.titles),
+            (clipboard::Column::SourceUrl, &self.urls),
+        ]
+    }
+
+    /// Type, extension, date, star and source filters.
+    fn matches_filters(&self, clipboard: &FullClipboardDto) -> bool {
+        let model = &clipboard.clipboard;
+        let types = ClipboardType::from_json_value(&model.types).unwrap_or_default();
+
+        if !self.types.is_empty()
+            && !self.types.iter().any(|filter| match filter {
+                TypeFilter::Clipboard(t) => types.contains(t),
+                TypeFilter::Text(t) => clipboard
+                    .text
+                    .as_ref()
+                    .is_some_and(|text| text.r#type == t.to_string()),
+            })
+        {
+            return false;
+        }
+
+        if !self.extensions.is_empty() {
+            let image_ext = clipboard
+                .image
+                .as_ref()
+                .map(|i| i.extension.to_ascii_lowercase());
+            let file_exts = clipboard
+                .files
+                .iter()
+                .filter_map(|f| f.extension.as_ref().map(|e| e.to_ascii_lowercase()));
+            if !image_ext
+                .into_iter()
+                .chain(file_exts)
+                .any(|ext| self.extensions.contains(&ext))
+            {
+                return false;
+            }
+        }
+
+        let sources = [
+            &model.source_app,
+            &model.source_window_title,
+            &model.source_url,
+        ];
+        for ((_, values), source) in self.source_filters().into_iter().zip(sources) {
+            if !values.is_empty()
+                && !source.as_ref().is_some_and(|source| {
+                    let source = source.to_ascii_lowercase();
+                    values.iter().any(|value| source.contains(value.as_str()))
+                })
+            {
+                return false;
+            }
+        }
+
+        !(self.after.is_some_and(|after| model.created_at < after)
+            || self.before.is_some_and(|before| model.created_at >= before)
+            || self.star.is_some_and(|star| model.star != star))
+    }
+
+    /// Excluded terms and regexes against the search content.
+    fn matches_patterns(&self, content: &str, folded: &str, tokens: &[&str]) -> bool {
+        !self
+            .exclude
+            .iter()
+            .any(|term| term_matches(term, false, folded, tokens))
+            && self.regexes.iter().all(|regex| regex.is_match(content))
+            && !self
+                .exclude_regexes
+                .iter()
+                .any(|regex| regex.is_match(content))
+    }
+
+    fn push_text(&mut self, text: String, phrase: bool, negated: bool) {
+        if text.trim().is_empty() {
+            return;
+        }
+
+        let term = TextTerm { text, phrase };
+        if negated {
+            self.exclude.push(term);
+        } else {
+            self.include.push(term);
+        }
+    }
+
+    /// Applies a `key:value` filter, returns false if the key or value is unknown so
+    /// the caller can search for it as plain text instead.
+    fn apply_filter(&mut self, key: &str, value: &str, language: &Language) -> bool {
+        match key.to_lowercase().as_str() {
+            "type" | "is" => match find_type(value, language) {
+                Some(filter) => self.types.push(filter),
+                None => return false,
+            },
+            "ext" => self
+                .extensions
+                .push(value.trim_start_matches('.').to_ascii_lowercase()),
+            "after" => match parse_date(value) {
+                Some(date) => self.after = Some(date),
+                None => return false,
+            },
+            "before" => match parse_date(value) {
+                Some(date) => self.before = Some(date),
+                None => return false,
+            },
+            "star" | "starred" => match value.to_lowercase().as_str() {
+                "true" | "yes" | "1" => self.star = Some(true),
+                "false" | "no" | "0" => self.star = Some(false),
+                _ => return false,
+            },
+            "app" => self.apps.push(value.to_ascii_lowercase()),
+            "title" => self.titles.push(value.to_ascii_lowercase()),
+            "url" => self.urls.push(value.to_ascii_lowercase()),
+            "regex" => match build_regex(value, "") {
+                Some(regex) => self.regexes.push(regex),
+                None => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[derive(Debug)]
+enum RawToken {
+    Word {
+        text: String,
+        negated: bool,
+    },
+    Phrase {
+        text: String,
+        negated: bool,
+    },
+    Regex {
+        pattern: String,
+        flags: String,
+        negated: bool,
+    },
+    Filter {
+        key: String,
+        value: String,
+        negated: bool,
+    },
+}
+
+fn tokenize(input: &str) -> Vec<RawToken> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let negated = chars[i] == '-' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+        if negated {
+            i += 1;
+        }
+
+        match chars[i] {
+            '"' => {
+                let (text, next) = read_until(&chars, i + 1, '"');
+                tokens.push(RawToken::Phrase { text, negated });
+                i = next;
+            }
+            '/' if regex_end(&chars, i).is_some() => {
+                let (close, end) = regex_end(&chars, i).expect("checked regex end");
+                tokens.push(RawToken::Regex {
+                    pattern: chars[i + 1..close]
+                        .iter()
+                        .collect::<String>()
+                        .replace("\\/", "/"),
+                    flags: chars[close + 1..end].iter().collect(),
+                    negated,
+                });
+                i = end;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && (chars[i] != ':' || i == start)
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+
+                if i < chars.len() && chars[i] == ':' && !word.is_empty() {
+                    // key:value or key:"quoted value"
+                    let (value, next) = if chars.get(i + 1) == Some(&'"') {
+                        read_until(&chars, i + 2, '"')
+                    } else {
+                        let value_start = i + 1;
+                        let mut end = value_start;
+                        while end < chars.len() && !chars[end].is_whitespace() {
+                            end += 1;
+                        }
+                        (chars[value_start..end].iter().collect(), end)
+                    };
+                    tokens.push(RawToken::Filter {
+                        key: word,
+                        value,
+                        negated,
+                    });
+                    i = next;
+                } else {
+                    tokens.push(RawToken::Word {
+                        text: word,
+                        negated,
+                    });
+                }
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Finds the closing slash of a `/regex/flags` token starting at `start`. The slash
+/// must be followed by optional `imsx` flags and then whitespace or the end of input,
+/// so paths like `/usr/bin` stay plain words. Returns (closing slash, token end).
+fn regex_end(chars: &[char], start: usize) -> Option<(usize, usize)> {
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '/' => {
+                let mut end = i + 1;
+                while end < chars.len() && matches!(chars[end], 'i' | 'm' | 's' | 'x') {
+                    end += 1;
+                }
+                if end == chars.len() || chars[end].is_whitespace() {
+                    return (i > start + 1).then_some((i, end));
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    None
+}
+
+/// Reads up to the next unescaped `end` char, returns the text and the index after it.
+fn read_until(chars: &[char], start: usize, end: char) -> (String, usize) {
+    let mut text = String::new();
+    let mut i = start;
+
+    while i < chars.len() {
+        if chars[i] == '\\' && chars.get(i + 1) == Some(&end) {
+            text.push(end);
+            i += 2;
+            continue;
+        }
+        if chars[i] == end {
+            return (text, i + 1);
+        }
+        text.push(chars[i]);
+        i += 1;
+    }
+
+    (text, i)
+}
+
+fn build_regex(pattern: &str, flags: &str) -> Option<Regex> {
+    if pattern.is_empty() {
+        return None;
+    }
+
+    // Inline the flags so `Regex::as_str` round-trips through SQLite REGEXP.
+    let inline: String = flags
+        .chars()
+        .filter(|c| matches!(c, 'i' | 'm' | 's' | 'x'))
+        .collect();
+    let pattern = if inline.is_empty() {
+        pattern.to_string()
+    } else {
+        format!("(?{}){}", inline, pattern)
+    };
+
+    RegexBuilder::new(&pattern).size_limit(1 << 20).build().ok()
+}
+
+fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// Resolves a `type:` value: enum names (`image`, `link`) or localized keywords.
+fn find_type(value: &str, language: &Language) -> Option<TypeFilter> {
+    let value = value.to_lowercase();
+
+    if let Some(t) = ClipboardType::iter().find(|t| t.to_string() == value) {
+        return Some(TypeFilter::Clipboard(t));
+    }
+
+    if let Some(t) = ClipboardTextType::iter().find(|t| t.to_string() == value) {
+        return Some(TypeFilter::Text(t));
+    }
+
+    let (_, text_keywords) = KeywordBuilder::build_default();
+    find_keyword_type(&value, language).or_else(|| {
+        KeywordBuilder::find_text_type(&value, language, &text_keywords).map(TypeFilter::Text)
+    })
+}
+
+fn find_keyword_type(value: &str, language: &Language) -> Option<TypeFilter> {
+    let (clipboard_keywords, text_keywords) = KeywordBuilder::build_default();
+    let value = value.to_lowercase();
+
+    match KeywordBuilder::find_clipboard_type(&value, language, &clipboard_keywords)? {
+        ClipboardType::Text => Some(TypeFilter::Text(
+            KeywordBuilder::find_text_type(&value, language, &text_keywords)
+                .unwrap_or(ClipboardTextType::Text),
+        )),
+        clip_type => Some(TypeFilter::Clipboard(clip_type)),
+    }
+}
+
+/// Substring pattern for `LIKE ... ESCAPE '\'`, so `%`, `_` and `\` in the search
+/// match themselves like they do in memory.
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// True if the unicode61 tokenizer produces at least one token for the text.
+fn is_indexable(text: &str) -> bool {
+    text.chars().any(char::is_alphanumeric)
+}
+
+fn fts_term(term: &TextTerm, prefix: bool) -> String {
+    let quoted = format!("\"{}\"", term.text.trim().replace('"', "\"\""));
+    if prefix {
+        format!("{}*", quoted)
+    } else {
+        quoted
+    }
+}
+
+/// FTS5 semantics in memory: the term's tokens must appear consecutively, with the
+/// last one matched as a prefix for bare words. Non-indexable terms use substring.
+fn term_matches(term: &TextTerm, prefix: bool, folded: &str, tokens: &[&str]) -> bool {
+    let needle = fold_search_text(&term.text);
+    if !is_indexable(&needle) {
+        return folded.contains(needle.trim());
+    }
+
+    let needle_tokens = search_tokens(&needle);
+    let Some((last, init)) = needle_tokens.split_last() else {
+        return false;
+    };
+
+    tokens.windows(needle_tokens.len()).any(|window| {
+        window[..init.len()] == *init
+            && if prefix {
+                window[init.len()].starts_with(last)
+            } else {
+                window[init.len()] == *last
+            }
+    })
+}
+
+/// Tokenized text a fuzzy term is matched against. `location` is set for the fields
+/// the UI renders, so matches there produce highlight ranges.
+struct FuzzySource {
+    location: Option<(MatchField, usize)>,
+    tokens: Vec<TokenSpan>,
+}
+
+fn fuzzy_sources(clipboard: &FullClipboardDto) -> Vec<FuzzySource> {
+    let mut sources = Vec::new();
+    let mut rest: Vec<String> = Vec::new();
+
+    if let Some(text) = &clipboard.text {
+        sources.push(FuzzySource {
+            location: Some((MatchField::Text, 0)),
+            tokens: token_spans(&text.data),
+        });
+    }
+
+    if let Some(html) = &clipboard.html {
+        rest.push(html_to_text(&html.data));
+    }
+
+    if let Some(rtf) = &clipboard.rtf {
+        rest.push(rtf_to_text(&rtf.data));
+    }
+
+    if let Some(image) = &clipboard.image {
+        rest.push(image.extension.clone());
+        if let Some(ocr_text) = &image.ocr_text {
+            sources.push(FuzzySource {
+                location: Some((MatchField::Ocr, 0)),
+                tokens: token_spans(ocr_text),
+            });
+        }
+    }
+
+    for (index, file) in clipboard.files.iter().enumerate() {
+        sources.push(FuzzySource {
+            location: Some((MatchField::File, index)),
+            tokens: token_spans(&file.name),
+        });
+        rest.extend(file.extension.clone());
+        rest.extend(file.mime_type.clone());
+    }
+
+    sources.push(FuzzySource {
+        location: None,
+        tokens: token_spans(&rest.join("\n")),
+    });
+
+    sources
+}
+
+/// Best quality of a term over all sources, or `None` if it matches nowhere. The
+/// term's tokens must appear consecutively; phrases only accept exact tokens. Every
+/// occurrence in a rendered field is added to `ranges`.
+fn fuzzy_term(
+    term: &TextTerm,
+    sources: &[FuzzySource],
+    ranges: &mut Vec<MatchRange>,
+) -> Option<f32> {
+    let needle = fold_search_text(&term.text);
+    let needle_tokens = search_tokens(&needle);
+    let mut best: Option<f32> = None;
+
+    for source in sources {
+        for window in source.tokens.windows(needle_tokens.len()) {
+            let mut total = 0.0;
+            let matched = needle_tokens.iter().zip(window).all(|(needle, token)| {
+                let quality = if term.phrase {
+                    (token.folded == *needle).then_some(QUALITY_EXACT)
+                } else {
+                    term_quality(needle, &token.folded)
+                };
+                total += quality.unwrap_or_default();
+                quality.is_some()
+            });
+
+            if !matched {
+                continue;
+            }
+
+            let quality = total / needle_tokens.len() as f32;
+            best = Some(best.map_or(quality, |b| b.max(quality)));
+
+            if let (Some((field, index)), Some(first), Some(last)) =
+                (source.location, window.first(), window.last())
+            {
+                ranges.push(MatchRange {
+                    field,
+                    index,
+                    start: first.start,
+                    end: last.end,
+                });
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait, Value};
+
+    fn parse(input: &str) -> SearchQuery {
+        SearchQuery::parse(input, &Language::English)
+    }
+
+    /// SQL of a condition on `clipboard` and its string values.
+    fn statement(condition: Condition) -> (String, Vec<String>) {
+        let statement = clipboard::Entity::find()
+            .filter(condition)
+            .build(DbBackend::Sqlite);
+        let values = statement
+            .values
+            .map(|values| values.0)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::String(Some(value)) => Some(*value),
+                _ => None,
+            })
+            .collect();
+
+        (statement.sql, values)
+    }
+
+    fn term(text: &str, phrase: bool) -> TextTerm {
+        TextTerm {
+            text: text.to_string(),
+            phrase,
+        }
+    }
+
+    #[test]
+    fn parses_words_phrases_and_negations() {
+        let query = parse(r#"hello "exact phrase" -excluded -"not this""#);
+
+        assert_eq!(
+            query.include,
+            vec![term("hello", false), term("exact phrase", true)]
+        );
+        assert_eq!(
+            query.exclude,
+            vec![term("excluded", false), term("not this", true)]
+        );
+        assert_eq!(
+            query.fts_query().as_deref(),
+            Some(r#""hello"* "exact phrase""#)
+        );
+    }
+
+    #[test]
+    fn parses_filters() {
+        let query = parse(
+            "type:image type:file ext:.PNG after:2026-01-01 before:2026-02-01T12:30 star:yes \
+             app:Firefox title:\"Pull Request\" url:github.com",
+        );
+
+        assert_eq!(
+            query.types,
+            vec![
+                TypeFilter::Clipboard(ClipboardType::Image),
+                TypeFilter::Clipboard(ClipboardType::File),
+            ]
+        );
+        assert_eq!(query.extensions, vec!["png"]);
+        assert_eq!(
+            query.after,
+            NaiveDate::from_ymd_opt(2026, 1, 1).and_then(|d| d.and_hms_opt(0, 0, 0))
+        );
+        assert_eq!(
+            query.before,
+            NaiveDate::from_ymd_opt(2026, 2, 1).and_then(|d| d.and_hms_opt(12, 30, 0))
+        );
+        assert_eq!(query.star, Some(true));
+        assert_eq!(query.apps, vec!["firefox"]);
+        assert_eq!(query.titles, vec!["pull request"]);
+        assert_eq!(query.urls, vec!["github.com"]);
+        assert!(query.include.is_empty());
+    }
+
+    #[test]
+    fn unknown_and_negated_filters_are_text() {
+        let query = parse("foo:bar type:nonsense after:yesterday -star:true");
+
+        assert_eq!(
+            query.include,
+            vec![
+                term("foo:bar", false),
+                term("type:nonsense", false),
+                term("after:yesterday", false),
+            ]
+        );
+        assert_eq!(query.exclude, vec![term("star:true", false)]);
+        assert!(query.types.is_empty());
+        assert!(query.after.is_none());
+        assert!(query.star.is_none());
+    }
+
+    #[test]
+    fn parses_regexes() {
+        let query = parse(r"/fo+\/bar/i -/baz$/ /usr/bin regex:^a");
+
+        let regexes: Vec<&str> = query.regexes.iter().map(Regex::as_str).collect();
+        assert_eq!(regexes, vec!["(?i)fo+/bar", "^a"]);
+        assert_eq!(query.exclude_regexes[0].as_str(), "baz$");
+        assert_eq!(query.include, vec![term("/usr/bin", false)]);
+    }
+
+    #[test]
+    fn invalid_regex_is_text() {
+        let query = parse("/(unclosed/");
+
+        assert!(query.regexes.is_empty());
+        assert_eq!(query.include, vec![term("(unclosed", false)]);
+    }
+
+    #[test]
+    fn lone_type_keyword_filters_by_type() {
+        let query = parse("image");
+
+        assert_eq!(
+            query.types,
+            vec![TypeFilter::Clipboard(ClipboardType::Image)]
+        );
+        assert!(query.include.is_empty());
+    }
+
+    #[test]
+    fn empty_input_is_empty() {
+        assert!(parse("").is_empty());
+        assert!(parse("   \"\"  ").is_empty());
+        assert!(!parse("star:false").is_empty());
+    }
+
+    #[test]
+    fn like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("abc"), "%abc%");
+        assert_eq!(like_pattern("100%"), r"%100\%%");
+        assert_eq!(like_pattern("a_b"), r"%a\_b%");
+        assert_eq!(like_pattern(r"C:\temp"), r"%C:\\temp%");
+    }
+
+    #[test]
+    fn source_and_extension_filters_fold_ascii_only() {
+        let query = parse("app:ÉDITEUR ext:PNG");
+        assert_eq!(query.apps, vec!["Éditeur"]);
+        assert_eq!(query.extensions, vec!["png"]);
+
+        let (sql, values) = statement(query.filter_condition());
+        assert!(
+            sql.contains(r#""clipboard"."source_app" LIKE ?"#),
+            "{}",
+            sql
+        );
+        assert!(values.contains(&"%Éditeur%".to_string()));
+
+        let clipboard = |app: &str, extension: &str| FullClipboardDto {
+            clipboard: clipboard::Model {
+                types: serde_json::json!(["image"]),
+                source_app: Some(app.to_string()),
+                ..Default::default()
+            },
+            text: None,
+            html: None,
+            image: Some(clipboard_image::Model {
+                extension: extension.to_string(),
+                ..Default::default()
+            }),
+            rtf: None,
+            files: Vec::new(),
+            search_match: None,
+        };
+        assert!(query.matches(&clipboard("Éditeur Pro", "PNG")));
+        assert!(query.matches(&clipboard("ÉDITEUR", "png")));
+        assert!(!query.matches(&clipboard("éditeur", "png")));
+    }
+
+    #[test]
+    fn like_conditions_escape() {
+        let (sql, values) = statement(parse("-% __ url:50%_off").to_condition());
+
+        assert_eq!(sql.matches(r"LIKE ? ESCAPE '\'").count(), 3, "{}", sql);
+        assert_eq!(values, vec![r"%50\%\_off%", r"%\_\_%", r"%\%%"]);
+    }
+}
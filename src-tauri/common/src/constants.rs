@@ -9,16 +9,59 @@ pub static GLOBAL_EVENTS: LazyLock<Vec<String>> = LazyLock::new(|| {
     ]
 });
 
+/// Must match `identifier` in tauri.conf.json, the headless CLI resolves the app data dir with it.
+pub static APP_IDENTIFIER: &str = "clippy";
 pub static DB_NAME: &str = "clippy.sqlite";
 pub static CONFIG_NAME: &str = "config.json";
 pub static TOKEN_NAME: &str = "token.json";
+/// Change feed cursor of the sync provider, prefixed with the provider type.
+pub static SYNC_CURSOR_NAME: &str = "sync_cursor.txt";
+/// Keychain entry of the WebDAV password.
+pub static WEBDAV_CREDENTIAL: &str = "webdav";
+/// Keychain entry of the S3 secret access key.
+pub static S3_CREDENTIAL: &str = "s3";
+/// Keychain entry of the key shared by LAN sync devices.
+pub static LAN_CREDENTIAL: &str = "lan";
+/// Folder in the app data dir the LAN sync host keeps the synced files in.
+pub static LAN_STORE_DIR: &str = "lan_sync";
+/// Folder in the app data dir the external blob store keeps its files in.
+pub static BLOB_STORE_DIR: &str = "blobs";
+/// Blobs below this many bytes stay in the db even with the external store enabled.
+pub static EXTERNAL_BLOB_MIN_SIZE: usize = 64 * 1024;
+/// Seconds an expiring sensitive clipboard is kept when its rule doesn't say.
+pub static SENSITIVE_EXPIRE_AFTER: u64 = 60;
+/// Clipboard formats password managers add to mark a copied secret, on macOS
+/// (nspasteboard.org), Windows and KDE.
+pub static CONCEALED_CLIPBOARD_FORMATS: &[&str] = &[
+    "org.nspasteboard.ConcealedType",
+    "ExcludeClipboardContentFromMonitorProcessing",
+    "x-kde-passwordManagerHint",
+];
+/// Clipboard formats that mark a copy as only briefly on the clipboard (nspasteboard.org).
+pub static TRANSIENT_CLIPBOARD_FORMATS: &[&str] = &["org.nspasteboard.TransientType"];
+/// Windows clipboard format of HTML with the CF_HTML header in front, see `html_source_url`.
+pub static CF_HTML_FORMAT: &str = "HTML Format";
 pub static CACHE_KEY: &str = "clipboards";
 pub static ENCRYPTION_MAGIC_STRING: &str = "clippy";
 
 pub static BACKUP_SETTINGS_PREFIX: &str = "settings";
 pub static BACKUP_FILE_PREFIX: &str = "clippy";
+/// Provider document listing deleted clipboards, see `TombstoneDocument`.
+pub static TOMBSTONE_DOCUMENT_NAME: &str = "tombstones.json";
+/// Provider document listing the devices that sync with it, see `SyncDevice`.
+pub static DEVICE_MANIFEST_NAME: &str = "devices.json";
 pub static BACKDUP_DATE_FORMAT: &str = "%Y%m%d%H%M%S";
 
+pub static ARCHIVE_FORMAT: &str = "clippy-archive";
+/// Bumped on incompatible layout changes, newer archives are refused on import.
+pub static ARCHIVE_VERSION: u32 = 1;
+/// Clipboards loaded with their blobs at once while exporting.
+pub static ARCHIVE_BATCH_SIZE: usize = 50;
+pub static SEALED_ARCHIVE_MAGIC: &[u8] = b"clippy-sealed";
+pub static SEALED_ARCHIVE_VERSION: u8 = 1;
+/// Plaintext bytes per AES-GCM chunk of a sealed archive.
+pub static SEALED_ARCHIVE_CHUNK_SIZE: usize = 1024 * 1024;
+
 pub static MAIN_WINDOW_X: i32 = 375;
 pub static MAIN_WINDOW_Y: i32 = 600;
 
@@ -35,6 +78,16 @@ pub static SYNC_LIMIT_SIZE_DEV: u64 = 10;
 pub static SYNC_LIMIT_SIZE_PROD: u64 = 100;
 pub static SYNC_LIMIT_SIZE_MIN: u64 = 0;
 pub static SYNC_LIMIT_SIZE_MAX: u64 = 250;
+/// Newest sync conflicts kept in the log, older ones are dropped.
+pub static SYNC_CONFLICT_LOG_LIMIT: u64 = 200;
+
+/// Best fuzzy search results kept, the rest would never be scrolled to anyway.
+pub static FUZZY_SEARCH_LIMIT: usize = 500;
+
+/// Days a tombstone stays once every known device has acknowledged it.
+pub static TOMBSTONE_TTL_DAYS: i32 = 30;
+pub static TOMBSTONE_TTL_DAYS_MIN: i32 = 1;
+pub static TOMBSTONE_TTL_DAYS_MAX: i32 = 365;
 
 pub static DISPLAY_SCALE: f32 = 1.0;
 pub static DISPLAY_SCALE_MIN: f32 = 0.5;
@@ -59,3 +112,12 @@ pub static MAX_RTF_SIZE_MAX: u32 = 104_857_600;
 pub static MAX_HTML_SIZE: u32 = 10_485_760;
 pub static MAX_HTML_SIZE_MIN: u32 = 0;
 pub static MAX_HTML_SIZE_MAX: u32 = 104_857_600;
+
+pub static API_PORT: u16 = 53_421;
+pub static API_PORT_MIN: u16 = 1_024;
+pub static API_PORT_MAX: u16 = 65_535;
+
+pub static LAN_PORT: u16 = 53_422;
+
+/// How long `clippy <subcommand>` waits for the running app to answer.
+pub static CLI_REPLY_TIMEOUT_SECS: u64 = 120;
@@ -0,0 +1,163 @@
+use crate::constants::{ARCHIVE_FORMAT, ARCHIVE_VERSION};
+use crate::types::archive::{ArchiveError, ArchiveManifest};
+use crate::types::orm_query::FullClipboardDto;
+use sea_orm::prelude::Uuid;
+use std::io::{Read, Seek, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const CLIPBOARD_DIR: &str = "clipboards/";
+const BLOB_DIR: &str = "blobs/";
+
+/// Writes an export archive, a zip laid out as:
+/// - `manifest.json`: format, version and the filter the export was made with
+/// - `clipboards/<uuid>.json`: one `FullClipboardDto` per clipboard, blob data emptied
+/// - `blobs/<id>`: raw image/file bytes, named after the id of their image/file row
+pub struct ArchiveWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    pub fn new(writer: W, manifest: &ArchiveManifest) -> Result<Self, ArchiveError> {
+        Self::start(ZipWriter::new(writer), manifest)
+    }
+
+    fn start(mut zip: ZipWriter<W>, manifest: &ArchiveManifest) -> Result<Self, ArchiveError> {
+        zip.start_file(MANIFEST_ENTRY, deflated())?;
+        zip.write_all(&serde_json::to_vec_pretty(manifest)?)?;
+
+        Ok(Self { zip })
+    }
+
+    pub fn add_clipboard(&mut self, mut clipboard: FullClipboardDto) -> Result<(), ArchiveError> {
+        if let Some(image) = &mut clipboard.image {
+            let data = std::mem::take(&mut image.data);
+            self.add_blob(image.id, &mut data.as_slice())?;
+        }
+
+        for file in &mut clipboard.files {
+            let data = std::mem::take(&mut file.data);
+            self.add_blob(file.id, &mut data.as_slice())?;
+        }
+
+        self.add_clipboard_entry(&clipboard)
+    }
+
+    /// Writes only the clipboard entry, its image/file bytes have to be added through
+    /// `add_blob` separately.
+    pub fn add_clipboard_entry(
+        &mut self,
+        clipboard: &FullClipboardDto,
+    ) -> Result<(), ArchiveError> {
+        self.zip.start_file(
+            format!("{}{}.json", CLIPBOARD_DIR, clipboard.clipboard.id),
+            deflated(),
+        )?;
+        self.zip.write_all(&serde_json::to_vec(clipboard)?)?;
+
+        Ok(())
+    }
+
+    /// Copies the bytes of the image/file row `id` from `reader` into the archive.
+    pub fn add_blob(&mut self, id: Uuid, reader: &mut impl Read) -> Result<(), ArchiveError> {
+        // Images are compressed already, deflating them again only costs time
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+        self.zip.start_file(format!("{}{}", BLOB_DIR, id), options)?;
+        std::io::copy(reader, &mut self.zip)?;
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<W, ArchiveError> {
+        Ok(self.zip.finish()?)
+    }
+}
+
+impl<W: Read + Write + Seek> ArchiveWriter<W> {
+    /// Flushes `writer` after every entry, the zip writer never goes back before the
+    /// last flush. For writers that can't change what they flushed.
+    pub fn new_flushing(writer: W, manifest: &ArchiveManifest) -> Result<Self, ArchiveError> {
+        let mut zip = ZipWriter::new(writer);
+        zip.set_flush_on_finish_file(true);
+
+        Self::start(zip, manifest)
+    }
+}
+
+pub struct ArchiveReader<R: Read + Seek> {
+    zip: ZipArchive<R>,
+    pub manifest: ArchiveManifest,
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    pub fn new(reader: R) -> Result<Self, ArchiveError> {
+        let mut zip = ZipArchive::new(reader).map_err(|_| ArchiveError::NotAnArchive)?;
+
+        let manifest: ArchiveManifest = match zip.by_name(MANIFEST_ENTRY) {
+            Ok(mut entry) => serde_json::from_slice(&read_entry(&mut entry)?)?,
+            Err(_) => return Err(ArchiveError::NotAnArchive),
+        };
+
+        if manifest.format != ARCHIVE_FORMAT {
+            return Err(ArchiveError::NotAnArchive);
+        }
+        if manifest.version > ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(manifest.version));
+        }
+
+        Ok(Self { zip, manifest })
+    }
+
+    /// Ids of all clipboards in the archive, oldest first (UUIDv7).
+    pub fn clipboard_ids(&self) -> Vec<Uuid> {
+        let mut ids = self
+            .zip
+            .file_names()
+            .filter_map(|name| name.strip_prefix(CLIPBOARD_DIR)?.strip_suffix(".json"))
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .collect::<Vec<_>>();
+
+        ids.sort();
+        ids
+    }
+
+    /// Reads a clipboard with its image/file data restored from the blob entries.
+    pub fn read_clipboard(&mut self, id: Uuid) -> Result<FullClipboardDto, ArchiveError> {
+        let mut entry = self
+            .zip
+            .by_name(&format!("{}{}.json", CLIPBOARD_DIR, id))?;
+        let mut clipboard: FullClipboardDto = serde_json::from_slice(&read_entry(&mut entry)?)?;
+        drop(entry);
+
+        if let Some(image) = &mut clipboard.image {
+            image.data = self.read_blob(image.id)?;
+        }
+
+        for file in &mut clipboard.files {
+            file.data = self.read_blob(file.id)?;
+        }
+
+        Ok(clipboard)
+    }
+
+    fn read_blob(&mut self, id: Uuid) -> Result<Vec<u8>, ArchiveError> {
+        let mut entry = self
+            .zip
+            .by_name(&format!("{}{}", BLOB_DIR, id))
+            .map_err(|_| ArchiveError::MissingBlob(id))?;
+
+        read_entry(&mut entry)
+    }
+}
+
+fn deflated() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+fn read_entry(entry: &mut impl Read) -> Result<Vec<u8>, ArchiveError> {
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
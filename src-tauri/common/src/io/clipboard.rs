@@ -1,4 +1,8 @@
-use crate::{constants::MAX_TEXT_PREVIEW, types::orm_query::FullClipboardDto};
+use crate::{
+    constants::MAX_TEXT_PREVIEW,
+    types::orm_query::{FullClipboardDbo, FullClipboardDto},
+};
+use ring::{digest, hmac};
 use tl::{parse, ParserOptions};
 
 pub fn trim_clipboard_data(mut clipboards: Vec<FullClipboardDto>) -> Vec<FullClipboardDto> {
@@ -53,3 +57,225 @@ fn truncate_text(text: &str, max_length: usize) -> String {
             .collect()
     }
 }
+
+/// Page an HTML clipboard was copied from, browsers on Windows put it in the CF_HTML
+/// header (`SourceURL:https://...`) in front of the markup.
+pub fn html_source_url(html: &str) -> Option<String> {
+    html.lines()
+        .take_while(|line| !line.trim_start().starts_with('<'))
+        .find_map(|line| line.strip_prefix("SourceURL:"))
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(str::to_owned)
+}
+
+/// Hex SHA-256 of image or file bytes, the key they are stored under in the `blob` table.
+pub fn blob_hash(data: &[u8]) -> String {
+    to_hex(digest::digest(&digest::SHA256, data))
+}
+
+/// Blob key of the plain bytes of an encrypted image or file, an HMAC-SHA256 under the
+/// encryption key. It doesn't give the content away and never matches a plain blob's key.
+pub fn encrypted_blob_hash(key: &[u8], data: &[u8]) -> String {
+    to_hex(hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data))
+}
+
+/// Hex hash over the plain content of a clipboard, the same thing copied again gives
+/// the same hash. Line endings are normalized, thumbnails, OCR text and file dates are
+/// left out. Encrypted clipboards pass the encryption `key`, their hash is an HMAC then,
+/// so it can't be used to confirm a guessed secret.
+pub fn clipboard_payload_hash(clipboard: &FullClipboardDto, key: Option<&[u8]>) -> String {
+    payload_hash(
+        key,
+        clipboard.text.as_ref().map(|text| text.data.as_str()),
+        clipboard.html.as_ref().map(|html| html.data.as_str()),
+        clipboard.rtf.as_ref().map(|rtf| rtf.data.as_str()),
+        clipboard.image.as_ref().map(|image| image.data.as_slice()),
+        clipboard.files.iter().map(|file| {
+            (
+                file.name.as_str(),
+                file.extension.as_deref(),
+                file.data.as_slice(),
+            )
+        }),
+    )
+}
+
+/// `clipboard_payload_hash` of a parsed clipboard that isn't stored yet.
+pub fn dbo_payload_hash(clipboard: &FullClipboardDbo, key: Option<&[u8]>) -> String {
+    payload_hash(
+        key,
+        clipboard
+            .clipboard_text_model
+            .data
+            .try_as_ref()
+            .map(String::as_str),
+        clipboard
+            .clipboard_html_model
+            .data
+            .try_as_ref()
+            .map(String::as_str),
+        clipboard
+            .clipboard_rtf_model
+            .data
+            .try_as_ref()
+            .map(String::as_str),
+        clipboard
+            .clipboard_image_model
+            .data
+            .try_as_ref()
+            .map(Vec::as_slice),
+        clipboard.clipboard_files_model.iter().map(|file| {
+            (
+                file.name
+                    .try_as_ref()
+                    .map(String::as_str)
+                    .unwrap_or_default(),
+                file.extension.try_as_ref().and_then(Option::as_deref),
+                file.data
+                    .try_as_ref()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default(),
+            )
+        }),
+    )
+}
+
+fn payload_hash<'a>(
+    key: Option<&[u8]>,
+    text: Option<&str>,
+    html: Option<&str>,
+    rtf: Option<&str>,
+    image: Option<&[u8]>,
+    files: impl Iterator<Item = (&'a str, Option<&'a str>, &'a [u8])>,
+) -> String {
+    let mut context = digest::Context::new(&digest::SHA256);
+
+    // Every part is tagged and length prefixed, so no two payloads hash the same input
+    let mut part = |tag: u8, data: &[u8]| {
+        context.update(&[tag]);
+        context.update(&(data.len() as u64).to_le_bytes());
+        context.update(data);
+    };
+
+    // Empty parts are never stored, see `insert_clipboard_dbo`
+    for (tag, data) in [(b't', text), (b'h', html), (b'r', rtf)] {
+        if let Some(data) = data.filter(|data| !data.is_empty()) {
+            part(tag, data.replace("\r\n", "\n").as_bytes());
+        }
+    }
+
+    if let Some(image) = image.filter(|image| !image.is_empty()) {
+        part(b'i', image);
+    }
+
+    for (name, extension, data) in files {
+        part(b'f', name.as_bytes());
+        part(b'e', extension.unwrap_or_default().as_bytes());
+        part(b'd', data);
+    }
+
+    let digest = context.finish();
+    match key {
+        Some(key) => encrypted_blob_hash(key, digest.as_ref()),
+        None => to_hex(digest),
+    }
+}
+
+fn to_hex(bytes: impl AsRef<[u8]>) -> String {
+    bytes
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CF_HTML: &str = "Version:0.9\r\n\
+        StartHTML:0000000171\r\n\
+        EndHTML:0000000257\r\n\
+        StartFragment:0000000207\r\n\
+        EndFragment:0000000221\r\n\
+        SourceURL:https://example.com/page?q=1\r\n\
+        <html><body>\r\n\
+        <!--StartFragment--><b>copied</b><!--EndFragment-->\r\n\
+        </body></html>";
+
+    fn clipboard(text: &str) -> FullClipboardDto {
+        FullClipboardDto {
+            clipboard: entity::clipboard::Model {
+                content_hash: Some("0".repeat(64)),
+                ..Default::default()
+            },
+            text: Some(entity::clipboard_text::Model {
+                data: text.to_owned(),
+                ..Default::default()
+            }),
+            html: None,
+            image: None,
+            rtf: None,
+            files: Vec::new(),
+            search_match: None,
+        }
+    }
+
+    #[test]
+    fn payload_hash_ignores_line_endings() {
+        assert_eq!(
+            clipboard_payload_hash(&clipboard("a\r\nb"), None),
+            clipboard_payload_hash(&clipboard("a\nb"), None)
+        );
+        assert_ne!(
+            clipboard_payload_hash(&clipboard("a"), None),
+            clipboard_payload_hash(&clipboard("b"), None)
+        );
+    }
+
+    #[test]
+    fn keyed_payload_hash_differs_per_key() {
+        let clipboard = clipboard("hunter2");
+        let plain = clipboard_payload_hash(&clipboard, None);
+        let keyed = clipboard_payload_hash(&clipboard, Some(&[1; 32]));
+
+        assert_ne!(plain, keyed);
+        assert_ne!(keyed, clipboard_payload_hash(&clipboard, Some(&[2; 32])));
+        assert_eq!(keyed, clipboard_payload_hash(&clipboard, Some(&[1; 32])));
+    }
+
+    #[test]
+    fn content_hash_is_never_serialized() {
+        let json = serde_json::to_string(&clipboard("hunter2")).unwrap();
+        assert!(!json.contains("content_hash"));
+    }
+
+    #[test]
+    fn reads_the_source_url_from_the_cf_html_header() {
+        assert_eq!(
+            html_source_url(CF_HTML).as_deref(),
+            Some("https://example.com/page?q=1")
+        );
+    }
+
+    #[test]
+    fn markup_without_header_has_no_source_url() {
+        assert_eq!(html_source_url("<b>copied</b>"), None);
+        assert_eq!(html_source_url(""), None);
+    }
+
+    #[test]
+    fn ignores_source_url_lines_in_the_markup() {
+        let html = "<html><body>\nSourceURL:https://example.com\n</body></html>";
+        assert_eq!(html_source_url(html), None);
+    }
+
+    #[test]
+    fn ignores_an_empty_source_url() {
+        assert_eq!(
+            html_source_url("Version:0.9\r\nSourceURL: \r\n<html>"),
+            None
+        );
+    }
+}
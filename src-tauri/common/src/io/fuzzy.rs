@@ -0,0 +1,328 @@
+use super::search::fold_search_text;
+use chrono::NaiveDateTime;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// Match quality of an exact token hit, the best possible score.
+pub const QUALITY_EXACT: f32 = 1.0;
+/// Match quality when the term is a prefix of the token.
+pub const QUALITY_PREFIX: f32 = 0.85;
+/// Match quality of a fuzzy hit with one typo, each further typo costs `TYPO_PENALTY`.
+pub const QUALITY_FUZZY: f32 = 0.65;
+pub const TYPO_PENALTY: f32 = 0.2;
+
+/// Weights of the final rank: match quality dominates, recency and star break ties.
+const WEIGHT_QUALITY: f32 = 0.75;
+const WEIGHT_RECENCY: f32 = 0.2;
+const WEIGHT_STAR: f32 = 0.05;
+/// Age in days at which the recency part has dropped to half.
+const RECENCY_HALF_DAYS: f32 = 7.0;
+
+/// A token of the original text. `start`/`end` are UTF-16 offsets so the frontend
+/// can use them with `String.prototype.slice` directly.
+#[derive(Debug, Clone)]
+pub struct TokenSpan {
+    pub folded: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Tokenizes text like the FTS5 unicode61 tokenizer (runs of letters/digits), keeping
+/// the position of every token in the original string.
+pub fn token_spans(text: &str) -> Vec<TokenSpan> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+    let mut offset = 0;
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if current.is_empty() {
+                start = offset;
+            }
+            current.push(c);
+        } else if !current.is_empty() {
+            spans.push(TokenSpan {
+                folded: fold_search_text(&current),
+                start,
+                end: offset,
+            });
+            current.clear();
+        }
+        offset += c.len_utf16();
+    }
+
+    if !current.is_empty() {
+        spans.push(TokenSpan {
+            folded: fold_search_text(&current),
+            start,
+            end: offset,
+        });
+    }
+
+    spans
+}
+
+/// Number of typos tolerated for a term: none for short terms, where a single edit
+/// already matches almost anything, up to two for long ones.
+pub fn max_typos(term_len: usize) -> usize {
+    match term_len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent transpositions, so
+/// "recieve" is one edit from "receive"). Returns `None` once it exceeds `max`.
+pub fn edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let width = b.len() + 1;
+    let mut prev_prev = vec![0usize; width];
+    let mut prev: Vec<usize> = (0..width).collect();
+    let mut curr = vec![0usize; width];
+
+    for i in 1..=a.len() {
+        curr[0] = i;
+        let mut row_min = curr[0];
+
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                curr[j] = curr[j].min(prev_prev[j - 2] + 1);
+            }
+
+            row_min = row_min.min(curr[j]);
+        }
+
+        // Every later row is at least this row's minimum, stop early.
+        if row_min > max {
+            return None;
+        }
+
+        std::mem::swap(&mut prev_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    let distance = prev[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+/// Scores a folded search term against a folded token: exact, prefix, then typo
+/// tolerant against the whole token or a same-length prefix of it.
+pub fn term_quality(term: &str, token: &str) -> Option<f32> {
+    if token == term {
+        return Some(QUALITY_EXACT);
+    }
+
+    if token.starts_with(term) {
+        return Some(QUALITY_PREFIX);
+    }
+
+    let term_chars: Vec<char> = term.chars().collect();
+    let max = max_typos(term_chars.len());
+    if max == 0 {
+        return None;
+    }
+
+    let token_chars: Vec<char> = token.chars().collect();
+    let whole = edit_distance(&term_chars, &token_chars, max);
+    let prefix = if token_chars.len() > term_chars.len() {
+        edit_distance(&term_chars, &token_chars[..term_chars.len()], max)
+    } else {
+        None
+    };
+
+    match (whole, prefix) {
+        (Some(d), _) => Some(QUALITY_FUZZY - TYPO_PENALTY * (d - 1) as f32),
+        (None, Some(d)) => Some(QUALITY_FUZZY - TYPO_PENALTY * d as f32),
+        (None, None) => None,
+    }
+}
+
+/// Combines match quality (0..=1) with recency and star status into a sortable score.
+pub fn rank_score(quality: f32, created_at: NaiveDateTime, star: bool, now: NaiveDateTime) -> f32 {
+    let age_days = (now - created_at).num_minutes().max(0) as f32 / (60.0 * 24.0);
+    let recency = 1.0 / (1.0 + age_days / RECENCY_HALF_DAYS);
+
+    WEIGHT_QUALITY * quality + WEIGHT_RECENCY * recency + WEIGHT_STAR * f32::from(u8::from(star))
+}
+
+/// The `limit` best scored items pushed, for ranking many candidates without keeping or
+/// sorting all of them. Equal scores keep the order they were pushed in.
+pub struct TopRanked<T> {
+    limit: usize,
+    pushed: usize,
+    heap: BinaryHeap<Reverse<Ranked<T>>>,
+}
+
+impl<T> TopRanked<T> {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            pushed: 0,
+            heap: BinaryHeap::with_capacity(limit.min(1024) + 1),
+        }
+    }
+
+    pub fn push(&mut self, score: f32, item: T) {
+        self.heap.push(Reverse(Ranked {
+            score,
+            order: self.pushed,
+            item,
+        }));
+        self.pushed += 1;
+
+        // The root is the worst one kept
+        if self.heap.len() > self.limit {
+            self.heap.pop();
+        }
+    }
+
+    /// Best first.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ranked)| ranked.item)
+            .collect()
+    }
+}
+
+struct Ranked<T> {
+    score: f32,
+    order: usize,
+    item: T,
+}
+
+impl<T> Ord for Ranked<T> {
+    /// Higher score first, then earlier pushed.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+impl<T> PartialOrd for Ranked<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Ranked<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Ranked<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &str, b: &str, max: usize) -> Option<usize> {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        edit_distance(&a, &b, max)
+    }
+
+    fn spans(text: &str) -> Vec<(String, usize, usize)> {
+        token_spans(text)
+            .into_iter()
+            .map(|span| (span.folded, span.start, span.end))
+            .collect()
+    }
+
+    #[test]
+    fn edit_distance_counts_edits() {
+        assert_eq!(distance("kitten", "kitten", 2), Some(0));
+        assert_eq!(distance("kitten", "sitten", 2), Some(1));
+        assert_eq!(distance("kitten", "kittens", 2), Some(1));
+        assert_eq!(distance("kitten", "kiten", 2), Some(1));
+        assert_eq!(distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(distance("", "abc", 3), Some(3));
+    }
+
+    #[test]
+    fn edit_distance_counts_transposition_once() {
+        assert_eq!(distance("recieve", "receive", 1), Some(1));
+        assert_eq!(distance("ab", "ba", 1), Some(1));
+    }
+
+    #[test]
+    fn edit_distance_stops_past_max() {
+        assert_eq!(distance("kitten", "sitting", 2), None);
+        assert_eq!(distance("a", "abcd", 2), None);
+        assert_eq!(distance("abcd", "wxyz", 1), None);
+    }
+
+    #[test]
+    fn max_typos_grows_with_length() {
+        assert_eq!(max_typos(0), 0);
+        assert_eq!(max_typos(3), 0);
+        assert_eq!(max_typos(4), 1);
+        assert_eq!(max_typos(7), 1);
+        assert_eq!(max_typos(8), 2);
+        assert_eq!(max_typos(40), 2);
+    }
+
+    #[test]
+    fn term_quality_ranks_exact_prefix_fuzzy() {
+        assert_eq!(term_quality("hello", "hello"), Some(QUALITY_EXACT));
+        assert_eq!(term_quality("hel", "hello"), Some(QUALITY_PREFIX));
+        assert_eq!(term_quality("helo", "hello"), Some(QUALITY_FUZZY));
+        // A typo in the part that matches a longer token costs more
+        assert!(term_quality("hlelo", "hellos").is_some_and(|q| q < QUALITY_FUZZY));
+        assert_eq!(term_quality("cat", "car"), None);
+        assert_eq!(term_quality("hello", "world"), None);
+    }
+
+    #[test]
+    fn token_spans_split_on_non_alphanumerics() {
+        assert_eq!(
+            spans("Hello, wörld-42!"),
+            vec![
+                ("hello".to_string(), 0, 5),
+                ("world".to_string(), 7, 12),
+                ("42".to_string(), 13, 15),
+            ]
+        );
+        assert!(spans(" .,- ").is_empty());
+    }
+
+    #[test]
+    fn token_spans_use_utf16_offsets() {
+        // The emoji is two UTF-16 units, the CJK char one
+        assert_eq!(
+            spans("😀 ab 日本"),
+            vec![("ab".to_string(), 3, 5), ("日本".to_string(), 6, 8)]
+        );
+    }
+
+    #[test]
+    fn top_ranked_keeps_best_in_order() {
+        let mut ranked = TopRanked::new(3);
+        for (score, item) in [(0.2, "a"), (0.9, "b"), (0.5, "c"), (0.9, "d"), (0.1, "e")] {
+            ranked.push(score, item);
+        }
+
+        assert_eq!(ranked.into_sorted_vec(), vec!["b", "d", "c"]);
+    }
+
+    #[test]
+    fn top_ranked_ties_keep_push_order() {
+        let mut ranked = TopRanked::new(2);
+        for item in ["a", "b", "c"] {
+            ranked.push(1.0, item);
+        }
+
+        assert_eq!(ranked.into_sorted_vec(), vec!["a", "b"]);
+    }
+}
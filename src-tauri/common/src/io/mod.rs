@@ -1,3 +1,6 @@
+pub mod archive;
 pub mod clipboard;
+pub mod fuzzy;
 pub mod keyboard;
-pub mod language;
\ No newline at end of file
+pub mod language;
+pub mod search;
\ No newline at end of file
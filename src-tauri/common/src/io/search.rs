@@ -0,0 +1,247 @@
+use crate::types::orm_query::FullClipboardDto;
+use tl::{parse, Node, NodeHandle, Parser, ParserOptions};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// RTF destinations whose content is never part of the visible document text.
+const RTF_SKIP_DESTINATIONS: [&str; 16] = [
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "header",
+    "footer",
+    "generator",
+    "themedata",
+    "colorschememapping",
+    "latentstyles",
+    "datastore",
+    "listtable",
+    "listoverridetable",
+    "fldinst",
+];
+
+/// Builds the plain text stored in the `clipboard_fts` index for a clipboard:
+/// text, HTML body text, RTF plain text, file names and OCR text.
+pub fn clipboard_search_text(clipboard: &FullClipboardDto) -> String {
+    let mut parts: Vec<String> = Vec::new();
+
+    if let Some(text) = &clipboard.text {
+        parts.push(text.data.clone());
+    }
+
+    if let Some(html) = &clipboard.html {
+        parts.push(html_to_text(&html.data));
+    }
+
+    if let Some(rtf) = &clipboard.rtf {
+        parts.push(rtf_to_text(&rtf.data));
+    }
+
+    if let Some(image) = &clipboard.image {
+        parts.push(image.extension.clone());
+        if let Some(ocr_text) = &image.ocr_text {
+            parts.push(ocr_text.clone());
+        }
+    }
+
+    for file in &clipboard.files {
+        parts.push(match &file.extension {
+            Some(extension) => format!("{}.{}", file.name, extension),
+            None => file.name.clone(),
+        });
+        if let Some(mime_type) = &file.mime_type {
+            parts.push(mime_type.clone());
+        }
+    }
+
+    parts.retain(|p| !p.trim().is_empty());
+    parts.join("\n")
+}
+
+/// Case and diacritic folding matching the `unicode61 remove_diacritics 2` tokenizer
+/// of `clipboard_fts`, so in-memory search agrees with the index.
+pub fn fold_search_text(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Splits folded text into tokens the way unicode61 does: runs of letters/digits.
+pub fn search_tokens(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Extracts the visible text of an HTML fragment, skipping scripts and styles.
+pub fn html_to_text(html: &str) -> String {
+    let Ok(dom) = parse(html, ParserOptions::default()) else {
+        return html.to_string();
+    };
+    let parser = dom.parser();
+
+    let roots: Vec<NodeHandle> = match dom.query_selector("body").and_then(|mut b| b.next()) {
+        Some(body) => vec![body],
+        None => dom.children().to_vec(),
+    };
+
+    let mut out = String::new();
+    for handle in roots {
+        collect_html_text(handle, parser, &mut out);
+    }
+
+    decode_html_entities(out.trim())
+}
+
+fn collect_html_text(handle: NodeHandle, parser: &Parser, out: &mut String) {
+    match handle.get(parser) {
+        Some(Node::Tag(tag)) => {
+            let name = tag.name().as_utf8_str().to_lowercase();
+            if matches!(name.as_str(), "script" | "style" | "head" | "title") {
+                return;
+            }
+            for child in tag.children().top().iter() {
+                collect_html_text(*child, parser, out);
+            }
+        }
+        Some(Node::Raw(raw)) => {
+            let text = raw.as_utf8_str();
+            let text = text.trim();
+            if !text.is_empty() {
+                if !out.is_empty() {
+                    out.push(' ');
+                }
+                out.push_str(text);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn decode_html_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Strips RTF control words and groups, keeping only the document text.
+pub fn rtf_to_text(rtf: &str) -> String {
+    let chars: Vec<char> = rtf.chars().collect();
+    let mut out = String::new();
+    let mut skip_stack: Vec<bool> = Vec::new();
+    let mut skip = false;
+    // Number of fallback characters still to drop after a `\uN` escape.
+    let mut unicode_skip = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        match c {
+            '{' => skip_stack.push(skip),
+            '}' => skip = skip_stack.pop().unwrap_or(false),
+            '\r' | '\n' => {}
+            '\\' if i < chars.len() => {
+                let next = chars[i];
+                match next {
+                    '\\' | '{' | '}' => {
+                        i += 1;
+                        if unicode_skip > 0 {
+                            unicode_skip -= 1;
+                        } else if !skip {
+                            out.push(next);
+                        }
+                    }
+                    '\'' => {
+                        let hex: String = chars.iter().skip(i + 1).take(2).collect();
+                        i += 1 + hex.len();
+                        if unicode_skip > 0 {
+                            unicode_skip -= 1;
+                        } else if !skip {
+                            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                                out.push(char::from(byte));
+                            }
+                        }
+                    }
+                    '*' => {
+                        i += 1;
+                        skip = true;
+                    }
+                    '~' => {
+                        i += 1;
+                        if !skip {
+                            out.push(' ');
+                        }
+                    }
+                    c if c.is_ascii_alphabetic() => {
+                        let start = i;
+                        while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                            i += 1;
+                        }
+                        let word: String = chars[start..i].iter().collect();
+
+                        let param_start = i;
+                        if i < chars.len() && chars[i] == '-' {
+                            i += 1;
+                        }
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        let param: Option<i32> = chars[param_start..i]
+                            .iter()
+                            .collect::<String>()
+                            .parse()
+                            .ok();
+
+                        // A single space terminates the control word and is not text.
+                        if i < chars.len() && chars[i] == ' ' {
+                            i += 1;
+                        }
+
+                        if RTF_SKIP_DESTINATIONS.contains(&word.as_str()) {
+                            skip = true;
+                            continue;
+                        }
+                        if skip {
+                            continue;
+                        }
+
+                        match word.as_str() {
+                            "par" | "line" | "row" | "sect" | "page" => out.push('\n'),
+                            "tab" | "cell" => out.push('\t'),
+                            "u" => {
+                                if let Some(code) = param {
+                                    let code = if code < 0 { code + 65536 } else { code };
+                                    if let Some(ch) = char::from_u32(code as u32) {
+                                        out.push(ch);
+                                    }
+                                    unicode_skip = 1;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    _ => i += 1,
+                }
+            }
+            _ => {
+                if unicode_skip > 0 {
+                    unicode_skip -= 1;
+                } else if !skip {
+                    out.push(c);
+                }
+            }
+        }
+    }
+
+    out.trim().to_string()
+}
@@ -0,0 +1,103 @@
+use super::enums::ClipboardType;
+use crate::constants::{ARCHIVE_FORMAT, ARCHIVE_VERSION};
+use chrono::NaiveDateTime;
+use sea_orm::prelude::Uuid;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Which clipboards go into an export, every field narrows the selection.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ArchiveFilter {
+    pub star: Option<bool>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub r#type: Option<ClipboardType>,
+}
+
+/// `manifest.json` of an export archive.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: NaiveDateTime,
+    pub count: usize,
+    pub filter: ArchiveFilter,
+}
+
+impl ArchiveManifest {
+    pub fn new(app_version: &str, count: usize, filter: ArchiveFilter) -> Self {
+        ArchiveManifest {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            app_version: app_version.to_string(),
+            created_at: chrono::Local::now().naive_local(),
+            count,
+            filter,
+        }
+    }
+}
+
+/// Outcome of an import. Duplicates already exist locally with the same content,
+/// conflicts share the UUID but differ, the local content is kept for those. Failed
+/// ones are encrypted under a key the local one doesn't match and are not imported.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub duplicates: usize,
+    pub conflicts: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    Json(serde_json::Error),
+    NotAnArchive,
+    UnsupportedVersion(u32),
+    MissingBlob(Uuid),
+    PassphraseRequired,
+    InvalidPassphrase,
+    Corrupt,
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArchiveError::Io(e) => write!(f, "{}", e),
+            ArchiveError::Zip(e) => write!(f, "Invalid archive: {}", e),
+            ArchiveError::Json(e) => write!(f, "Invalid archive entry: {}", e),
+            ArchiveError::NotAnArchive => write!(f, "Not a clippy archive"),
+            ArchiveError::UnsupportedVersion(version) => write!(
+                f,
+                "Archive version {} is newer than supported ({}), update clippy",
+                version, ARCHIVE_VERSION
+            ),
+            ArchiveError::MissingBlob(id) => write!(f, "Archive is missing blob {}", id),
+            ArchiveError::PassphraseRequired => write!(f, "Archive is sealed, enter its passphrase"),
+            ArchiveError::InvalidPassphrase => write!(f, "Wrong passphrase for this archive"),
+            ArchiveError::Corrupt => write!(f, "Sealed archive is corrupt or cut off"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ArchiveError {
+    fn from(err: std::io::Error) -> Self {
+        ArchiveError::Io(err)
+    }
+}
+
+impl From<zip::result::ZipError> for ArchiveError {
+    fn from(err: zip::result::ZipError) -> Self {
+        ArchiveError::Zip(err)
+    }
+}
+
+impl From<serde_json::Error> for ArchiveError {
+    fn from(err: serde_json::Error) -> Self {
+        ArchiveError::Json(err)
+    }
+}
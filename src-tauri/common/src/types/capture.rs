@@ -0,0 +1,56 @@
+use super::enums::AppAction;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Capture rule for the app a clipboard was copied in, stored as JSON in
+/// `settings.app_rules`. Of the enabled rules that match, the strictest action decides.
+/// Once any rule allows, clipboards from apps no rule allows are skipped.
+#[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
+pub struct AppRule {
+    /// Process or app name, case insensitive and without `.exe`, like `keepassxc`.
+    pub app: String,
+    pub action: AppAction,
+    pub enabled: bool,
+}
+
+impl AppRule {
+    pub fn from_json_value(value: &Value) -> Vec<Self> {
+        match value {
+            Value::Array(arr) => serde_json::from_value(json!(arr)).unwrap_or_else(|_| vec![]),
+            _ => vec![],
+        }
+    }
+
+    /// What the rules do with a clipboard copied in `app`, `Allow` stores it as usual.
+    /// Without a known app only an allowlist applies, which skips it.
+    pub fn action_for(rules: &[Self], app: Option<&str>) -> AppAction {
+        let enabled = || rules.iter().filter(|rule| rule.enabled);
+
+        let matched = app.and_then(|app| {
+            enabled()
+                .filter(|rule| rule.matches(app))
+                .map(|rule| rule.action)
+                .min_by_key(|action| match action {
+                    AppAction::Skip => 0,
+                    AppAction::NoSync => 1,
+                    AppAction::Allow => 2,
+                })
+        });
+
+        match matched {
+            Some(action) => action,
+            None if enabled().any(|rule| rule.action == AppAction::Allow) => AppAction::Skip,
+            None => AppAction::Allow,
+        }
+    }
+
+    fn matches(&self, app: &str) -> bool {
+        let rule = app_name(&self.app);
+        !rule.is_empty() && rule == app_name(app)
+    }
+}
+
+fn app_name(app: &str) -> String {
+    let app = app.trim().to_lowercase();
+    app.strip_suffix(".exe").map(str::to_owned).unwrap_or(app)
+}
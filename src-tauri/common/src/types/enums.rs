@@ -1,3 +1,4 @@
+use crate::constants::{CONCEALED_CLIPBOARD_FORMATS, TRANSIENT_CLIPBOARD_FORMATS};
 use sea_orm::prelude::*;
 use sea_orm::{sea_query, EnumIter};
 use serde::{Deserialize, Serialize};
@@ -45,6 +46,144 @@ pub enum ClippyPosition {
 pub enum SyncProviderType {
     #[iden = "google_drive"]
     GoogleDrive,
+    #[iden = "webdav"]
+    WebDav,
+    #[iden = "local_folder"]
+    LocalFolder,
+    #[iden = "s3"]
+    S3,
+    #[iden = "lan"]
+    Lan,
+}
+
+/// Part of a clipboard that was changed on two devices, see `sync_conflict`.
+#[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncConflictField {
+    #[iden = "star"]
+    Star,
+    #[iden = "name"]
+    Name,
+    #[iden = "content"]
+    Content,
+    #[iden = "deleted"]
+    Deleted,
+}
+
+#[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncConflictWinner {
+    #[iden = "local"]
+    Local,
+    #[iden = "remote"]
+    Remote,
+}
+
+/// What copying a clipboard that is in the history already does.
+#[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateAction {
+    /// Moves the existing clipboard to the top
+    #[iden = "bump"]
+    Bump,
+    /// Leaves the history as it is
+    #[iden = "ignore"]
+    Ignore,
+}
+
+/// Built-in check of a `SensitiveRule`.
+#[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveDetector {
+    /// AWS access key ids
+    #[iden = "aws_key"]
+    AwsKey,
+    /// GitHub personal access, OAuth and app tokens
+    #[iden = "github_token"]
+    GithubToken,
+    #[iden = "jwt"]
+    Jwt,
+    /// PEM encoded private keys
+    #[iden = "private_key"]
+    PrivateKey,
+    /// Luhn valid card numbers
+    #[iden = "card_number"]
+    CardNumber,
+    /// Long random looking tokens
+    #[iden = "high_entropy"]
+    HighEntropy,
+}
+
+/// What a matching `SensitiveRule` does with a clipboard.
+#[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveAction {
+    /// Never stores it
+    #[iden = "drop"]
+    Drop,
+    /// Stores it on this device only
+    #[iden = "no_sync"]
+    NoSync,
+    /// Stores it on this device only and deletes it after `expire_after` seconds
+    #[iden = "expire"]
+    Expire,
+}
+
+/// Marker the copying app put on the clipboard, `settings.concealed_action` decides what
+/// happens to such a clipboard.
+#[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardHint {
+    /// A secret, like a password copied from a password manager
+    #[iden = "concealed"]
+    Concealed,
+    /// Only on the clipboard for a moment, like a one time code
+    #[iden = "transient"]
+    Transient,
+}
+
+impl ClipboardHint {
+    /// The hint among the formats on the clipboard, concealed wins over transient.
+    pub fn from_formats(formats: &[String]) -> Option<Self> {
+        let has_any = |markers: &[&str]| {
+            formats
+                .iter()
+                .any(|format| markers.contains(&format.as_str()))
+        };
+
+        if has_any(CONCEALED_CLIPBOARD_FORMATS) {
+            Some(Self::Concealed)
+        } else if has_any(TRANSIENT_CLIPBOARD_FORMATS) {
+            Some(Self::Transient)
+        } else {
+            None
+        }
+    }
+}
+
+/// What a matching `AppRule` does with a clipboard copied in its app.
+#[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AppAction {
+    /// Stores it as usual, and turns the rules into an allowlist
+    #[iden = "allow"]
+    Allow,
+    /// Never stores it
+    #[iden = "skip"]
+    Skip,
+    /// Stores it on this device only
+    #[iden = "no_sync"]
+    NoSync,
+}
+
+/// What a matching `SyncRule` does with a clipboard.
+#[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncRuleAction {
+    #[iden = "sync"]
+    Sync,
+    #[iden = "skip"]
+    Skip,
 }
 
 #[derive(Iden, EnumIter, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
@@ -268,3 +407,51 @@ impl ClipboardType {
         json!(types.iter().map(|t| t.to_string()).collect::<Vec<_>>())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(formats: &[&str]) -> Option<ClipboardHint> {
+        let formats: Vec<String> = formats.iter().copied().map(String::from).collect();
+        ClipboardHint::from_formats(&formats)
+    }
+
+    #[test]
+    fn clipboard_hint_from_markers() {
+        assert_eq!(
+            hint(&["public.utf8-plain-text", "org.nspasteboard.ConcealedType"]),
+            Some(ClipboardHint::Concealed)
+        );
+        assert_eq!(
+            hint(&["ExcludeClipboardContentFromMonitorProcessing"]),
+            Some(ClipboardHint::Concealed)
+        );
+        assert_eq!(
+            hint(&["text/plain", "x-kde-passwordManagerHint"]),
+            Some(ClipboardHint::Concealed)
+        );
+        assert_eq!(
+            hint(&["org.nspasteboard.TransientType"]),
+            Some(ClipboardHint::Transient)
+        );
+    }
+
+    #[test]
+    fn clipboard_hint_concealed_wins() {
+        assert_eq!(
+            hint(&[
+                "org.nspasteboard.TransientType",
+                "org.nspasteboard.ConcealedType"
+            ]),
+            Some(ClipboardHint::Concealed)
+        );
+    }
+
+    #[test]
+    fn clipboard_hint_needs_exact_marker() {
+        assert_eq!(hint(&[]), None);
+        assert_eq!(hint(&["text/plain", "text/html"]), None);
+        assert_eq!(hint(&["org.nspasteboard.concealedtype"]), None);
+    }
+}
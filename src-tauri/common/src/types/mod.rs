@@ -1,6 +1,9 @@
+pub mod archive;
+pub mod capture;
 pub mod enums;
 pub mod hotkey;
 pub mod orm_query;
+pub mod sensitive;
 pub mod types;
 pub mod sync;
 pub mod cipher;
\ No newline at end of file
@@ -12,6 +12,34 @@ pub struct FullClipboardDto {
     pub image: Option<clipboard_image::Model>,
     pub rtf: Option<clipboard_rtf::Model>,
     pub files: Vec<clipboard_file::Model>,
+    /// Score and highlight ranges, only set on results of a fuzzy search.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_match: Option<SearchMatch>,
+}
+
+/// Part of a clipboard a `MatchRange` points into.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    Text,
+    Ocr,
+    File,
+}
+
+/// Matched characters as UTF-16 offsets into `text.data`, `image.ocr_text` or
+/// `files[index].name` (`index` is only meaningful for files).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MatchRange {
+    pub field: MatchField,
+    pub index: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchMatch {
+    pub score: f32,
+    pub ranges: Vec<MatchRange>,
 }
 
 #[derive(Debug, Clone)]
@@ -1,11 +1,13 @@
+use super::enums::{ClipboardType, SyncRuleAction};
 use super::orm_query::FullClipboardDto;
 use chrono::NaiveDateTime;
 use google_drive3::{hyper_rustls, hyper_util, DriveHub};
 use sea_orm::prelude::Uuid;
-use serde_json::Value;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Clippy {
     pub id: Uuid,
     pub provider_id: String,
@@ -13,24 +15,164 @@ pub struct Clippy {
     pub encrypted: bool,
     pub created_at: NaiveDateTime,
     pub deleted_at: Option<NaiveDateTime>,
+    /// Hash of the uploaded content, `None` for files from before it was tracked.
+    pub content_hash: Option<String>,
+}
+
+/// Result of reading a provider's change feed, `cursor` continues after it.
+#[derive(Clone, Debug)]
+pub struct RemoteChanges {
+    pub cursor: String,
+    pub changed: bool,
+}
+
+/// Deleted clipboards shared by every device of a provider, stored as
+/// `TOMBSTONE_DOCUMENT_NAME`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TombstoneDocument {
+    #[serde(default)]
+    pub tombstones: HashMap<Uuid, Tombstone>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub deleted_at: NaiveDateTime,
+    /// Device that deleted the clipboard.
+    pub device_id: String,
+    /// Devices that applied the deletion, the tombstone expires once all have.
+    #[serde(default)]
+    pub acked_by: HashSet<String>,
+}
+
+/// Entry of the provider's device manifest, `DEVICE_MANIFEST_NAME`. Every device
+/// keeps its own entry up to date while it syncs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncDevice {
+    /// `Config::device_id` of the device.
+    pub id: String,
+    pub name: String,
+    pub os: String,
+    pub app_version: String,
+    pub last_seen: NaiveDateTime,
+    /// Revoked devices turn sync off the next time they try, and tombstones no longer
+    /// wait for them. Never cleared once set.
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+/// Filter on what gets synced, stored as JSON in `settings.sync_rules`. Every condition
+/// that is set has to hold for the rule to match, the first enabled rule that matches
+/// decides and clipboards no rule matches are synced.
+#[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Clone)]
+pub struct SyncRule {
+    /// Clipboards that contain this type.
+    pub clipboard_type: Option<ClipboardType>,
+    /// Clipboards of at least this many bytes, all contents together.
+    pub min_size: Option<u64>,
+    /// Clipboards smaller than this many bytes.
+    pub max_size: Option<u64>,
+    pub star: Option<bool>,
+    /// Case insensitive regex on the name and the text contents, never matches the
+    /// contents of encrypted clipboards.
+    pub pattern: Option<String>,
+    pub action: SyncRuleAction,
+    pub enabled: bool,
+}
+
+impl SyncRule {
+    pub fn from_json_value(value: &Value) -> Vec<Self> {
+        match value {
+            Value::Array(arr) => serde_json::from_value(json!(arr)).unwrap_or_else(|_| vec![]),
+            _ => vec![],
+        }
+    }
+
+    /// Whether `clipboard` syncs under `rules`, never for the ones kept on this device.
+    pub fn allows(rules: &[Self], clipboard: &FullClipboardDto) -> bool {
+        if clipboard.clipboard.no_sync {
+            return false;
+        }
+
+        rules
+            .iter()
+            .find(|rule| rule.enabled && rule.matches(clipboard))
+            .is_none_or(|rule| rule.action == SyncRuleAction::Sync)
+    }
+
+    fn matches(&self, clipboard: &FullClipboardDto) -> bool {
+        if let Some(clipboard_type) = &self.clipboard_type {
+            let types = ClipboardType::from_json_value(&clipboard.clipboard.types);
+            if !types.is_some_and(|types| types.contains(clipboard_type)) {
+                return false;
+            }
+        }
+
+        if self
+            .star
+            .is_some_and(|star| star != clipboard.clipboard.star)
+        {
+            return false;
+        }
+
+        if self.min_size.is_some() || self.max_size.is_some() {
+            let size = clipboard_size(clipboard);
+            if self.min_size.is_some_and(|min_size| size < min_size)
+                || self.max_size.is_some_and(|max_size| size >= max_size)
+            {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = self
+            .pattern
+            .as_deref()
+            .filter(|pattern| !pattern.is_empty())
+        {
+            let Ok(regex) = regex::RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+            else {
+                return false;
+            };
+
+            let mut texts = vec![clipboard.clipboard.name.as_deref()];
+            if !clipboard.clipboard.encrypted {
+                texts.push(clipboard.text.as_ref().map(|text| text.data.as_str()));
+                texts.push(clipboard.html.as_ref().map(|html| html.data.as_str()));
+                texts.push(clipboard.rtf.as_ref().map(|rtf| rtf.data.as_str()));
+            }
+
+            if !texts.into_iter().flatten().any(|text| regex.is_match(text)) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Bytes of all contents of `clipboard`.
+fn clipboard_size(clipboard: &FullClipboardDto) -> u64 {
+    let text = clipboard.text.as_ref().map_or(0, |text| text.data.len());
+    let html = clipboard.html.as_ref().map_or(0, |html| html.data.len());
+    let rtf = clipboard.rtf.as_ref().map_or(0, |rtf| rtf.data.len());
+    let image = clipboard.image.as_ref().map_or(0, |image| image.data.len());
+    let files: usize = clipboard.files.iter().map(|file| file.data.len()).sum();
+
+    (text + html + rtf + image + files) as u64
+}
+
+/// Device manifest as shown in the settings, `device_id` is this device.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SyncDevices {
+    pub device_id: String,
+    pub devices: Vec<SyncDevice>,
 }
 
 #[async_trait::async_trait]
 pub trait SyncProvider: Send + Sync {
     async fn fetch_all_clipboards(&self) -> Result<Vec<Clippy>, Box<dyn std::error::Error>>;
 
-    async fn compare_and_fetch_new_clipboards(
-        &self,
-        local_clipboards: &HashMap<Uuid, (bool, NaiveDateTime)>,
-        remote_clipboards: &Vec<Clippy>,
-    ) -> Result<Vec<FullClipboardDto>, Box<dyn std::error::Error>>;
-
-    async fn upload_new_clipboards(
-        &self,
-        new_local_clipboards: &[FullClipboardDto],
-        remote_clipboards: &Vec<Clippy>,
-    ) -> Result<Vec<Clippy>, Box<dyn std::error::Error>>;
-
     async fn mark_for_deletion(&self, clippy: &Clippy);
 
     async fn delete_clipboard(&self, clippy: &Clippy);
@@ -66,9 +208,36 @@ pub trait SyncProvider: Send + Sync {
     async fn get_settings(&self) -> Result<HashMap<String, Value>, Box<dyn std::error::Error>>;
 
     async fn is_authenticated(&self) -> bool;
+
+    /// Shared JSON document next to the clipboards, `None` if it doesn't exist yet.
+    async fn read_document(&self, name: &str)
+        -> Result<Option<String>, Box<dyn std::error::Error>>;
+
+    /// Creates or replaces the document `name`.
+    async fn write_document(
+        &self,
+        name: &str,
+        content: String,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Changes since `cursor` from the provider's change feed, a fresh cursor when it's
+    /// `None`. Providers without a feed return `Ok(None)` and get polled instead.
+    async fn fetch_changes(
+        &self,
+        _cursor: Option<&str>,
+    ) -> Result<Option<RemoteChanges>, Box<dyn std::error::Error>> {
+        Ok(None)
+    }
 }
 
 pub struct GoogleDriveProvider {
     pub hub:
         DriveHub<hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>>,
 }
+
+/// A LAN sync host found through mDNS, `address` is `ip:port`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LanPeer {
+    pub name: String,
+    pub address: String,
+}
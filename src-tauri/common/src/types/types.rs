@@ -5,13 +5,22 @@ use serde_json::{json, Value as JsonValue};
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub db: String,
+    /// Identifies this installation to the other devices of a sync provider, generated
+    /// on first run. Empty in configs written before it existed.
+    #[serde(default)]
+    pub device_id: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Progress {
     pub label: String,
     pub total: usize,
     pub current: usize,
+    /// Running counts of an archive import, see `ImportSummary`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflicts: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -78,6 +87,12 @@ impl From<std::io::Error> for CommandError {
     }
 }
 
+impl From<super::archive::ArchiveError> for CommandError {
+    fn from(err: super::archive::ArchiveError) -> Self {
+        CommandError::Error(err.to_string())
+    }
+}
+
 impl From<Box<dyn std::error::Error>> for CommandError {
     fn from(err: Box<dyn std::error::Error>) -> Self {
         CommandError::Error(err.to_string())
@@ -22,6 +22,29 @@ pub struct Model {
     pub encrypted: bool,
     pub created_at: DateTime,
     pub name: Option<String>,
+    /// Missing in clipboards synced or exported before it existed
+    #[serde(default)]
+    pub updated_at: DateTime,
+    #[serde(default)]
+    pub origin_device: Option<String>,
+    /// `clipboard_payload_hash` of the plain content, keyed for encrypted clipboards. Only
+    /// used locally to find duplicates, never serialized so it isn't synced or exported
+    #[serde(skip)]
+    pub content_hash: Option<String>,
+    /// Flagged sensitive, stays on this device
+    #[serde(default)]
+    pub no_sync: bool,
+    /// Sensitive clipboards deleted at this point
+    #[serde(default)]
+    pub expires_at: Option<DateTime>,
+    /// App the clipboard was copied in, when it could be detected
+    #[serde(default)]
+    pub source_app: Option<String>,
+    #[serde(default)]
+    pub source_window_title: Option<String>,
+    /// Page an HTML clipboard was copied from
+    #[serde(default)]
+    pub source_url: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -32,6 +55,14 @@ pub enum Column {
     Encrypted,
     CreatedAt,
     Name,
+    UpdatedAt,
+    OriginDevice,
+    ContentHash,
+    NoSync,
+    ExpiresAt,
+    SourceApp,
+    SourceWindowTitle,
+    SourceUrl,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -52,6 +83,7 @@ pub enum Relation {
     ClipboardHtml,
     ClipboardImage,
     ClipboardRtf,
+    ClipboardSearch,
     ClipboardText,
 }
 
@@ -65,6 +97,14 @@ impl ColumnTrait for Column {
             Self::Encrypted => ColumnType::Boolean.def(),
             Self::CreatedAt => ColumnType::DateTime.def(),
             Self::Name => ColumnType::String(StringLen::None).def().null(),
+            Self::UpdatedAt => ColumnType::DateTime.def(),
+            Self::OriginDevice => ColumnType::String(StringLen::None).def().null(),
+            Self::ContentHash => ColumnType::String(StringLen::None).def().null(),
+            Self::NoSync => ColumnType::Boolean.def(),
+            Self::ExpiresAt => ColumnType::DateTime.def().null(),
+            Self::SourceApp => ColumnType::String(StringLen::None).def().null(),
+            Self::SourceWindowTitle => ColumnType::String(StringLen::None).def().null(),
+            Self::SourceUrl => ColumnType::String(StringLen::None).def().null(),
         }
     }
 }
@@ -76,6 +116,7 @@ impl RelationTrait for Relation {
             Self::ClipboardHtml => Entity::has_one(super::clipboard_html::Entity).into(),
             Self::ClipboardImage => Entity::has_one(super::clipboard_image::Entity).into(),
             Self::ClipboardRtf => Entity::has_one(super::clipboard_rtf::Entity).into(),
+            Self::ClipboardSearch => Entity::has_one(super::clipboard_search::Entity).into(),
             Self::ClipboardText => Entity::has_one(super::clipboard_text::Entity).into(),
         }
     }
@@ -105,6 +146,12 @@ impl Related<super::clipboard_rtf::Entity> for Entity {
     }
 }
 
+impl Related<super::clipboard_search::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ClipboardSearch.def()
+    }
+}
+
 impl Related<super::clipboard_text::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ClipboardText.def()
@@ -25,6 +25,10 @@ pub struct Model {
     pub created_date: DateTime,
     pub modified_date: DateTime,
     pub data: Vec<u8>,
+    /// Key of the `blob` row that holds `data` in the db, the column itself stays empty.
+    /// Storage detail only, loaded models carry the bytes in `data` instead.
+    #[serde(skip)]
+    pub blob_hash: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -38,6 +42,7 @@ pub enum Column {
     CreatedDate,
     ModifiedDate,
     Data,
+    BlobHash,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -70,6 +75,7 @@ impl ColumnTrait for Column {
             Self::CreatedDate => ColumnType::DateTime.def(),
             Self::ModifiedDate => ColumnType::DateTime.def(),
             Self::Data => ColumnType::Blob.def(),
+            Self::BlobHash => ColumnType::String(StringLen::None).def().null(),
         }
     }
 }
@@ -25,6 +25,10 @@ pub struct Model {
     pub width: i32,
     pub height: i32,
     pub ocr_text: Option<String>,
+    /// Key of the `blob` row that holds `data` in the db, the column itself stays empty.
+    /// Storage detail only, loaded models carry the bytes in `data` instead.
+    #[serde(skip)]
+    pub blob_hash: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -38,6 +42,7 @@ pub enum Column {
     Width,
     Height,
     OcrText,
+    BlobHash,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -70,6 +75,7 @@ impl ColumnTrait for Column {
             Self::Width => ColumnType::Integer.def(),
             Self::Height => ColumnType::Integer.def(),
             Self::OcrText => ColumnType::Text.def().null(),
+            Self::BlobHash => ColumnType::String(StringLen::None).def().null(),
         }
     }
 }
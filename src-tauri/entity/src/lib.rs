@@ -2,11 +2,16 @@
 
 pub mod prelude;
 
+pub mod blob;
 pub mod clipboard;
 pub mod clipboard_file;
 pub mod clipboard_html;
 pub mod clipboard_image;
 pub mod clipboard_rtf;
+pub mod clipboard_search;
 pub mod clipboard_text;
 pub mod hotkey;
 pub mod settings;
+pub mod sync_conflict;
+pub mod sync_state;
+pub mod tombstone;
@@ -1,10 +1,15 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.4
 
+pub use super::blob::Entity as Blob;
 pub use super::clipboard::Entity as Clipboard;
 pub use super::clipboard_file::Entity as ClipboardFile;
 pub use super::clipboard_html::Entity as ClipboardHtml;
 pub use super::clipboard_image::Entity as ClipboardImage;
 pub use super::clipboard_rtf::Entity as ClipboardRtf;
+pub use super::clipboard_search::Entity as ClipboardSearch;
 pub use super::clipboard_text::Entity as ClipboardText;
 pub use super::hotkey::Entity as Hotkey;
 pub use super::settings::Entity as Settings;
+pub use super::sync_conflict::Entity as SyncConflict;
+pub use super::sync_state::Entity as SyncState;
+pub use super::tombstone::Entity as Tombstone;
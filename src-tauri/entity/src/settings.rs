@@ -39,6 +39,29 @@ pub struct Model {
     pub glass: bool,
     pub glass_opacity: f32,
     pub glass_grain: f32,
+    pub api_enabled: bool,
+    pub api_port: i32,
+    pub api_token: String,
+    pub webdav_url: String,
+    pub webdav_username: String,
+    pub local_sync_path: String,
+    pub s3_endpoint: String,
+    pub s3_region: String,
+    pub s3_bucket: String,
+    pub s3_prefix: String,
+    pub s3_access_key_id: String,
+    pub lan_port: i32,
+    pub lan_peer: String,
+    pub tombstone_ttl_days: i32,
+    pub sync_rules: Json,
+    pub external_blob_store: bool,
+    pub duplicate_action: String,
+    pub retention_days: i32,
+    pub retention_max_clips: i32,
+    pub retention_image_days: i32,
+    pub sensitive_rules: Json,
+    pub concealed_action: String,
+    pub app_rules: Json,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -66,6 +89,29 @@ pub enum Column {
     Glass,
     GlassOpacity,
     GlassGrain,
+    ApiEnabled,
+    ApiPort,
+    ApiToken,
+    WebdavUrl,
+    WebdavUsername,
+    LocalSyncPath,
+    S3Endpoint,
+    S3Region,
+    S3Bucket,
+    S3Prefix,
+    S3AccessKeyId,
+    LanPort,
+    LanPeer,
+    TombstoneTtlDays,
+    SyncRules,
+    ExternalBlobStore,
+    DuplicateAction,
+    RetentionDays,
+    RetentionMaxClips,
+    RetentionImageDays,
+    SensitiveRules,
+    ConcealedAction,
+    AppRules,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -110,6 +156,29 @@ impl ColumnTrait for Column {
             Self::Glass => ColumnType::Boolean.def(),
             Self::GlassOpacity => ColumnType::Float.def(),
             Self::GlassGrain => ColumnType::Float.def(),
+            Self::ApiEnabled => ColumnType::Boolean.def(),
+            Self::ApiPort => ColumnType::Integer.def(),
+            Self::ApiToken => ColumnType::String(StringLen::None).def(),
+            Self::WebdavUrl => ColumnType::String(StringLen::None).def(),
+            Self::WebdavUsername => ColumnType::String(StringLen::None).def(),
+            Self::LocalSyncPath => ColumnType::String(StringLen::None).def(),
+            Self::S3Endpoint => ColumnType::String(StringLen::None).def(),
+            Self::S3Region => ColumnType::String(StringLen::None).def(),
+            Self::S3Bucket => ColumnType::String(StringLen::None).def(),
+            Self::S3Prefix => ColumnType::String(StringLen::None).def(),
+            Self::S3AccessKeyId => ColumnType::String(StringLen::None).def(),
+            Self::LanPort => ColumnType::Integer.def(),
+            Self::LanPeer => ColumnType::String(StringLen::None).def(),
+            Self::TombstoneTtlDays => ColumnType::Integer.def(),
+            Self::SyncRules => ColumnType::Json.def(),
+            Self::ExternalBlobStore => ColumnType::Boolean.def(),
+            Self::DuplicateAction => ColumnType::String(StringLen::None).def(),
+            Self::RetentionDays => ColumnType::Integer.def(),
+            Self::RetentionMaxClips => ColumnType::Integer.def(),
+            Self::RetentionImageDays => ColumnType::Integer.def(),
+            Self::SensitiveRules => ColumnType::Json.def(),
+            Self::ConcealedAction => ColumnType::String(StringLen::None).def(),
+            Self::AppRules => ColumnType::Json.def(),
         }
     }
 }
@@ -0,0 +1,56 @@
+use common::types::enums::SyncProviderType;
+use sea_orm::{ConnectionTrait, Iterable, Statement};
+use sea_orm_migration::prelude::*;
+
+const SYNC_PROVIDER_CHECK: &str = "CHECK (\"sync_provider\" IN (";
+
+/// SQLite can't alter a CHECK constraint, so the settings table is rebuilt with the
+/// `sync_provider` check listing every `SyncProviderType`. Migrations that add a
+/// provider call this, it does nothing when the check is up to date already.
+pub async fn rebuild_sync_provider_check(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+
+    let sql: String = db
+        .query_one(Statement::from_string(
+            manager.get_database_backend(),
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'settings'",
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Custom("settings table not found".to_string()))?
+        .try_get("", "sql")?;
+
+    let start = sql
+        .find(SYNC_PROVIDER_CHECK)
+        .ok_or_else(|| DbErr::Custom("sync_provider check not found".to_string()))?
+        + SYNC_PROVIDER_CHECK.len();
+    let end = start
+        + sql[start..]
+            .find(')')
+            .ok_or_else(|| DbErr::Custom("sync_provider check not closed".to_string()))?;
+
+    let providers = SyncProviderType::iter()
+        .map(|provider| format!("'{}'", provider.to_string()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if sql[start..end] == providers {
+        return Ok(());
+    }
+
+    let create = format!("{}{}{}", &sql[..start], providers, &sql[end..]).replacen(
+        "\"settings\"",
+        "\"settings_new\"",
+        1,
+    );
+
+    for statement in [
+        create.as_str(),
+        "INSERT INTO settings_new SELECT * FROM settings",
+        "DROP TABLE settings",
+        "ALTER TABLE settings_new RENAME TO settings",
+    ] {
+        db.execute_unprepared(statement).await?;
+    }
+
+    Ok(())
+}
@@ -16,6 +16,29 @@ mod m000013_fix_encryption_typo;
 mod m000014_add_super_to_hotkey;
 mod m000015_add_theme_and_glass;
 mod m000016_add_glass_opacity_and_grain;
+mod m000017_create_clipboard_search;
+mod m000018_add_api_settings;
+mod m000019_add_webdav_sync;
+mod m000020_add_local_folder_sync;
+mod m000021_add_s3_sync;
+mod m000022_add_lan_sync;
+mod m000023_create_sync_state;
+mod m000024_add_updated_at_to_clipboard;
+mod m000025_create_sync_conflict;
+mod m000026_create_tombstone;
+mod m000027_add_origin_device_to_clipboard;
+mod m000028_add_sync_rules;
+mod m000029_create_blob;
+mod m000030_add_external_blob_store;
+mod m000031_add_content_hash;
+mod m000032_add_retention;
+mod m000033_add_sensitive_rules;
+mod m000034_add_concealed_action;
+mod m000035_add_app_rules;
+mod m000036_add_clipboard_source;
+mod m000037_rehash_encrypted_content;
+
+mod helpers;
 
 pub struct Migrator;
 
@@ -39,6 +62,27 @@ impl MigratorTrait for Migrator {
             Box::new(m000014_add_super_to_hotkey::Migration),
             Box::new(m000015_add_theme_and_glass::Migration),
             Box::new(m000016_add_glass_opacity_and_grain::Migration),
+            Box::new(m000017_create_clipboard_search::Migration),
+            Box::new(m000018_add_api_settings::Migration),
+            Box::new(m000019_add_webdav_sync::Migration),
+            Box::new(m000020_add_local_folder_sync::Migration),
+            Box::new(m000021_add_s3_sync::Migration),
+            Box::new(m000022_add_lan_sync::Migration),
+            Box::new(m000023_create_sync_state::Migration),
+            Box::new(m000024_add_updated_at_to_clipboard::Migration),
+            Box::new(m000025_create_sync_conflict::Migration),
+            Box::new(m000026_create_tombstone::Migration),
+            Box::new(m000027_add_origin_device_to_clipboard::Migration),
+            Box::new(m000028_add_sync_rules::Migration),
+            Box::new(m000029_create_blob::Migration),
+            Box::new(m000030_add_external_blob_store::Migration),
+            Box::new(m000031_add_content_hash::Migration),
+            Box::new(m000032_add_retention::Migration),
+            Box::new(m000033_add_sensitive_rules::Migration),
+            Box::new(m000034_add_concealed_action::Migration),
+            Box::new(m000035_add_app_rules::Migration),
+            Box::new(m000036_add_clipboard_source::Migration),
+            Box::new(m000037_rehash_encrypted_content::Migration),
         ]
     }
 }
@@ -0,0 +1,367 @@
+use crate::m000001_create_clipboard::Clipboard;
+use sea_orm_migration::sea_orm::prelude::Uuid;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement, Value};
+use sea_orm_migration::{
+    prelude::*,
+    schema::{pk_auto, text, uuid},
+};
+use std::collections::HashMap;
+use tl::{parse, Node, NodeHandle, Parser, ParserOptions};
+
+#[derive(Iden)]
+enum ClipboardSearch {
+    Table,
+    Id,
+    ClipboardId,
+    Content,
+}
+
+// `clipboard_search` holds the derived plain text per clipboard (html/rtf stripped),
+// `clipboard_fts` is an external-content FTS5 index over it. The triggers keep the
+// index in step with the content table, and the cascading foreign key removes the
+// content row whenever a clipboard is deleted or its id is bumped.
+const CREATE_FTS: [&str; 4] = [
+    "CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_fts USING fts5(\
+        content, \
+        content = 'clipboard_search', \
+        content_rowid = 'id', \
+        tokenize = 'unicode61 remove_diacritics 2', \
+        prefix = '2 3'\
+    )",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_search_ai AFTER INSERT ON clipboard_search BEGIN \
+        INSERT INTO clipboard_fts(rowid, content) VALUES (new.id, new.content); \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_search_ad AFTER DELETE ON clipboard_search BEGIN \
+        INSERT INTO clipboard_fts(clipboard_fts, rowid, content) VALUES ('delete', old.id, old.content); \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_search_au AFTER UPDATE OF content ON clipboard_search BEGIN \
+        INSERT INTO clipboard_fts(clipboard_fts, rowid, content) VALUES ('delete', old.id, old.content); \
+        INSERT INTO clipboard_fts(rowid, content) VALUES (new.id, new.content); \
+    END",
+];
+
+const DROP_FTS: [&str; 4] = [
+    "DROP TRIGGER IF EXISTS clipboard_search_au",
+    "DROP TRIGGER IF EXISTS clipboard_search_ad",
+    "DROP TRIGGER IF EXISTS clipboard_search_ai",
+    "DROP TABLE IF EXISTS clipboard_fts",
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ClipboardSearch::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ClipboardSearch::Id))
+                    .col(uuid(ClipboardSearch::ClipboardId).unique_key())
+                    .col(text(ClipboardSearch::Content))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-clipboard-search")
+                            .from(ClipboardSearch::Table, ClipboardSearch::ClipboardId)
+                            .to(Clipboard::Table, Clipboard::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        for sql in CREATE_FTS {
+            db.execute(Statement::from_string(backend, sql.to_owned()))
+                .await?;
+        }
+
+        // Backfill the index from existing history. Raw SQL and the frozen text
+        // extraction below keep this independent of the current entities and indexing.
+        // Encrypted clipboards are never indexed.
+        let rows = db
+            .query_all(Statement::from_string(
+                backend,
+                "SELECT c.id, t.data AS text, h.data AS html, r.data AS rtf, \
+                    i.extension AS image_extension, i.ocr_text \
+                 FROM clipboard c \
+                 LEFT JOIN clipboard_text t ON t.clipboard_id = c.id \
+                 LEFT JOIN clipboard_html h ON h.clipboard_id = c.id \
+                 LEFT JOIN clipboard_rtf r ON r.clipboard_id = c.id \
+                 LEFT JOIN clipboard_image i ON i.clipboard_id = c.id \
+                 WHERE c.encrypted = 0"
+                    .to_owned(),
+            ))
+            .await?;
+
+        let file_rows = db
+            .query_all(Statement::from_string(
+                backend,
+                "SELECT f.clipboard_id, f.name, f.extension, f.mime_type \
+                 FROM clipboard_file f \
+                 INNER JOIN clipboard c ON c.id = f.clipboard_id \
+                 WHERE c.encrypted = 0"
+                    .to_owned(),
+            ))
+            .await?;
+
+        let mut files_by_clip: HashMap<Uuid, Vec<String>> = HashMap::new();
+        for row in file_rows {
+            let clipboard_id: Uuid = row.try_get("", "clipboard_id")?;
+            let name: String = row.try_get("", "name")?;
+            let extension: Option<String> = row.try_get("", "extension")?;
+            let mime_type: Option<String> = row.try_get("", "mime_type")?;
+
+            let parts = files_by_clip.entry(clipboard_id).or_default();
+            parts.push(match extension {
+                Some(extension) => format!("{}.{}", name, extension),
+                None => name,
+            });
+            parts.extend(mime_type);
+        }
+
+        for row in rows {
+            let id: Uuid = row.try_get("", "id")?;
+            let text: Option<String> = row.try_get("", "text")?;
+            let html: Option<String> = row.try_get("", "html")?;
+            let rtf: Option<String> = row.try_get("", "rtf")?;
+            let image_extension: Option<String> = row.try_get("", "image_extension")?;
+            let ocr_text: Option<String> = row.try_get("", "ocr_text")?;
+
+            let mut parts: Vec<String> = Vec::new();
+            parts.extend(text);
+            parts.extend(html.map(|html| html_to_text(&html)));
+            parts.extend(rtf.map(|rtf| rtf_to_text(&rtf)));
+            if let Some(image_extension) = image_extension {
+                parts.push(image_extension);
+                parts.extend(ocr_text);
+            }
+            parts.extend(files_by_clip.remove(&id).unwrap_or_default());
+
+            parts.retain(|p| !p.trim().is_empty());
+            let content = parts.join("\n");
+            if content.is_empty() {
+                continue;
+            }
+
+            db.execute(Statement::from_sql_and_values(
+                backend,
+                "INSERT INTO clipboard_search (clipboard_id, content) VALUES (?, ?)",
+                [Value::from(id), Value::from(content)],
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        for sql in DROP_FTS {
+            db.execute(Statement::from_string(backend, sql.to_owned()))
+                .await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(ClipboardSearch::Table).to_owned())
+            .await
+    }
+}
+
+// Text extraction as `common::io::search` had it when this migration was written.
+
+/// RTF destinations whose content is never part of the visible document text.
+const RTF_SKIP_DESTINATIONS: [&str; 16] = [
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "header",
+    "footer",
+    "generator",
+    "themedata",
+    "colorschememapping",
+    "latentstyles",
+    "datastore",
+    "listtable",
+    "listoverridetable",
+    "fldinst",
+];
+
+/// Extracts the visible text of an HTML fragment, skipping scripts and styles.
+fn html_to_text(html: &str) -> String {
+    let Ok(dom) = parse(html, ParserOptions::default()) else {
+        return html.to_string();
+    };
+    let parser = dom.parser();
+
+    let roots: Vec<NodeHandle> = match dom.query_selector("body").and_then(|mut b| b.next()) {
+        Some(body) => vec![body],
+        None => dom.children().to_vec(),
+    };
+
+    let mut out = String::new();
+    for handle in roots {
+        collect_html_text(handle, parser, &mut out);
+    }
+
+    decode_html_entities(out.trim())
+}
+
+fn collect_html_text(handle: NodeHandle, parser: &Parser, out: &mut String) {
+    match handle.get(parser) {
+        Some(Node::Tag(tag)) => {
+            let name = tag.name().as_utf8_str().to_lowercase();
+            if matches!(name.as_str(), "script" | "style" | "head" | "title") {
+                return;
+            }
+            for child in tag.children().top().iter() {
+                collect_html_text(*child, parser, out);
+            }
+        }
+        Some(Node::Raw(raw)) => {
+            let text = raw.as_utf8_str();
+            let text = text.trim();
+            if !text.is_empty() {
+                if !out.is_empty() {
+                    out.push(' ');
+                }
+                out.push_str(text);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn decode_html_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Strips RTF control words and groups, keeping only the document text.
+fn rtf_to_text(rtf: &str) -> String {
+    let chars: Vec<char> = rtf.chars().collect();
+    let mut out = String::new();
+    let mut skip_stack: Vec<bool> = Vec::new();
+    let mut skip = false;
+    // Number of fallback characters still to drop after a `\uN` escape.
+    let mut unicode_skip = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        match c {
+            '{' => skip_stack.push(skip),
+            '}' => skip = skip_stack.pop().unwrap_or(false),
+            '\r' | '\n' => {}
+            '\\' if i < chars.len() => {
+                let next = chars[i];
+                match next {
+                    '\\' | '{' | '}' => {
+                        i += 1;
+                        if unicode_skip > 0 {
+                            unicode_skip -= 1;
+                        } else if !skip {
+                            out.push(next);
+                        }
+                    }
+                    '\'' => {
+                        let hex: String = chars.iter().skip(i + 1).take(2).collect();
+                        i += 1 + hex.len();
+                        if unicode_skip > 0 {
+                            unicode_skip -= 1;
+                        } else if !skip {
+                            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                                out.push(char::from(byte));
+                            }
+                        }
+                    }
+                    '*' => {
+                        i += 1;
+                        skip = true;
+                    }
+                    '~' => {
+                        i += 1;
+                        if !skip {
+                            out.push(' ');
+                        }
+                    }
+                    c if c.is_ascii_alphabetic() => {
+                        let start = i;
+                        while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                            i += 1;
+                        }
+                        let word: String = chars[start..i].iter().collect();
+
+                        let param_start = i;
+                        if i < chars.len() && chars[i] == '-' {
+                            i += 1;
+                        }
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        let param: Option<i32> = chars[param_start..i]
+                            .iter()
+                            .collect::<String>()
+                            .parse()
+                            .ok();
+
+                        // A single space terminates the control word and is not text.
+                        if i < chars.len() && chars[i] == ' ' {
+                            i += 1;
+                        }
+
+                        if RTF_SKIP_DESTINATIONS.contains(&word.as_str()) {
+                            skip = true;
+                            continue;
+                        }
+                        if skip {
+                            continue;
+                        }
+
+                        match word.as_str() {
+                            "par" | "line" | "row" | "sect" | "page" => out.push('\n'),
+                            "tab" | "cell" => out.push('\t'),
+                            "u" => {
+                                if let Some(code) = param {
+                                    let code = if code < 0 { code + 65536 } else { code };
+                                    if let Some(ch) = char::from_u32(code as u32) {
+                                        out.push(ch);
+                                    }
+                                    unicode_skip = 1;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    _ => i += 1,
+                }
+            }
+            _ => {
+                if unicode_skip > 0 {
+                    unicode_skip -= 1;
+                } else if !skip {
+                    out.push(c);
+                }
+            }
+        }
+    }
+
+    out.trim().to_string()
+}
@@ -0,0 +1,71 @@
+use common::constants::{API_PORT, API_PORT_MAX, API_PORT_MIN};
+use sea_orm_migration::{
+    prelude::*,
+    schema::{boolean, integer, string},
+};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    ApiEnabled,
+    ApiPort,
+    ApiToken,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite supports only one column per ALTER TABLE, so add each separately.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(boolean(Settings::ApiEnabled).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(
+                        integer(Settings::ApiPort).default(API_PORT).check(
+                            Expr::col(Settings::ApiPort)
+                                .gte(API_PORT_MIN)
+                                .and(Expr::col(Settings::ApiPort).lte(API_PORT_MAX)),
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Empty until the API is enabled the first time, then a random token is set.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(string(Settings::ApiToken).default(""))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Settings::ApiEnabled, Settings::ApiPort, Settings::ApiToken] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Settings::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
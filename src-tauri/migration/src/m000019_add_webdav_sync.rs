@@ -0,0 +1,48 @@
+use crate::helpers::rebuild_sync_provider_check;
+use sea_orm_migration::{prelude::*, schema::string};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    WebdavUrl,
+    WebdavUsername,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rebuild_sync_provider_check(manager).await?;
+
+        // The password is kept in the OS keychain, not in the database.
+        for column in [Settings::WebdavUrl, Settings::WebdavUsername] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Settings::Table)
+                        .add_column(string(column).default(""))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Settings::WebdavUrl, Settings::WebdavUsername] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Settings::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
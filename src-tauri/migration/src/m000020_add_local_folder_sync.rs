@@ -0,0 +1,38 @@
+use crate::helpers::rebuild_sync_provider_check;
+use sea_orm_migration::{prelude::*, schema::string};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    LocalSyncPath,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rebuild_sync_provider_check(manager).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(string(Settings::LocalSyncPath).default(""))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .drop_column(Settings::LocalSyncPath)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,63 @@
+use crate::helpers::rebuild_sync_provider_check;
+use sea_orm_migration::{prelude::*, schema::string};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    S3Endpoint,
+    S3Region,
+    S3Bucket,
+    S3Prefix,
+    S3AccessKeyId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rebuild_sync_provider_check(manager).await?;
+
+        // The secret access key is kept in the OS keychain, not in the database.
+        for column in [
+            Settings::S3Endpoint,
+            Settings::S3Region,
+            Settings::S3Bucket,
+            Settings::S3Prefix,
+            Settings::S3AccessKeyId,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Settings::Table)
+                        .add_column(string(column).default(""))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Settings::S3Endpoint,
+            Settings::S3Region,
+            Settings::S3Bucket,
+            Settings::S3Prefix,
+            Settings::S3AccessKeyId,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Settings::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::helpers::rebuild_sync_provider_check;
+use common::constants::{API_PORT_MAX, API_PORT_MIN, LAN_PORT};
+use sea_orm_migration::{
+    prelude::*,
+    schema::{integer, string},
+};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    LanPort,
+    LanPeer,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rebuild_sync_provider_check(manager).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(
+                        integer(Settings::LanPort).default(LAN_PORT).check(
+                            Expr::col(Settings::LanPort)
+                                .gte(API_PORT_MIN)
+                                .and(Expr::col(Settings::LanPort).lte(API_PORT_MAX)),
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // `ip:port` of the paired host, empty while this device is the host.
+        // The shared key is kept in the OS keychain, not in the database.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(string(Settings::LanPeer).default(""))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Settings::LanPort, Settings::LanPeer] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Settings::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
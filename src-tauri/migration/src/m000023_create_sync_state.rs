@@ -0,0 +1,49 @@
+use sea_orm_migration::{
+    prelude::*,
+    schema::{boolean, date_time, pk_uuid, string, string_null},
+};
+
+#[derive(Iden)]
+enum SyncState {
+    Table,
+    ClipboardId,
+    ProviderId,
+    Star,
+    Name,
+    Encrypted,
+    ContentHash,
+    SyncedAt,
+}
+
+// What the sync provider last saw of each clipboard, so a sync only sends the
+// differences. No foreign key: the row outlives a locally deleted clipboard, which
+// is how the deletion is told apart from a clipboard that was never synced.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SyncState::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(SyncState::ClipboardId))
+                    .col(string(SyncState::ProviderId))
+                    .col(boolean(SyncState::Star))
+                    .col(string_null(SyncState::Name))
+                    .col(boolean(SyncState::Encrypted))
+                    .col(string(SyncState::ContentHash))
+                    .col(date_time(SyncState::SyncedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SyncState::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::{prelude::*, schema::date_time};
+
+#[derive(Iden)]
+enum Clipboard {
+    Table,
+    UpdatedAt,
+    CreatedAt,
+}
+
+// SQLite only takes constant defaults on ADD COLUMN, existing rows get their
+// `created_at` afterwards and new ones are stamped by the app.
+const UPDATED_AT_DEFAULT: &str = "1970-01-01 00:00:00";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .add_column(date_time(Clipboard::UpdatedAt).default(UPDATED_AT_DEFAULT))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Clipboard::Table)
+                    .value(Clipboard::UpdatedAt, Expr::col(Clipboard::CreatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .drop_column(Clipboard::UpdatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
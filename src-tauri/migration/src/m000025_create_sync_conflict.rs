@@ -0,0 +1,48 @@
+use sea_orm_migration::{
+    prelude::*,
+    schema::{date_time, pk_auto, string, string_null, uuid},
+};
+
+#[derive(Iden)]
+enum SyncConflict {
+    Table,
+    Id,
+    ClipboardId,
+    Field,
+    LocalValue,
+    RemoteValue,
+    Winner,
+    CreatedAt,
+}
+
+// Log of clipboards edited on two devices and how the sync merged them. Kept after
+// the clipboard is deleted, hence no foreign key.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SyncConflict::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SyncConflict::Id))
+                    .col(uuid(SyncConflict::ClipboardId))
+                    .col(string(SyncConflict::Field))
+                    .col(string_null(SyncConflict::LocalValue))
+                    .col(string_null(SyncConflict::RemoteValue))
+                    .col(string(SyncConflict::Winner))
+                    .col(date_time(SyncConflict::CreatedAt).default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SyncConflict::Table).to_owned())
+            .await
+    }
+}
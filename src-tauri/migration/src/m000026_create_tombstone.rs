@@ -0,0 +1,76 @@
+use common::constants::{TOMBSTONE_TTL_DAYS, TOMBSTONE_TTL_DAYS_MAX, TOMBSTONE_TTL_DAYS_MIN};
+use sea_orm_migration::{
+    prelude::*,
+    schema::{boolean, date_time, integer, pk_uuid},
+};
+
+#[derive(Iden)]
+enum Tombstone {
+    Table,
+    ClipboardId,
+    DeletedAt,
+    Published,
+}
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    TombstoneTtlDays,
+}
+
+// Clipboards deleted on this device. `published` once they are in the provider's
+// tombstone document, the row goes when the document drops the tombstone again.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tombstone::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(Tombstone::ClipboardId))
+                    .col(date_time(Tombstone::DeletedAt).default(Expr::current_timestamp()))
+                    .col(boolean(Tombstone::Published).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(
+                        integer(Settings::TombstoneTtlDays)
+                            .default(TOMBSTONE_TTL_DAYS)
+                            .check(
+                                Expr::col(Settings::TombstoneTtlDays)
+                                    .gte(TOMBSTONE_TTL_DAYS_MIN)
+                                    .and(
+                                        Expr::col(Settings::TombstoneTtlDays)
+                                            .lte(TOMBSTONE_TTL_DAYS_MAX),
+                                    ),
+                            ),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .drop_column(Settings::TombstoneTtlDays)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Tombstone::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::{prelude::*, schema::string_null};
+
+#[derive(Iden)]
+enum Clipboard {
+    Table,
+    OriginDevice,
+}
+
+// Device id (see `Config::device_id`) of the device that copied the clipboard, NULL
+// for clipboards from before it was recorded.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .add_column(string_null(Clipboard::OriginDevice))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .drop_column(Clipboard::OriginDevice)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,212 @@
+use crate::m000003_create_clipboard_image::ClipboardImage;
+use crate::m000006_create_clipboard_file::ClipboardFile;
+use common::io::clipboard::blob_hash;
+use sea_orm_migration::sea_orm::prelude::Uuid;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement, Value};
+use sea_orm_migration::{
+    prelude::*,
+    schema::{blob, date_time, integer, string, string_null},
+};
+
+#[derive(Iden)]
+enum Blob {
+    Table,
+    Hash,
+    Data,
+    Size,
+    RefCount,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum BlobHash {
+    BlobHash,
+}
+
+// Image and file bytes live once per SHA-256 in `blob`, rows point at them through
+// `blob_hash` and keep their own `data` empty. The triggers count the rows pointing at
+// a blob and drop it with the last one, so cascading deletes need no extra work.
+pub const CREATE_TRIGGERS: [&str; 6] = [
+    "CREATE TRIGGER IF NOT EXISTS clipboard_image_blob_ai AFTER INSERT ON clipboard_image \
+     WHEN new.blob_hash IS NOT NULL BEGIN \
+        UPDATE blob SET ref_count = ref_count + 1 WHERE hash = new.blob_hash; \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_image_blob_ad AFTER DELETE ON clipboard_image \
+     WHEN old.blob_hash IS NOT NULL BEGIN \
+        UPDATE blob SET ref_count = ref_count - 1 WHERE hash = old.blob_hash; \
+        DELETE FROM blob WHERE hash = old.blob_hash AND ref_count <= 0; \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_image_blob_au AFTER UPDATE OF blob_hash ON clipboard_image BEGIN \
+        UPDATE blob SET ref_count = ref_count + 1 WHERE hash = new.blob_hash; \
+        UPDATE blob SET ref_count = ref_count - 1 WHERE hash = old.blob_hash; \
+        DELETE FROM blob WHERE hash = old.blob_hash AND ref_count <= 0; \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_file_blob_ai AFTER INSERT ON clipboard_file \
+     WHEN new.blob_hash IS NOT NULL BEGIN \
+        UPDATE blob SET ref_count = ref_count + 1 WHERE hash = new.blob_hash; \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_file_blob_ad AFTER DELETE ON clipboard_file \
+     WHEN old.blob_hash IS NOT NULL BEGIN \
+        UPDATE blob SET ref_count = ref_count - 1 WHERE hash = old.blob_hash; \
+        DELETE FROM blob WHERE hash = old.blob_hash AND ref_count <= 0; \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_file_blob_au AFTER UPDATE OF blob_hash ON clipboard_file BEGIN \
+        UPDATE blob SET ref_count = ref_count + 1 WHERE hash = new.blob_hash; \
+        UPDATE blob SET ref_count = ref_count - 1 WHERE hash = old.blob_hash; \
+        DELETE FROM blob WHERE hash = old.blob_hash AND ref_count <= 0; \
+    END",
+];
+
+const DROP_TRIGGERS: [&str; 6] = [
+    "DROP TRIGGER IF EXISTS clipboard_file_blob_au",
+    "DROP TRIGGER IF EXISTS clipboard_file_blob_ad",
+    "DROP TRIGGER IF EXISTS clipboard_file_blob_ai",
+    "DROP TRIGGER IF EXISTS clipboard_image_blob_au",
+    "DROP TRIGGER IF EXISTS clipboard_image_blob_ad",
+    "DROP TRIGGER IF EXISTS clipboard_image_blob_ai",
+];
+
+const BLOB_TABLES: [&str; 2] = ["clipboard_image", "clipboard_file"];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Blob::Table)
+                    .if_not_exists()
+                    .col(string(Blob::Hash).primary_key())
+                    .col(blob(Blob::Data))
+                    .col(integer(Blob::Size))
+                    .col(integer(Blob::RefCount).default(0))
+                    .col(date_time(Blob::CreatedAt).default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardImage::Table)
+                    .add_column(string_null(BlobHash::BlobHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardFile::Table)
+                    .add_column(string_null(BlobHash::BlobHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        for sql in CREATE_TRIGGERS {
+            db.execute(Statement::from_string(backend, sql.to_owned()))
+                .await?;
+        }
+
+        // Fold the existing bytes into blobs one row at a time, screenshot heavy
+        // histories don't fit in memory at once. The triggers count the references.
+        for table in BLOB_TABLES {
+            let ids = db
+                .query_all(Statement::from_string(
+                    backend,
+                    format!("SELECT id FROM {table} WHERE length(data) > 0"),
+                ))
+                .await?;
+
+            for row in ids {
+                let id: Uuid = row.try_get("", "id")?;
+
+                let Some(row) = db
+                    .query_one(Statement::from_sql_and_values(
+                        backend,
+                        format!("SELECT data FROM {table} WHERE id = ?"),
+                        [Value::from(id)],
+                    ))
+                    .await?
+                else {
+                    continue;
+                };
+                let data: Vec<u8> = row.try_get("", "data")?;
+                let hash = blob_hash(&data);
+                let size = data.len() as i64;
+
+                db.execute(Statement::from_sql_and_values(
+                    backend,
+                    "INSERT INTO blob (hash, data, size) VALUES (?, ?, ?) \
+                     ON CONFLICT (hash) DO NOTHING",
+                    [
+                        Value::from(hash.clone()),
+                        Value::from(data),
+                        Value::from(size),
+                    ],
+                ))
+                .await?;
+
+                db.execute(Statement::from_sql_and_values(
+                    backend,
+                    format!("UPDATE {table} SET blob_hash = ?, data = X'' WHERE id = ?"),
+                    [Value::from(hash), Value::from(id)],
+                ))
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        for sql in DROP_TRIGGERS {
+            db.execute(Statement::from_string(backend, sql.to_owned()))
+                .await?;
+        }
+
+        // Give every row its bytes back before the blobs go
+        for table in BLOB_TABLES {
+            db.execute(Statement::from_string(
+                backend,
+                format!(
+                    "UPDATE {table} SET data = \
+                     (SELECT data FROM blob WHERE blob.hash = {table}.blob_hash) \
+                     WHERE blob_hash IS NOT NULL"
+                ),
+            ))
+            .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardFile::Table)
+                    .drop_column(BlobHash::BlobHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardImage::Table)
+                    .drop_column(BlobHash::BlobHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Blob::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,115 @@
+use crate::m000029_create_blob::CREATE_TRIGGERS;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement};
+use sea_orm_migration::{prelude::*, schema::boolean};
+
+#[derive(Iden)]
+enum Blob {
+    Table,
+    External,
+}
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    ExternalBlobStore,
+}
+
+// External blobs keep their row when the last reference goes, `purge_blobs_db` removes
+// it together with the file. The triggers only drop unreferenced blobs held in the db.
+const CREATE_DELETE_TRIGGERS: [&str; 4] = [
+    "CREATE TRIGGER IF NOT EXISTS clipboard_image_blob_ad AFTER DELETE ON clipboard_image \
+     WHEN old.blob_hash IS NOT NULL BEGIN \
+        UPDATE blob SET ref_count = ref_count - 1 WHERE hash = old.blob_hash; \
+        DELETE FROM blob WHERE hash = old.blob_hash AND ref_count <= 0 AND external = 0; \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_image_blob_au AFTER UPDATE OF blob_hash ON clipboard_image BEGIN \
+        UPDATE blob SET ref_count = ref_count + 1 WHERE hash = new.blob_hash; \
+        UPDATE blob SET ref_count = ref_count - 1 WHERE hash = old.blob_hash; \
+        DELETE FROM blob WHERE hash = old.blob_hash AND ref_count <= 0 AND external = 0; \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_file_blob_ad AFTER DELETE ON clipboard_file \
+     WHEN old.blob_hash IS NOT NULL BEGIN \
+        UPDATE blob SET ref_count = ref_count - 1 WHERE hash = old.blob_hash; \
+        DELETE FROM blob WHERE hash = old.blob_hash AND ref_count <= 0 AND external = 0; \
+    END",
+    "CREATE TRIGGER IF NOT EXISTS clipboard_file_blob_au AFTER UPDATE OF blob_hash ON clipboard_file BEGIN \
+        UPDATE blob SET ref_count = ref_count + 1 WHERE hash = new.blob_hash; \
+        UPDATE blob SET ref_count = ref_count - 1 WHERE hash = old.blob_hash; \
+        DELETE FROM blob WHERE hash = old.blob_hash AND ref_count <= 0 AND external = 0; \
+    END",
+];
+
+const DROP_DELETE_TRIGGERS: [&str; 4] = [
+    "DROP TRIGGER IF EXISTS clipboard_file_blob_au",
+    "DROP TRIGGER IF EXISTS clipboard_file_blob_ad",
+    "DROP TRIGGER IF EXISTS clipboard_image_blob_au",
+    "DROP TRIGGER IF EXISTS clipboard_image_blob_ad",
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blob::Table)
+                    .add_column(boolean(Blob::External).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(boolean(Settings::ExternalBlobStore).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        for sql in DROP_DELETE_TRIGGERS
+            .into_iter()
+            .chain(CREATE_DELETE_TRIGGERS)
+        {
+            db.execute(Statement::from_string(backend, sql.to_owned()))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        // Back to the triggers of m000029, the `IF NOT EXISTS` skips the untouched ones
+        for sql in DROP_DELETE_TRIGGERS.into_iter().chain(CREATE_TRIGGERS) {
+            db.execute(Statement::from_string(backend, sql.to_owned()))
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .drop_column(Settings::ExternalBlobStore)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blob::Table)
+                    .drop_column(Blob::External)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,90 @@
+use common::types::enums::DuplicateAction;
+use sea_orm_migration::{
+    prelude::*,
+    schema::{string, string_null},
+};
+
+#[derive(Iden)]
+enum Clipboard {
+    Table,
+    ContentHash,
+}
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    DuplicateAction,
+}
+
+const CONTENT_HASH_INDEX: &str = "idx_clipboard_content_hash";
+
+// Hash of the plain content, see `clipboard_payload_hash`. NULL until the app fills it
+// in, for encrypted clipboards once the key is set.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .add_column(string_null(Clipboard::ContentHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(CONTENT_HASH_INDEX)
+                    .table(Clipboard::Table)
+                    .col(Clipboard::ContentHash)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(
+                        string(Settings::DuplicateAction)
+                            .default(DuplicateAction::Bump.to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .drop_column(Settings::DuplicateAction)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(CONTENT_HASH_INDEX)
+                    .table(Clipboard::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .drop_column(Clipboard::ContentHash)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,54 @@
+use sea_orm_migration::{prelude::*, schema::integer};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    RetentionDays,
+    RetentionMaxClips,
+    RetentionImageDays,
+}
+
+// 0 keeps clipboards forever, see `apply_retention_db`
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Settings::RetentionDays,
+            Settings::RetentionMaxClips,
+            Settings::RetentionImageDays,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Settings::Table)
+                        .add_column(integer(column).default(0))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Settings::RetentionDays,
+            Settings::RetentionMaxClips,
+            Settings::RetentionImageDays,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Settings::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
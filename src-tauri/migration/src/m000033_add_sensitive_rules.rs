@@ -0,0 +1,85 @@
+use common::types::sensitive::SensitiveRule;
+use sea_orm_migration::{
+    prelude::*,
+    schema::{boolean, date_time_null, json},
+};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    SensitiveRules,
+}
+
+#[derive(Iden)]
+enum Clipboard {
+    Table,
+    NoSync,
+    ExpiresAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let rules = serde_json::to_string(&SensitiveRule::defaults())
+            .map_err(|e| DbErr::Custom(e.to_string()))?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(json(Settings::SensitiveRules).default(Expr::value(rules)))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .add_column(boolean(Clipboard::NoSync).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .add_column(date_time_null(Clipboard::ExpiresAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .drop_column(Clipboard::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .drop_column(Clipboard::NoSync)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .drop_column(Settings::SensitiveRules)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,40 @@
+use common::types::enums::SensitiveAction;
+use sea_orm_migration::{prelude::*, schema::string};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    ConcealedAction,
+}
+
+// What happens to clipboards a password manager marked, see `ClipboardHint`
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(
+                        string(Settings::ConcealedAction)
+                            .default(SensitiveAction::Drop.to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .drop_column(Settings::ConcealedAction)
+                    .to_owned(),
+            )
+            .await
+    }
+}
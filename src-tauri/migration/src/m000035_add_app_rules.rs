@@ -0,0 +1,62 @@
+use sea_orm_migration::{
+    prelude::*,
+    schema::{json, string_null},
+};
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    AppRules,
+}
+
+#[derive(Iden)]
+enum Clipboard {
+    Table,
+    SourceApp,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .add_column(json(Settings::AppRules).default(Expr::value("[]")))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .add_column(string_null(Clipboard::SourceApp))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Clipboard::Table)
+                    .drop_column(Clipboard::SourceApp)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Settings::Table)
+                    .drop_column(Settings::AppRules)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,45 @@
+use sea_orm_migration::{prelude::*, schema::string_null};
+
+#[derive(Iden)]
+enum Clipboard {
+    Table,
+    SourceWindowTitle,
+    SourceUrl,
+}
+
+// Where a clipboard was copied, next to `source_app`. NULL for older clipboards
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Clipboard::SourceWindowTitle, Clipboard::SourceUrl] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Clipboard::Table)
+                        .add_column(string_null(column))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Clipboard::SourceWindowTitle, Clipboard::SourceUrl] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Clipboard::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
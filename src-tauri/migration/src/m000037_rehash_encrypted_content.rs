@@ -0,0 +1,32 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+enum Clipboard {
+    Table,
+    Encrypted,
+    ContentHash,
+}
+
+// Encrypted clipboards used to get a plain SHA-256 of their content, they are keyed
+// with the encryption key now. Cleared here, the app hashes them again once unlocked.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Clipboard::Table)
+                    .value(Clipboard::ContentHash, Option::<String>::None)
+                    .and_where(Expr::col(Clipboard::Encrypted).eq(true))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
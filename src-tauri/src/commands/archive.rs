@@ -0,0 +1,67 @@
+use crate::service::archive::{export_archive_db, import_archive_db};
+use crate::tao::global::get_app;
+use common::constants::{BACKDUP_DATE_FORMAT, BACKUP_FILE_PREFIX};
+use common::types::archive::{ArchiveFilter, ImportSummary};
+use common::types::types::CommandError;
+use std::path::PathBuf;
+use tauri_plugin_dialog::DialogExt;
+
+/// Exports into a zip archive, asks where to save it when no path is given. A passphrase
+/// seals the archive. Returns the number of exported clipboards or `None` when the
+/// dialog was cancelled.
+#[tauri::command]
+pub async fn export_clipboards(
+    path: Option<String>,
+    filter: Option<ArchiveFilter>,
+    passphrase: Option<String>,
+) -> Result<Option<usize>, CommandError> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let file_name = format!(
+                "{}_{}.zip",
+                BACKUP_FILE_PREFIX,
+                chrono::Local::now().format(BACKDUP_DATE_FORMAT)
+            );
+
+            match get_app()
+                .dialog()
+                .file()
+                .set_file_name(file_name)
+                .add_filter("Clippy archive", &["zip"])
+                .blocking_save_file()
+                .and_then(|path| path.into_path().ok())
+            {
+                Some(path) => path,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    Ok(Some(
+        export_archive_db(&path, filter.unwrap_or_default(), passphrase).await?,
+    ))
+}
+
+/// Merges an archive into the history, asks for the file when no path is given.
+#[tauri::command]
+pub async fn import_clipboards(
+    path: Option<String>,
+    passphrase: Option<String>,
+) -> Result<Option<ImportSummary>, CommandError> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match get_app()
+            .dialog()
+            .file()
+            .add_filter("Clippy archive", &["zip"])
+            .blocking_pick_file()
+            .and_then(|path| path.into_path().ok())
+        {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Some(import_archive_db(&path, passphrase).await?))
+}
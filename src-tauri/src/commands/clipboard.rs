@@ -1,6 +1,7 @@
 use crate::service::cipher::is_encryption_key_set;
 use crate::service::clipboard::{
     filter_clipboards, get_all_clipboards_db, init_clipboards, load_clipboards_for_search,
+    search_score,
 };
 use crate::service::decrypt::{decrypt_clipboard, decrypt_clipboard_search, read_encryption_key};
 use crate::service::settings::get_global_settings;
@@ -14,17 +15,23 @@ use crate::{
     },
     utils::hotkey_manager::unregister_hotkeys,
 };
-use common::constants::CACHE_KEY;
+use common::builder::query::SearchQuery;
+use common::constants::{CACHE_KEY, FUZZY_SEARCH_LIMIT};
 use common::io::clipboard::trim_clipboard_data;
+use common::io::fuzzy::TopRanked;
 use common::types::orm_query::FullClipboardDto;
 use common::{
     printlog,
-    types::{enums::ClipboardType, orm_query::ClipboardsResponse, types::CommandError},
+    types::{
+        enums::{ClipboardType, Language},
+        orm_query::ClipboardsResponse,
+        types::CommandError,
+    },
 };
 use entity::clipboard;
 use rayon::prelude::*;
 use sea_orm::prelude::Uuid;
-use sea_orm::{EntityTrait, PaginatorTrait, QueryOrder};
+use sea_orm::{EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
 use serde::Serialize;
 use std::fs::File;
 use std::sync::atomic::Ordering;
@@ -55,7 +62,7 @@ pub async fn get_clipboards(
         // If cache is ready, use it for fast in-memory search
         if let Some(cached) = get_cache().get(CACHE_KEY) {
             let filtered =
-                filter_clipboards(&cached, search.as_ref(), star, img, &settings);
+                filter_clipboards(&cached, search.as_ref(), star, img, false, &settings);
             let start = cursor.unwrap_or(0) as usize;
             let end = (start + 25).min(filtered.len());
             if start < filtered.len() {
@@ -96,7 +103,7 @@ pub async fn get_clipboards(
 
                 let single = vec![cb];
                 let matches =
-                    filter_clipboards(&single, search.as_ref(), star, img, &settings);
+                    filter_clipboards(&single, search.as_ref(), star, img, false, &settings);
                 matched.extend(matches);
 
                 if matched.len() >= needed {
@@ -198,7 +205,7 @@ pub enum SearchEvent {
     },
 }
 
-/// Rows per page for the encrypted no-cache search path.
+/// Rows per page for the encrypted no-cache and fuzzy search paths.
 const SEARCH_PAGE_SIZE: u64 = 64;
 
 #[tauri::command]
@@ -206,6 +213,7 @@ pub async fn search_clipboards(
     search: Option<String>,
     star: Option<bool>,
     img: Option<bool>,
+    fuzzy: Option<bool>,
     on_chunk: Channel<SearchEvent>,
 ) -> Result<(), CommandError> {
     // Bump the generation. Any in-flight search with an older generation will see this
@@ -217,7 +225,15 @@ pub async fn search_clipboards(
     let is_encrypted = settings.encryption && is_encryption_key_set();
     let total = get_clipboard_count_db().await?;
 
-    if !is_encrypted {
+    // Fuzzy matching can't use the FTS5 index: it scores every candidate in memory
+    // and only sends results once all of them are ranked.
+    let search_query = SearchQuery::parse(
+        search.as_deref().unwrap_or_default(),
+        &Language::from_iso_code(&settings.language),
+    );
+    let fuzzy = fuzzy.unwrap_or(false) && !search_query.include.is_empty();
+
+    if !is_encrypted && !fuzzy {
         let clipboards = get_clipboards_db(None, search, star, img).await?;
         if superseded() {
             return Ok(());
@@ -230,8 +246,8 @@ pub async fn search_clipboards(
     }
 
     // Encrypted: check cache first
-    if let Some(cached) = get_cache().get(CACHE_KEY) {
-        let filtered = filter_clipboards(&cached, search.as_ref(), star, img, &settings);
+    if let Some(cached) = is_encrypted.then(|| get_cache().get(CACHE_KEY)).flatten() {
+        let filtered = filter_clipboards(&cached, search.as_ref(), star, img, fuzzy, &settings);
         for chunk in filtered.chunks(100) {
             if superseded() {
                 return Ok(());
@@ -246,12 +262,22 @@ pub async fn search_clipboards(
 
     // Encrypted, no cache: paginate, decrypt small fields in parallel (never image/file
     // blobs), stream matches, build a blob-free cache. Cancel if superseded.
-    let key = read_encryption_key().map_err(|e| CommandError::new(&e.to_string()))?;
+    // Unencrypted fuzzy search takes the same path without decryption or caching.
+    let key = if is_encrypted {
+        Some(read_encryption_key().map_err(|e| CommandError::new(&e.to_string()))?)
+    } else {
+        None
+    };
     let mut all_decrypted: Vec<FullClipboardDto> = Vec::new();
+    // Every page is ranked as it comes, only the best matches are kept
+    let mut ranked = TopRanked::new(FUZZY_SEARCH_LIMIT);
 
-    let mut paginator = clipboard::Entity::find()
-        .order_by_desc(clipboard::Column::Id)
-        .paginate(db(), SEARCH_PAGE_SIZE);
+    let mut query = clipboard::Entity::find().order_by_desc(clipboard::Column::Id);
+    // Encrypted rows hide file extensions, so only narrow the plain database
+    if !is_encrypted {
+        query = query.filter(search_query.filter_condition());
+    }
+    let mut paginator = query.paginate(db(), SEARCH_PAGE_SIZE);
 
     while let Some(models) = paginator
         .fetch_and_next()
@@ -263,23 +289,29 @@ pub async fn search_clipboards(
         }
 
         // Load this page without blob columns, then decrypt small fields in parallel.
-        let page = load_clipboards_for_search(models).await;
+        let page = load_clipboards_for_search(models)
+            .await
+            .map_err(|e| CommandError::new(&e.to_string()))?;
         let decrypted_page: Vec<FullClipboardDto> = tokio::task::spawn_blocking(move || {
             page.into_par_iter()
-                .map(|c| {
-                    if c.clipboard.encrypted {
-                        decrypt_clipboard_search(c.clone(), &key).unwrap_or(c)
-                    } else {
-                        c
+                .map(|c| match &key {
+                    Some(key) if c.clipboard.encrypted => {
+                        decrypt_clipboard_search(c.clone(), key).unwrap_or(c)
                     }
+                    _ => c,
                 })
                 .collect()
         })
         .await
         .map_err(|e| CommandError::new(&e.to_string()))?;
 
-        let matches = filter_clipboards(&decrypted_page, search.as_ref(), star, img, &settings);
-        if !matches.is_empty() {
+        let matches =
+            filter_clipboards(&decrypted_page, search.as_ref(), star, img, fuzzy, &settings);
+        if fuzzy {
+            for clipboard in matches {
+                ranked.push(search_score(&clipboard), clipboard);
+            }
+        } else if !matches.is_empty() {
             on_chunk
                 .send(SearchEvent::Batch {
                     clipboards: trim_clipboard_data(matches),
@@ -287,14 +319,28 @@ pub async fn search_clipboards(
                 .map_err(|e| CommandError::new(&e.to_string()))?;
         }
 
-        all_decrypted.extend(decrypted_page);
+        if is_encrypted {
+            all_decrypted.extend(decrypted_page);
+        }
     }
 
     if superseded() {
         return Ok(());
     }
 
-    get_cache().insert(CACHE_KEY.to_string(), all_decrypted);
+    if fuzzy {
+        for chunk in ranked.into_sorted_vec().chunks(100) {
+            on_chunk
+                .send(SearchEvent::Batch {
+                    clipboards: trim_clipboard_data(chunk.to_vec()),
+                })
+                .map_err(|e| CommandError::new(&e.to_string()))?;
+        }
+    }
+
+    if is_encrypted {
+        get_cache().insert(CACHE_KEY.to_string(), all_decrypted);
+    }
     on_chunk.send(SearchEvent::Done { total }).map_err(|e| CommandError::new(&e.to_string()))?;
     Ok(())
 }
@@ -1,3 +1,4 @@
+pub mod archive;
 pub mod cipher;
 pub mod clipboard;
 pub mod hotkey;
@@ -1,9 +1,15 @@
 use crate::{
+    service::api::regenerate_api_token_db,
+    service::blob::migrate_blob_store_db,
     service::settings::{
-        autostart, get_settings_db, update_settings_db, update_settings_text_matchers,
+        autostart, get_settings_db, update_settings_app_rules, update_settings_db,
+        update_settings_sensitive_rules, update_settings_sync_rules, update_settings_text_matchers,
     },
     tao::config::{change_clipboard_db_location_enable, reset_clipboard_db_location_disable},
 };
+use common::types::capture::AppRule;
+use common::types::sensitive::SensitiveRule;
+use common::types::sync::SyncRule;
 use common::types::types::{CommandError, TextMatcher};
 use entity::settings::Model;
 
@@ -26,6 +32,39 @@ pub async fn change_settings_text_matchers(text_matchers: Vec<TextMatcher>) {
         .expect("Failed to update replace patterns");
 }
 
+#[tauri::command]
+pub async fn change_settings_sync_rules(
+    sync_rules: Vec<SyncRule>,
+) -> Result<Vec<SyncRule>, CommandError> {
+    update_settings_sync_rules(sync_rules).await
+}
+
+#[tauri::command]
+pub async fn change_settings_sensitive_rules(
+    sensitive_rules: Vec<SensitiveRule>,
+) -> Result<Vec<SensitiveRule>, CommandError> {
+    update_settings_sensitive_rules(sensitive_rules).await
+}
+
+#[tauri::command]
+pub async fn change_settings_app_rules(
+    app_rules: Vec<AppRule>,
+) -> Result<Vec<AppRule>, CommandError> {
+    update_settings_app_rules(app_rules).await
+}
+
+/// Switches between keeping blobs in the db and the external store, moving the
+/// existing ones along. Returns how many were moved.
+#[tauri::command]
+pub async fn migrate_blob_store(external: bool) -> Result<usize, CommandError> {
+    migrate_blob_store_db(external).await
+}
+
+#[tauri::command]
+pub async fn regenerate_api_token() -> Result<Model, CommandError> {
+    regenerate_api_token_db().await
+}
+
 #[tauri::command]
 pub async fn toggle_autostart() {
     autostart()
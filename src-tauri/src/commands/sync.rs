@@ -1,13 +1,24 @@
 use crate::{
+    prelude::*,
     service::{
+        lan::{configure_lan_host, discover_lan_peers, pair_lan_peer, start_lan_pairing},
         settings::{get_global_settings, init_settings_window, set_global_settings},
-        sync::{get_sync_provider, sync_toggle, upsert_settings_sync},
+        sync::{
+            clear_sync_conflicts_db, configure_local_folder_sync, configure_s3_sync,
+            configure_webdav_sync, get_sync_conflicts_db, get_sync_devices, get_sync_provider,
+            revoke_sync_device, sync_toggle, upsert_settings_sync,
+        },
     },
-    tao::connection::db,
+    tao::{connection::db, global::get_app},
+};
+use common::types::{
+    sync::{LanPeer, SyncDevices},
+    types::CommandError,
 };
-use common::types::types::CommandError;
 use entity::settings::{self, ActiveModel};
+use entity::sync_conflict;
 use sea_orm::{ActiveModelTrait, EntityTrait};
+use tauri_plugin_dialog::DialogExt;
 
 #[tauri::command]
 pub async fn sync_authenticate_toggle() -> Result<bool, CommandError> {
@@ -30,7 +41,13 @@ pub async fn sync_limit_change(sync_limit: i32) -> Result<settings::Model, Comma
     if settings.sync {
         let remote_settings = settings.clone();
         tauri::async_runtime::spawn(async move {
-            let provider = get_sync_provider().await;
+            let provider = match get_sync_provider().await {
+                Ok(provider) => provider,
+                Err(e) => {
+                    printlog!("failed to clean up remote clipboards: {:?}", e);
+                    return;
+                }
+            };
             let remote_clipboards = provider
                 .fetch_all_clipboards()
                 .await
@@ -51,3 +68,95 @@ pub async fn sync_limit_change(sync_limit: i32) -> Result<settings::Model, Comma
 
     Ok(settings)
 }
+
+#[tauri::command]
+pub async fn sync_webdav_configure(
+    url: String,
+    username: String,
+    password: String,
+) -> Result<settings::Model, CommandError> {
+    configure_webdav_sync(url, username, password).await
+}
+
+/// Without a path the folder picker is shown, cancelling it leaves the settings as they are.
+#[tauri::command]
+pub async fn sync_local_folder_configure(
+    path: Option<String>,
+) -> Result<settings::Model, CommandError> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let picked =
+                tokio::task::spawn_blocking(|| get_app().dialog().file().blocking_pick_folder())
+                    .await
+                    .map_err(|e| CommandError::Error(e.to_string()))?;
+
+            match picked {
+                Some(dir) => dir.to_string(),
+                None => return Ok(get_global_settings()),
+            }
+        }
+    };
+
+    configure_local_folder_sync(path).await
+}
+
+#[tauri::command]
+pub async fn sync_s3_configure(
+    endpoint: String,
+    region: String,
+    bucket: String,
+    prefix: String,
+    access_key_id: String,
+    secret_access_key: String,
+) -> Result<settings::Model, CommandError> {
+    configure_s3_sync(
+        endpoint,
+        region,
+        bucket,
+        prefix,
+        access_key_id,
+        secret_access_key,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn sync_lan_host() -> Result<settings::Model, CommandError> {
+    configure_lan_host().await
+}
+
+#[tauri::command]
+pub async fn sync_lan_pairing_code() -> Result<String, CommandError> {
+    start_lan_pairing().await
+}
+
+#[tauri::command]
+pub async fn sync_lan_discover() -> Result<Vec<LanPeer>, CommandError> {
+    discover_lan_peers().await
+}
+
+#[tauri::command]
+pub async fn sync_lan_pair(address: String, code: String) -> Result<settings::Model, CommandError> {
+    pair_lan_peer(address, code).await
+}
+
+#[tauri::command]
+pub async fn sync_conflicts() -> Result<Vec<sync_conflict::Model>, CommandError> {
+    Ok(get_sync_conflicts_db().await?)
+}
+
+#[tauri::command]
+pub async fn sync_conflicts_clear() -> Result<(), CommandError> {
+    Ok(clear_sync_conflicts_db().await?)
+}
+
+#[tauri::command]
+pub async fn sync_devices() -> Result<SyncDevices, CommandError> {
+    get_sync_devices().await
+}
+
+#[tauri::command]
+pub async fn sync_device_revoke(device_id: String) -> Result<SyncDevices, CommandError> {
+    revoke_sync_device(device_id).await
+}
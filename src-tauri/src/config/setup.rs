@@ -5,8 +5,9 @@ use crate::{
         window_events::setup_window_event_listener,
     },
     service::{
-        cipher::init_encryption_password_lock, settings::setup_settings, sync::setup_sync_interval,
-        window::setup_window,
+        api::setup_api_server, blob::setup_blob_store, cipher::init_encryption_password_lock,
+        clipboard::setup_content_hashes, lan::setup_lan_server, retention::setup_retention,
+        settings::setup_settings, sync::setup_sync_interval, window::setup_window,
     },
     tao::{config::setup_config, connection::init_db, tao_constants::setup_globals},
 };
@@ -22,6 +23,8 @@ pub fn setup(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error + 'st
     });
 
     setup_settings();
+    setup_blob_store();
+    setup_content_hashes();
     setup_window();
     setup_system_tray()?;
 
@@ -29,6 +32,9 @@ pub fn setup(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error + 'st
     setup_hotkey_listener();
     setup_window_event_listener();
     setup_sync_interval();
+    setup_retention();
+    setup_api_server();
+    setup_lan_server();
 
     init_encryption_password_lock();
 
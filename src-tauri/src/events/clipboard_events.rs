@@ -1,4 +1,7 @@
-use crate::{tao::global::get_app, utils::clipboard_manager::ClipboardManagerExt};
+use crate::{
+    tao::global::get_app,
+    utils::{clipboard_manager::ClipboardManagerExt, clipboard_reader::SystemClipboard},
+};
 use common::types::orm_query::FullClipboardDbo;
 use tauri::{Listener, Manager};
 use tauri_plugin_clipboard::Clipboard;
@@ -15,7 +18,7 @@ pub fn setup_clipboard_listener() {
         "plugin:clipboard://clipboard-monitor/update",
         move |_event| {
             tauri::async_runtime::spawn(async {
-                FullClipboardDbo::upsert_clipboard().await;
+                FullClipboardDbo::upsert_clipboard(SystemClipboard).await;
             });
         },
     );
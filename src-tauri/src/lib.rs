@@ -6,9 +6,11 @@ mod service;
 mod tao;
 mod utils;
 
-use commands::{cipher, clipboard, hotkey, settings, sync, window};
+use commands::{archive, cipher, clipboard, hotkey, settings, sync, window};
 use config::setup;
+use service::cli::{parse_cli_args, CLI_USAGE};
 use tauri_plugin_autostart::MacosLauncher;
+use utils::cli::{handle_cli_request, run_cli_command, CLI_REQUEST_ARG, EXIT_NOT_RUNNING};
 
 /// On the Windows GUI release (`windows_subsystem="windows"`) the process starts
 /// with no console, so stdout/stderr go nowhere. If the app was launched FROM a
@@ -28,6 +30,57 @@ fn attach_parent_console() -> bool {
     true
 }
 
+fn context() -> tauri::Context<tauri::Wry> {
+    tauri::generate_context!()
+}
+
+/// Handles `clippy <subcommand>` before anything of the app is set up. Returns `None`
+/// for a regular start, otherwise the exit code of the command.
+pub async fn run_cli(args: Vec<String>) -> Option<i32> {
+    let args = args.get(1..).unwrap_or_default();
+
+    if args.first().map(String::as_str) == Some(CLI_REQUEST_ARG) {
+        probe_running_instance();
+    }
+
+    if matches!(
+        args.first().map(String::as_str),
+        Some("help" | "--help" | "-h")
+    ) {
+        attach_parent_console();
+        println!("{}", CLI_USAGE);
+        return Some(0);
+    }
+
+    let command = parse_cli_args(args)?;
+
+    // The Windows release has no console of its own to print to
+    attach_parent_console();
+
+    match command {
+        Ok(command) => Some(run_cli_command(command).await),
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, CLI_USAGE);
+            Some(2)
+        }
+    }
+}
+
+/// The probe process of `utils::cli`. When the app is running the single-instance
+/// plugin forwards our argv to it and exits with 0, otherwise we are the first instance
+/// and report that through the exit code before anything is shown.
+fn probe_running_instance() -> ! {
+    let mut context = context();
+    context.config_mut().app.windows.clear();
+
+    let _ = tauri::Builder::default()
+        .plugin(tauri_plugin_single_instance::init(|_app, _argv, _cwd| {}))
+        .setup(|_app| std::process::exit(EXIT_NOT_RUNNING))
+        .run(context);
+
+    std::process::exit(EXIT_NOT_RUNNING)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Route panics through `log` so tauri-plugin-log writes them to the file too.
@@ -83,7 +136,9 @@ pub fn run() {
 
     #[cfg(desktop)]
     {
-        builder = builder.plugin(tauri_plugin_single_instance::init(|_app, _argv, _cwd| {}));
+        builder = builder.plugin(tauri_plugin_single_instance::init(|_app, argv, _cwd| {
+            handle_cli_request(&argv);
+        }));
     }
 
     builder
@@ -98,6 +153,9 @@ pub fn run() {
             clipboard::clear_clipboards,
             clipboard::save_clipboard_image,
             //
+            archive::export_clipboards,
+            archive::import_clipboards,
+            //
             hotkey::get_hotkeys,
             hotkey::update_hotkey,
             hotkey::stop_hotkeys,
@@ -105,6 +163,11 @@ pub fn run() {
             settings::get_settings,
             settings::update_settings,
             settings::change_settings_text_matchers,
+            settings::change_settings_sync_rules,
+            settings::change_settings_sensitive_rules,
+            settings::change_settings_app_rules,
+            settings::migrate_blob_store,
+            settings::regenerate_api_token,
             settings::toggle_autostart,
             settings::change_clipboard_db_location,
             settings::reset_clipboard_db_location,
@@ -121,6 +184,17 @@ pub fn run() {
             //
             sync::sync_authenticate_toggle,
             sync::sync_limit_change,
+            sync::sync_webdav_configure,
+            sync::sync_local_folder_configure,
+            sync::sync_s3_configure,
+            sync::sync_lan_host,
+            sync::sync_lan_pairing_code,
+            sync::sync_lan_discover,
+            sync::sync_lan_pair,
+            sync::sync_conflicts,
+            sync::sync_conflicts_clear,
+            sync::sync_devices,
+            sync::sync_device_revoke,
             //
             //
             cipher::enable_encryption,
@@ -129,6 +203,6 @@ pub fn run() {
             cipher::password_unlock,
             cipher::password_unlock_stream,
         ])
-        .run(tauri::generate_context!())
+        .run(context())
         .expect("error while running tauri application");
 }
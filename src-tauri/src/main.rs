@@ -35,6 +35,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tauri::async_runtime::set(tokio::runtime::Handle::current());
 
+    // `clippy list`, `clippy add --stdin`, ... run without starting the app
+    if let Some(code) = tauri_app_lib::run_cli(std::env::args().collect()).await {
+        std::process::exit(code);
+    }
+
     tauri_app_lib::run();
 
     Ok(())
@@ -0,0 +1,74 @@
+use super::settings::{get_global_settings, get_settings_db, update_settings_db};
+use crate::prelude::*;
+use crate::tao::global::get_app;
+use crate::utils::api_server::ApiServer;
+use common::constants::{API_PORT_MAX, API_PORT_MIN};
+use common::types::types::CommandError;
+use entity::settings;
+use ring::rand::{SecureRandom, SystemRandom};
+use tauri::{Manager, State};
+use tokio::sync::Mutex;
+
+pub fn get_api_server() -> State<'static, Mutex<ApiServer>> {
+    match get_app().try_state() {
+        Some(server) => server,
+        None => {
+            get_app().manage(Mutex::new(ApiServer::new()));
+            get_app().state()
+        }
+    }
+}
+
+pub fn setup_api_server() {
+    tauri::async_runtime::spawn(async {
+        // needs to be real db call because of setup race condition in `fn setup_settings()`
+        let settings = get_settings_db().await.expect("Failed to get settings");
+        apply_api_settings(&settings).await;
+    });
+}
+
+/// Starts, restarts (port change) or stops the local API to match the settings.
+pub async fn apply_api_settings(settings: &settings::Model) {
+    let mut server = get_api_server().lock().await;
+
+    if !settings.api_enabled {
+        server.stop().await;
+        return;
+    }
+
+    let Some(port) = api_port(settings.api_port) else {
+        printlog!("api port {} is out of range", settings.api_port);
+        server.stop().await;
+        return;
+    };
+
+    if let Err(e) = server.start(port).await {
+        printlog!(
+            "failed to start api server on {}: {:?}",
+            settings.api_port,
+            e
+        );
+    }
+}
+
+/// The port if it's in the allowed range, never a truncated one.
+pub fn api_port(port: i32) -> Option<u16> {
+    u16::try_from(port)
+        .ok()
+        .filter(|port| (API_PORT_MIN..=API_PORT_MAX).contains(port))
+}
+
+/// 32 random bytes, hex encoded.
+pub fn generate_api_token() -> String {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to generate API token");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub async fn regenerate_api_token_db() -> Result<settings::Model, CommandError> {
+    let mut settings = get_global_settings();
+    settings.api_token = generate_api_token();
+    update_settings_db(settings).await
+}
@@ -0,0 +1,643 @@
+use super::blob::{load_clipboard_blobs, open_blob_db};
+use super::cipher::{derive_key, is_encryption_key_set, PBKDF2_ITERATIONS};
+use super::clipboard::{
+    get_clipboard_db, get_clipboard_uuids_db, init_clipboards, load_clipboards_for_search,
+    upsert_clipboard_dto,
+};
+use super::decrypt::decrypt_clipboard;
+use super::encrypt::encrypt_clipboard;
+use super::settings::get_global_settings;
+use crate::prelude::*;
+use crate::tao::connection::db;
+use crate::tao::global::{get_app, get_cache, is_headless};
+use chrono::Utc;
+use common::constants::{
+    ARCHIVE_BATCH_SIZE, SEALED_ARCHIVE_CHUNK_SIZE, SEALED_ARCHIVE_MAGIC, SEALED_ARCHIVE_VERSION,
+};
+use common::io::archive::{ArchiveReader, ArchiveWriter};
+use common::types::archive::{ArchiveError, ArchiveFilter, ArchiveManifest, ImportSummary};
+use common::types::enums::ListenEvent;
+use common::types::orm_query::FullClipboardDto;
+use common::types::types::{CommandError, Progress};
+use entity::clipboard;
+use ring::aead;
+use ring::rand::{SecureRandom, SystemRandom};
+use sea_orm::prelude::Uuid;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QueryTrait};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, EventTarget};
+
+const SEALED_SALT_LEN: usize = 16;
+const SEALED_HEADER_LEN: usize = SEALED_ARCHIVE_MAGIC.len() + 1 + 4 + SEALED_SALT_LEN;
+/// Upper bound for the iteration count read from a header, a tampered file must
+/// not be able to stall the import for hours.
+const SEALED_MAX_ITERATIONS: u32 = 10_000_000;
+const SEALED_TAG_LEN: usize = 16;
+
+/// Writes the clipboards matching `filter` into a zip archive at `path`.
+///
+/// Without a passphrase clipboards are stored as they are in the database, encrypted
+/// ones stay encrypted and only import where the same password unlocks the history.
+/// With a passphrase they are decrypted with the local key and
+/// the whole zip is sealed with a key derived from the passphrase instead, so the
+/// archive opens on any device that knows the passphrase.
+pub async fn export_archive_db(
+    path: &Path,
+    filter: ArchiveFilter,
+    passphrase: Option<String>,
+) -> Result<usize, CommandError> {
+    if passphrase.is_some() {
+        ensure_key_for_encrypted_history()?;
+    }
+
+    let clipboards = clipboard::Entity::find()
+        .apply_if(filter.star, |q, star| {
+            q.filter(clipboard::Column::Star.eq(star))
+        })
+        .apply_if(filter.from, |q, from| {
+            q.filter(clipboard::Column::CreatedAt.gte(from))
+        })
+        .apply_if(filter.to, |q, to| q.filter(clipboard::Column::CreatedAt.lte(to)))
+        .apply_if(filter.r#type.clone(), |q, r#type| {
+            q.filter(clipboard::Column::Types.contains(r#type.to_string()))
+        })
+        .order_by_asc(clipboard::Column::Id)
+        .all(db())
+        .await?;
+
+    let total = clipboards.len();
+    let manifest = ArchiveManifest::new(env!("CARGO_PKG_VERSION"), total, filter);
+
+    let file = BufWriter::new(File::create(path)?);
+    match passphrase {
+        Some(passphrase) => {
+            let writer = SealingWriter::new(file, &passphrase)?;
+            let writer = ArchiveWriter::new_flushing(writer, &manifest)?;
+            write_archive(writer, clipboards, true)
+                .await?
+                .finish()?
+                .flush()?;
+        }
+        None => {
+            let writer = ArchiveWriter::new(file, &manifest)?;
+            write_archive(writer, clipboards, false).await?.flush()?;
+        }
+    }
+
+    printlog!("exported {} clipboards to {}", total, path.display());
+
+    Ok(total)
+}
+
+async fn write_archive<W: Write + Seek>(
+    mut writer: ArchiveWriter<W>,
+    clipboards: Vec<clipboard::Model>,
+    decrypt: bool,
+) -> Result<W, CommandError> {
+    let total = clipboards.len();
+
+    // Blobs can be large, only a batch of clipboards is held in memory at once
+    let mut current = 0;
+    for batch in clipboards.chunks(ARCHIVE_BATCH_SIZE) {
+        for mut clipboard in load_clipboards_for_search(batch.to_vec()).await? {
+            if decrypt && clipboard.clipboard.encrypted {
+                load_clipboard_blobs(db(), std::slice::from_mut(&mut clipboard)).await?;
+                let clipboard = decrypt_clipboard(clipboard).map_err(|e| {
+                    CommandError::Error(format!("Failed to decrypt clipboard: {}", e))
+                })?;
+                writer.add_clipboard(clipboard)?;
+            } else {
+                // Everything else is streamed from the blobs as it is stored
+                let blobs = clipboard
+                    .image
+                    .iter()
+                    .map(|image| (image.id, image.blob_hash.as_deref()))
+                    .chain(
+                        clipboard
+                            .files
+                            .iter()
+                            .map(|file| (file.id, file.blob_hash.as_deref())),
+                    );
+                for (id, hash) in blobs {
+                    writer.add_blob(id, &mut open_blob_db(hash).await?)?;
+                }
+                writer.add_clipboard_entry(&clipboard)?;
+            }
+
+            current += 1;
+            emit_archive_progress("SETTINGS.BACKUP.EXPORTING_CLIPBOARDS", total, current, None);
+        }
+    }
+
+    Ok(writer.finish()?)
+}
+
+/// Merges an archive into the history by UUID. New clipboards are inserted, known
+/// ones are kept: identical content counts as duplicate, different content as conflict
+/// where only the star and a missing name are taken over from the archive.
+///
+/// Sealed archives need their passphrase. Clipboards arrive in plaintext or encrypted,
+/// encrypted ones have to open with the local key and count as failed otherwise. All
+/// are stored under the local key when the history is encrypted.
+pub async fn import_archive_db(
+    path: &Path,
+    passphrase: Option<String>,
+) -> Result<ImportSummary, CommandError> {
+    ensure_key_for_encrypted_history()?;
+
+    let mut file = File::open(path)?;
+
+    let summary = if is_sealed_archive(&mut file)? {
+        let passphrase = passphrase.ok_or(ArchiveError::PassphraseRequired)?;
+        let mut sealed = OpeningReader::new(BufReader::new(file), &passphrase)?;
+
+        // The zip reader has to seek, the opened zip goes through a temporary file
+        let temp =
+            TempPath(std::env::temp_dir().join(format!("clippy-import-{}.zip", Uuid::now_v7())));
+        let mut zip = BufWriter::new(
+            File::options()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&temp.0)?,
+        );
+        io::copy(&mut sealed, &mut zip).map_err(archive_error)?;
+
+        let mut zip = zip.into_inner().map_err(|e| e.into_error())?;
+        zip.seek(SeekFrom::Start(0))?;
+        import_archive(ArchiveReader::new(BufReader::new(zip))?).await?
+    } else {
+        import_archive(ArchiveReader::new(BufReader::new(file))?).await?
+    };
+
+    printlog!(
+        "imported {} clipboards from {}, duplicates: {} conflicts: {} failed: {}",
+        summary.imported,
+        path.display(),
+        summary.duplicates,
+        summary.conflicts,
+        summary.failed
+    );
+
+    if summary.imported > 0 {
+        get_cache().invalidate_all();
+
+        if !is_headless() {
+            init_clipboards();
+        }
+    }
+
+    Ok(summary)
+}
+
+async fn import_archive<R: Read + Seek>(
+    mut reader: ArchiveReader<R>,
+) -> Result<ImportSummary, CommandError> {
+    let ids = reader.clipboard_ids();
+    let local_ids = get_clipboard_uuids_db().await?;
+    let encrypt = get_global_settings().encryption;
+
+    let total = ids.len();
+    let mut summary = ImportSummary::default();
+
+    for (index, id) in ids.into_iter().enumerate() {
+        match readable(reader.read_clipboard(id)?) {
+            None => {
+                printlog!("clipboard {} doesn't open with the local key, skipped", id);
+                summary.failed += 1;
+            }
+            Some(clipboard) if local_ids.contains_key(&id) => {
+                let local = get_clipboard_db(id).await?;
+                let local = readable(local.clone()).unwrap_or(local);
+
+                if has_same_content(&local, &clipboard) {
+                    summary.duplicates += 1;
+                } else {
+                    summary.conflicts += 1;
+                }
+
+                merge_clipboard_meta(&local, &clipboard).await?;
+            }
+            Some(clipboard) => {
+                let clipboard = if encrypt {
+                    encrypt_clipboard(clipboard)
+                } else {
+                    clipboard
+                };
+
+                upsert_clipboard_dto(clipboard).await?;
+                summary.imported += 1;
+            }
+        }
+
+        emit_archive_progress(
+            "SETTINGS.BACKUP.IMPORTING_CLIPBOARDS",
+            total,
+            index + 1,
+            Some(&summary),
+        );
+    }
+
+    Ok(summary)
+}
+
+/// Plaintext form of a clipboard, `None` if it is encrypted and the local key doesn't
+/// open it, like one exported unsealed on a device with another password.
+fn readable(clipboard: FullClipboardDto) -> Option<FullClipboardDto> {
+    if !clipboard.clipboard.encrypted {
+        return Some(clipboard);
+    }
+
+    decrypt_clipboard(clipboard).ok()
+}
+
+/// Sealed exports and imports into an encrypted history both need the local key.
+fn ensure_key_for_encrypted_history() -> Result<(), CommandError> {
+    if get_global_settings().encryption && !is_encryption_key_set() {
+        return Err(CommandError::Error(
+            "Clipboard history is encrypted, unlock it first".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Star wins over no star and a name fills a missing one, the content stays local.
+async fn merge_clipboard_meta(
+    local: &FullClipboardDto,
+    archived: &FullClipboardDto,
+) -> Result<(), DbErr> {
+    let star = local.clipboard.star || archived.clipboard.star;
+    let name = local
+        .clipboard
+        .name
+        .clone()
+        .or_else(|| archived.clipboard.name.clone());
+
+    if star == local.clipboard.star && name == local.clipboard.name {
+        return Ok(());
+    }
+
+    clipboard::Entity::update(clipboard::ActiveModel {
+        id: Set(local.clipboard.id),
+        star: Set(star),
+        name: Set(name),
+        updated_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    })
+    .exec(db())
+    .await?;
+
+    Ok(())
+}
+
+fn has_same_content(a: &FullClipboardDto, b: &FullClipboardDto) -> bool {
+    a.clipboard.types == b.clipboard.types
+        && a.clipboard.encrypted == b.clipboard.encrypted
+        && a.text.as_ref().map(|t| &t.data) == b.text.as_ref().map(|t| &t.data)
+        && a.html.as_ref().map(|h| &h.data) == b.html.as_ref().map(|h| &h.data)
+        && a.rtf.as_ref().map(|r| &r.data) == b.rtf.as_ref().map(|r| &r.data)
+        && a.image.as_ref().map(|i| &i.data) == b.image.as_ref().map(|i| &i.data)
+        && a.files.len() == b.files.len()
+        && a
+            .files
+            .iter()
+            .zip(&b.files)
+            .all(|(a, b)| a.name == b.name && a.data == b.data)
+}
+
+fn emit_archive_progress(
+    label: &str,
+    total: usize,
+    current: usize,
+    summary: Option<&ImportSummary>,
+) {
+    // Headless CLI runs have no window to report to
+    if is_headless() {
+        return;
+    }
+
+    get_app()
+        .emit_to(
+            EventTarget::any(),
+            ListenEvent::Progress.to_string().as_str(),
+            Progress {
+                label: label.to_string(),
+                total,
+                current,
+                duplicates: summary.map(|s| s.duplicates),
+                conflicts: summary.map(|s| s.conflicts),
+            },
+        )
+        .ok();
+}
+
+/// Sealed archive layout, the zip is split into chunks so no chunk nonce repeats:
+/// - header: magic, version (u8), PBKDF2 iterations (u32 BE), random salt
+/// - chunks: ciphertext length (u32 BE) followed by AES-256-GCM ciphertext + tag
+///
+/// The nonce is the chunk counter with the last byte marking the final chunk, which
+/// together with the header as AAD rejects reordered, swapped or cut off chunks.
+///
+/// Chunks are sealed while the zip is written. The zip writer goes back to the header
+/// of an entry once its data is written, so bytes are held until it flushes after the
+/// entry and only whole chunks are sealed then. `finish` seals the rest as final chunk.
+struct SealingWriter<W: Write> {
+    writer: W,
+    key: aead::LessSafeKey,
+    header: Vec<u8>,
+    /// Not sealed yet, starts at offset `sealed` of the zip
+    buffer: Vec<u8>,
+    sealed: u64,
+    position: u64,
+    index: u64,
+}
+
+impl<W: Write> SealingWriter<W> {
+    fn new(mut writer: W, passphrase: &str) -> Result<Self, CommandError> {
+        let mut salt = [0u8; SEALED_SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| CommandError::new("Failed to generate archive salt"))?;
+
+        let mut header = Vec::with_capacity(SEALED_HEADER_LEN);
+        header.extend_from_slice(SEALED_ARCHIVE_MAGIC);
+        header.push(SEALED_ARCHIVE_VERSION);
+        header.extend_from_slice(&PBKDF2_ITERATIONS.to_be_bytes());
+        header.extend_from_slice(&salt);
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer,
+            key: sealing_key(passphrase, &salt, PBKDF2_ITERATIONS)?,
+            header,
+            buffer: Vec::new(),
+            sealed: 0,
+            position: 0,
+            index: 0,
+        })
+    }
+
+    fn seal_chunk(&mut self, len: usize, last: bool) -> io::Result<()> {
+        let mut in_out: Vec<u8> = self.buffer.drain(..len).collect();
+        self.key
+            .seal_in_place_append_tag(
+                chunk_nonce(self.index, last),
+                aead::Aad::from(&self.header),
+                &mut in_out,
+            )
+            .map_err(|_| io::Error::other("Failed to seal archive"))?;
+
+        self.writer
+            .write_all(&(in_out.len() as u32).to_be_bytes())?;
+        self.writer.write_all(&in_out)?;
+
+        self.sealed += len as u64;
+        self.index += 1;
+        Ok(())
+    }
+
+    /// An empty rest still becomes the final chunk, otherwise truncation goes unnoticed.
+    fn finish(mut self) -> io::Result<W> {
+        self.seal_chunk(self.buffer.len(), true)?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for SealingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.position < self.sealed {
+            return Err(io::Error::other("Archive part is sealed already"));
+        }
+
+        let offset = (self.position - self.sealed) as usize;
+        let overwrite = buf.len().min(self.buffer.len() - offset);
+        self.buffer[offset..offset + overwrite].copy_from_slice(&buf[..overwrite]);
+        self.buffer.extend_from_slice(&buf[overwrite..]);
+
+        self.position += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    /// Keeps at least one byte back, only `finish` knows which chunk is the last.
+    fn flush(&mut self) -> io::Result<()> {
+        while self.buffer.len() > SEALED_ARCHIVE_CHUNK_SIZE {
+            self.seal_chunk(SEALED_ARCHIVE_CHUNK_SIZE, false)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Seek for SealingWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let end = self.sealed + self.buffer.len() as u64;
+        let position = match pos {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => end.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) if (self.sealed..=end).contains(&position) => {
+                self.position = position;
+                Ok(position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Can't seek outside the unsealed part of the archive",
+            )),
+        }
+    }
+}
+
+/// The zip writer only needs to read back for features the archive doesn't use.
+impl<W: Write> Read for SealingWriter<W> {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Sealed archives can't be read while written",
+        ))
+    }
+}
+
+/// Opens a sealed archive chunk by chunk as it is read, see `SealingWriter` for the
+/// layout. A chunk is only the last one when nothing follows it.
+struct OpeningReader<R: Read> {
+    reader: R,
+    key: aead::LessSafeKey,
+    header: Vec<u8>,
+    /// Length of the next chunk, `None` once the final chunk was opened
+    next_len: Option<usize>,
+    plain: Vec<u8>,
+    offset: usize,
+    index: u64,
+}
+
+impl<R: Read> OpeningReader<R> {
+    fn new(mut reader: R, passphrase: &str) -> Result<Self, ArchiveError> {
+        let mut header = vec![0u8; SEALED_HEADER_LEN];
+        reader.read_exact(&mut header).map_err(cut_off)?;
+        let mut offset = SEALED_ARCHIVE_MAGIC.len();
+
+        let version = header[offset];
+        if version > SEALED_ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version as u32));
+        }
+        offset += 1;
+
+        let iterations = u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+        if iterations == 0 || iterations > SEALED_MAX_ITERATIONS {
+            return Err(ArchiveError::Corrupt);
+        }
+        offset += 4;
+
+        let key = sealing_key(passphrase, &header[offset..], iterations)
+            .map_err(|_| ArchiveError::Corrupt)?;
+
+        let mut opener = Self {
+            reader,
+            key,
+            header,
+            next_len: None,
+            plain: Vec::new(),
+            offset: 0,
+            index: 0,
+        };
+        opener.next_len = Some(opener.read_len()?.ok_or(ArchiveError::Corrupt)?);
+
+        Ok(opener)
+    }
+
+    /// `None` at the end of the file.
+    fn read_len(&mut self) -> Result<Option<usize>, ArchiveError> {
+        let mut len = [0u8; 4];
+        let mut filled = 0;
+        while filled < len.len() {
+            match self.reader.read(&mut len[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(ArchiveError::Corrupt),
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let len = u32::from_be_bytes(len) as usize;
+        if len < SEALED_TAG_LEN || len > SEALED_ARCHIVE_CHUNK_SIZE + SEALED_TAG_LEN {
+            return Err(ArchiveError::Corrupt);
+        }
+
+        Ok(Some(len))
+    }
+
+    fn open_next_chunk(&mut self, len: usize) -> Result<(), ArchiveError> {
+        let mut chunk = vec![0u8; len];
+        self.reader.read_exact(&mut chunk).map_err(cut_off)?;
+
+        self.next_len = self.read_len()?;
+        let last = self.next_len.is_none();
+
+        let mut in_out = chunk.clone();
+        let Ok(plain) = self.key.open_in_place(
+            chunk_nonce(self.index, last),
+            aead::Aad::from(&self.header),
+            &mut in_out,
+        ) else {
+            // The first chunk opening with the wrong final flag means the passphrase
+            // is right but the rest of the file is missing
+            let cut_off = self.index == 0
+                && self
+                    .key
+                    .open_in_place(
+                        chunk_nonce(0, !last),
+                        aead::Aad::from(&self.header),
+                        &mut chunk,
+                    )
+                    .is_ok();
+
+            if self.index == 0 && !cut_off {
+                return Err(ArchiveError::InvalidPassphrase);
+            }
+            return Err(ArchiveError::Corrupt);
+        };
+
+        self.plain = plain.to_vec();
+        self.offset = 0;
+        self.index += 1;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for OpeningReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.plain.len() {
+            let Some(len) = self.next_len else {
+                return Ok(0);
+            };
+            self.open_next_chunk(len)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+
+        let read = buf.len().min(self.plain.len() - self.offset);
+        buf[..read].copy_from_slice(&self.plain[self.offset..self.offset + read]);
+        self.offset += read;
+        Ok(read)
+    }
+}
+
+fn cut_off(e: io::Error) -> ArchiveError {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => ArchiveError::Corrupt,
+        _ => ArchiveError::Io(e),
+    }
+}
+
+/// Unwraps the errors `OpeningReader` hands through `io::copy`.
+fn archive_error(e: io::Error) -> ArchiveError {
+    if e.get_ref().is_some_and(|inner| inner.is::<ArchiveError>()) {
+        *e.into_inner().unwrap().downcast().unwrap()
+    } else {
+        ArchiveError::Io(e)
+    }
+}
+
+/// Removes the file when dropped.
+struct TempPath(PathBuf);
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+fn is_sealed_archive(file: &mut File) -> Result<bool, CommandError> {
+    let mut magic = vec![0u8; SEALED_ARCHIVE_MAGIC.len()];
+    let sealed = match file.read_exact(&mut magic) {
+        Ok(()) => magic == SEALED_ARCHIVE_MAGIC,
+        Err(_) => false,
+    };
+
+    file.seek(SeekFrom::Start(0))?;
+    Ok(sealed)
+}
+
+fn sealing_key(
+    passphrase: &str,
+    salt: &[u8],
+    iterations: u32,
+) -> Result<aead::LessSafeKey, CommandError> {
+    let key = derive_key(passphrase, salt, iterations);
+    let unbound_key = aead::UnboundKey::new(&aead::AES_256_GCM, &key.0)
+        .map_err(|_| CommandError::new("Invalid archive key"))?;
+
+    Ok(aead::LessSafeKey::new(unbound_key))
+}
+
+fn chunk_nonce(index: u64, last: bool) -> aead::Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[..8].copy_from_slice(&index.to_be_bytes());
+    nonce[11] = last as u8;
+    aead::Nonce::assume_unique_for_key(nonce)
+}
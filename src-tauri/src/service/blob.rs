@@ -0,0 +1,322 @@
+use super::settings::{get_global_settings, update_settings_external_blob_store_db};
+use crate::prelude::*;
+use crate::tao::config::get_data_path;
+use crate::tao::connection::db;
+use crate::tao::global::get_app;
+use chrono::Utc;
+use common::constants::{BLOB_STORE_DIR, EXTERNAL_BLOB_MIN_SIZE};
+use common::types::enums::ListenEvent;
+use common::types::orm_query::FullClipboardDto;
+use common::types::types::{CommandError, Progress};
+use entity::blob;
+use sea_orm::{
+    prelude::Expr, sea_query::OnConflict, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter,
+    QuerySelect, QueryTrait, TransactionTrait,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor, Read};
+use std::path::PathBuf;
+use tauri::{Emitter, EventTarget};
+
+/// Stores `data` under `hash` unless that blob exists already and returns the hash, see
+/// `blob_key` for how it's computed. The reference is taken by writing an image or file row with that `blob_hash`, the
+/// triggers on those tables keep `ref_count` in step and drop unreferenced blobs.
+///
+/// Large blobs go to the external store when it's enabled. `db` has to be a transaction
+/// that wrote already, holding the write lock keeps `purge_blobs_db` from removing the
+/// file before the row pointing at it is committed.
+pub async fn insert_blob_db<C: ConnectionTrait>(
+    db: &C,
+    hash: String,
+    data: &[u8],
+) -> Result<String, DbErr> {
+    let exists = blob::Entity::find_by_id(&hash)
+        .select_only()
+        .column(blob::Column::Hash)
+        .into_tuple::<String>()
+        .one(db)
+        .await?
+        .is_some();
+    if exists {
+        return Ok(hash);
+    }
+
+    let external =
+        get_global_settings().external_blob_store && data.len() >= EXTERNAL_BLOB_MIN_SIZE;
+    if external {
+        write_blob_file(&hash, data)?;
+    }
+
+    blob::Entity::insert(blob::ActiveModel {
+        hash: Set(hash.clone()),
+        data: Set(if external { Vec::new() } else { data.to_vec() }),
+        size: Set(data.len() as i32),
+        ref_count: Set(0),
+        created_at: Set(Utc::now().naive_utc()),
+        external: Set(external),
+    })
+    .on_conflict(
+        OnConflict::column(blob::Column::Hash)
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+
+    Ok(hash)
+}
+
+/// Fills `data` of the loaded image and file rows from their blobs, so nothing outside
+/// the db layer has to know about them.
+pub async fn load_clipboard_blobs<C: ConnectionTrait>(
+    db: &C,
+    clipboards: &mut [FullClipboardDto],
+) -> Result<(), DbErr> {
+    let hashes: HashSet<String> = clipboards
+        .iter()
+        .flat_map(|clipboard| {
+            let image = clipboard.image.iter().map(|image| &image.blob_hash);
+            let files = clipboard.files.iter().map(|file| &file.blob_hash);
+            image.chain(files).flatten().cloned()
+        })
+        .collect();
+
+    if hashes.is_empty() {
+        return Ok(());
+    }
+
+    let blobs: HashMap<String, Vec<u8>> = blob::Entity::find()
+        .filter(blob::Column::Hash.is_in(hashes))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|blob| {
+            let data = if blob.external {
+                read_blob_file(&blob.hash)?
+            } else {
+                blob.data
+            };
+            Ok((blob.hash, data))
+        })
+        .collect::<Result<_, DbErr>>()?;
+
+    for clipboard in clipboards {
+        if let Some(image) = &mut clipboard.image {
+            if let Some(data) = image.blob_hash.take().and_then(|hash| blobs.get(&hash)) {
+                image.data = data.clone();
+            }
+        }
+
+        for file in &mut clipboard.files {
+            if let Some(data) = file.blob_hash.take().and_then(|hash| blobs.get(&hash)) {
+                file.data = data.clone();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reader over the blob `hash` of a row loaded without its `data`, external blobs are
+/// streamed from their file instead of being read into memory.
+pub async fn open_blob_db(hash: Option<&str>) -> Result<Box<dyn Read + Send>, DbErr> {
+    let Some(hash) = hash else {
+        return Ok(Box::new(std::io::empty()));
+    };
+
+    let blob = blob::Entity::find_by_id(hash)
+        .one(db())
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("blob {} not found", hash)))?;
+
+    if blob.external {
+        let file = File::open(blob_path(hash)).map_err(|e| DbErr::Custom(e.to_string()))?;
+        Ok(Box::new(BufReader::new(file)))
+    } else {
+        Ok(Box::new(Cursor::new(blob.data)))
+    }
+}
+
+/// Removes external blobs no row points at anymore together with their files, the
+/// triggers only drop the ones kept in the db.
+pub async fn purge_blobs_db() -> Result<(), DbErr> {
+    let txn = db().begin().await?;
+
+    // Deleting first takes the write lock, so no transaction can be about to point a
+    // new row at one of these blobs while their files go
+    let purged = blob::Entity::delete_many()
+        .filter(blob::Column::External.eq(true))
+        .filter(blob::Column::RefCount.lte(0))
+        .exec_with_returning(&txn)
+        .await?;
+
+    for blob in &purged {
+        remove_blob_file(&blob.hash);
+    }
+
+    txn.commit().await?;
+
+    if !purged.is_empty() {
+        printlog!("purged {} external blobs", purged.len());
+    }
+
+    Ok(())
+}
+
+/// Moves the blobs from `EXTERNAL_BLOB_MIN_SIZE` on out to the external store, or all
+/// external ones back into the db, and keeps storing new blobs that way. Returns the
+/// number of blobs moved.
+pub async fn migrate_blob_store_db(external: bool) -> Result<usize, CommandError> {
+    update_settings_external_blob_store_db(external).await?;
+
+    let hashes: Vec<String> = blob::Entity::find()
+        .select_only()
+        .column(blob::Column::Hash)
+        .filter(blob::Column::External.eq(!external))
+        .apply_if(external.then_some(EXTERNAL_BLOB_MIN_SIZE), |q, size| {
+            q.filter(blob::Column::Size.gte(size as i32))
+        })
+        .into_tuple()
+        .all(db())
+        .await?;
+
+    let total = hashes.len();
+    for (index, hash) in hashes.iter().enumerate() {
+        if external {
+            let Some(blob) = blob::Entity::find_by_id(hash).one(db()).await? else {
+                continue;
+            };
+
+            write_blob_file(hash, &blob.data)?;
+            let result = blob::Entity::update_many()
+                .col_expr(blob::Column::Data, Expr::value(Vec::<u8>::new()))
+                .col_expr(blob::Column::External, Expr::value(true))
+                .filter(blob::Column::Hash.eq(hash))
+                .filter(blob::Column::External.eq(false))
+                .exec(db())
+                .await?;
+
+            // Deleted in the meantime
+            if result.rows_affected == 0 {
+                remove_blob_file(hash);
+            }
+        } else {
+            // Purged in the meantime
+            let Ok(data) = fs::read(blob_path(hash)) else {
+                continue;
+            };
+
+            let result = blob::Entity::update_many()
+                .col_expr(blob::Column::Data, Expr::value(data))
+                .col_expr(blob::Column::External, Expr::value(false))
+                .filter(blob::Column::Hash.eq(hash))
+                .filter(blob::Column::External.eq(true))
+                .exec(db())
+                .await?;
+
+            if result.rows_affected > 0 {
+                remove_blob_file(hash);
+            }
+        }
+
+        get_app()
+            .emit_to(
+                EventTarget::any(),
+                ListenEvent::Progress.to_string().as_str(),
+                Progress {
+                    label: "SETTINGS.BLOB_STORE.MOVING_BLOBS".to_string(),
+                    total,
+                    current: index + 1,
+                    ..Default::default()
+                },
+            )
+            .ok();
+    }
+
+    purge_blobs_db().await?;
+
+    printlog!(
+        "moved {} blobs {} the external store",
+        total,
+        if external { "to" } else { "out of" }
+    );
+
+    Ok(total)
+}
+
+/// Cleans up the external blob store before anything stores clipboards.
+pub fn setup_blob_store() {
+    tokio::task::block_in_place(|| {
+        tokio::runtime::Handle::current().block_on(async {
+            if let Err(e) = cleanup_blob_store().await {
+                printlog!("failed to clean up the blob store: {}", e);
+            }
+        })
+    });
+}
+
+/// Purges unreferenced external blobs and removes files without a blob row, left behind
+/// when the app quit between writing a file and committing its row.
+async fn cleanup_blob_store() -> Result<(), DbErr> {
+    purge_blobs_db().await?;
+
+    let Ok(entries) = fs::read_dir(blob_store_path()) else {
+        return Ok(());
+    };
+
+    let external: HashSet<String> = blob::Entity::find()
+        .select_only()
+        .column(blob::Column::Hash)
+        .filter(blob::Column::External.eq(true))
+        .into_tuple::<String>()
+        .all(db())
+        .await?
+        .into_iter()
+        .collect();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !external.contains(&name) {
+            printlog!("removing orphaned blob file {}", name);
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+fn blob_store_path() -> PathBuf {
+    PathBuf::from(get_data_path().config_path).join(BLOB_STORE_DIR)
+}
+
+fn blob_path(hash: &str) -> PathBuf {
+    blob_store_path().join(hash)
+}
+
+/// Blob bytes are written as they are, the ones of encrypted clipboards are AES-GCM
+/// ciphertext already. Goes through a temporary file so a crash never leaves a torn blob.
+fn write_blob_file(hash: &str, data: &[u8]) -> Result<(), DbErr> {
+    let path = blob_path(hash);
+    let temp = path.with_extension("tmp");
+
+    fs::create_dir_all(blob_store_path())
+        .and_then(|_| fs::write(&temp, data))
+        .and_then(|_| fs::rename(&temp, &path))
+        .map_err(|e| DbErr::Custom(format!("Failed to write blob {}: {}", hash, e)))
+}
+
+/// A missing or unreadable file fails the load, an empty image or file would look like
+/// valid content and could be synced or exported as such.
+fn read_blob_file(hash: &str) -> Result<Vec<u8>, DbErr> {
+    fs::read(blob_path(hash))
+        .map_err(|e| DbErr::Custom(format!("Failed to read blob {}: {}", hash, e)))
+}
+
+fn remove_blob_file(hash: &str) {
+    if let Err(e) = fs::remove_file(blob_path(hash)) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            printlog!("failed to remove blob {}: {}", hash, e);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use super::{
-    clipboard::load_clipboards_with_relations,
+    clipboard::{load_clipboards_with_relations, setup_content_hashes},
     decrypt::{decrypt_all_clipboards, decrypt_all_clipboards_streaming, decrypt_clipboard},
     encrypt::encrypt_all_clipboards,
     hotkey::init_hotkey_event,
@@ -19,7 +19,7 @@ use std::num::NonZeroU32;
 use tauri::{Emitter, EventTarget};
 
 const PBKDF2_SALT: &[u8] = b"clippy";
-const PBKDF2_ITERATIONS: u32 = 600_000;
+pub const PBKDF2_ITERATIONS: u32 = 600_000;
 
 /// Sets the encryption key from the password and verifies it by test-decrypting one
 /// stored clipboard, falling back to the legacy SHA-256 key for migration. Returns
@@ -38,7 +38,7 @@ async fn set_key_and_verify_password(password: &str) -> Result<bool, CommandErro
         return Ok(false);
     };
 
-    let mut clipboards = load_clipboards_with_relations(vec![clipboard]).await;
+    let mut clipboards = load_clipboards_with_relations(vec![clipboard]).await?;
     let test_clipboard = clipboards.remove(0);
 
     // Try PBKDF2 key first, fall back to legacy SHA-256 for migration.
@@ -89,6 +89,9 @@ pub async fn handle_password_unlock(
         }
     }
 
+    // Encrypted clipboards can be hashed now that the key is set
+    setup_content_hashes();
+
     init_hotkey_event();
 
     Ok(())
@@ -154,20 +157,28 @@ pub fn verify_encryption_password(password: String) -> Result<bool, EncryptionEr
 
 /// Sets the encryption key derived from a password using PBKDF2
 pub fn set_encryption_key(password: &str) -> Result<(), EncryptionError> {
+    let key = derive_key(password, PBKDF2_SALT, PBKDF2_ITERATIONS);
+
+    *ENCRYPTION_KEY
+        .lock()
+        .map_err(|_| EncryptionError::KeyLockFailed)? = Some(key);
+
+    Ok(())
+}
+
+/// PBKDF2-HMAC-SHA256 key from a password, also used for sealed export archives
+/// which bring their own salt and iteration count.
+pub fn derive_key(password: &str, salt: &[u8], iterations: u32) -> EncryptionKeyData {
     let mut key_bytes = [0u8; 32];
     ring::pbkdf2::derive(
         ring::pbkdf2::PBKDF2_HMAC_SHA256,
-        NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
-        PBKDF2_SALT,
+        NonZeroU32::new(iterations).expect("PBKDF2 iterations must not be zero"),
+        salt,
         password.as_bytes(),
         &mut key_bytes,
     );
 
-    *ENCRYPTION_KEY
-        .lock()
-        .map_err(|_| EncryptionError::KeyLockFailed)? = Some(EncryptionKeyData(key_bytes));
-
-    Ok(())
+    EncryptionKeyData(key_bytes)
 }
 
 /// Legacy SHA-256 key derivation for migrating existing encrypted users
@@ -0,0 +1,578 @@
+use super::archive::{export_archive_db, import_archive_db};
+use super::cipher::is_encryption_key_set;
+use super::clipboard::{
+    copy_clipboard_from_id, delete_clipboards_db, filter_clipboards, get_clipboard_db,
+    get_clipboards_db, init_clipboards, insert_clipboard_dbo, load_clipboards_for_search,
+    star_clipboard_db,
+};
+use super::decrypt::{decrypt_clipboard, decrypt_clipboard_search, read_encryption_key};
+use super::settings::get_global_settings;
+use crate::prelude::*;
+use crate::tao::connection::db;
+use crate::tao::global::is_headless;
+use crate::utils::clipboard_manager::ClipboardManagerExt;
+use chrono::{NaiveDate, NaiveDateTime};
+use common::io::clipboard::trim_clipboard_data;
+use common::types::archive::ArchiveFilter;
+use common::types::enums::ClipboardType;
+use common::types::orm_query::{FullClipboardDbo, FullClipboardDto};
+use common::types::types::CommandError;
+use entity::clipboard;
+use sea_orm::prelude::Uuid;
+use sea_orm::{EntityTrait, PaginatorTrait, QueryOrder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+
+pub const CLI_USAGE: &str = "Usage: clippy <command> [options]
+
+Commands:
+  list [--limit N] [--star] [--img] [--json]          Show the latest clipboards
+  search <query> [--limit N] [--star] [--img] [--json] Search like the search bar
+  get <uuid> [--json]                                   Print the content of a clipboard
+  copy <uuid> [--type text|html|rtf|image|file]         Copy a clipboard (needs the running app)
+  add --stdin                                           Add the text read from stdin
+  star <uuid> [--off]                                   Star or unstar a clipboard
+  delete <uuid>...                                      Delete clipboards
+  export <file> [--star] [--from DATE] [--to DATE] [--type T] [--passphrase]
+                                                        Write the history to a zip archive
+  import <file> [--passphrase]                          Merge an archive into the history
+
+--passphrase reads the archive passphrase from the first line of stdin and seals
+(export) or opens (import) the archive with it.
+
+Without a running Clippy the database is opened directly.";
+
+const DEFAULT_LIST_LIMIT: u64 = 25;
+const PREVIEW_LENGTH: usize = 80;
+/// Clipboards decrypted at once when listing an encrypted history
+const LIST_PAGE_SIZE: u64 = 64;
+
+/// A `clippy <subcommand>` invocation. Parsed in the CLI process and, when the app is
+/// already running, handed over to it as JSON through the single-instance channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum CliCommand {
+    List {
+        search: Option<String>,
+        limit: u64,
+        star: Option<bool>,
+        img: Option<bool>,
+        json: bool,
+    },
+    Get {
+        id: Uuid,
+        json: bool,
+    },
+    Copy {
+        id: Uuid,
+        r#type: Option<ClipboardType>,
+    },
+    Add {
+        text: String,
+    },
+    Star {
+        id: Uuid,
+        star: bool,
+    },
+    Delete {
+        ids: Vec<Uuid>,
+    },
+    Export {
+        path: PathBuf,
+        filter: ArchiveFilter,
+        passphrase: Option<String>,
+    },
+    Import {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+}
+
+/// Returns `None` when the arguments are no subcommand, e.g. a regular (auto)start.
+pub fn parse_cli_args(args: &[String]) -> Option<Result<CliCommand, String>> {
+    let (subcommand, rest) = args.split_first()?;
+
+    let command = match subcommand.as_str() {
+        "list" => parse_list(rest, false),
+        "search" => parse_list(rest, true),
+        "get" => CliArgs::parse(rest, &["--json"], &[]).and_then(|args| {
+            Ok(CliCommand::Get {
+                id: args.single_uuid()?,
+                json: args.has("--json"),
+            })
+        }),
+        "copy" => CliArgs::parse(rest, &[], &["--type"]).and_then(|args| {
+            Ok(CliCommand::Copy {
+                id: args.single_uuid()?,
+                r#type: args.value("--type").map(parse_type).transpose()?,
+            })
+        }),
+        "add" => CliArgs::parse(rest, &["--stdin"], &[]).and_then(|args| {
+            if !args.has("--stdin") || !args.positional.is_empty() {
+                return Err("add reads the clipboard from stdin, pass --stdin".to_string());
+            }
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| format!("Failed to read stdin: {}", e))?;
+            Ok(CliCommand::Add { text })
+        }),
+        "star" => CliArgs::parse(rest, &["--off"], &[]).and_then(|args| {
+            Ok(CliCommand::Star {
+                id: args.single_uuid()?,
+                star: !args.has("--off"),
+            })
+        }),
+        "delete" => CliArgs::parse(rest, &[], &[]).and_then(|args| {
+            if args.positional.is_empty() {
+                return Err("delete needs at least one uuid".to_string());
+            }
+            let ids = args
+                .positional
+                .iter()
+                .map(|id| parse_uuid(id))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(CliCommand::Delete { ids })
+        }),
+        "export" => CliArgs::parse(
+            rest,
+            &["--star", "--passphrase"],
+            &["--from", "--to", "--type"],
+        )
+        .and_then(|args| {
+            let filter = ArchiveFilter {
+                star: args.has("--star").then_some(true),
+                from: args.value("--from").map(|v| parse_date(v, false)).transpose()?,
+                to: args.value("--to").map(|v| parse_date(v, true)).transpose()?,
+                r#type: args.value("--type").map(parse_type).transpose()?,
+            };
+            Ok(CliCommand::Export {
+                path: args.single_path()?,
+                filter,
+                passphrase: read_passphrase(&args)?,
+            })
+        }),
+        "import" => CliArgs::parse(rest, &["--passphrase"], &[]).and_then(|args| {
+            Ok(CliCommand::Import {
+                path: args.single_path()?,
+                passphrase: read_passphrase(&args)?,
+            })
+        }),
+        _ => return None,
+    };
+
+    Some(command)
+}
+
+fn parse_list(rest: &[String], search: bool) -> Result<CliCommand, String> {
+    let args = CliArgs::parse(rest, &["--star", "--img", "--json"], &["--limit"])?;
+
+    let query = match (search, args.positional.is_empty()) {
+        (true, true) => return Err("search needs a query".to_string()),
+        (true, false) => Some(args.positional.join(" ")),
+        (false, true) => None,
+        (false, false) => return Err(format!("Unexpected argument: {}", args.positional[0])),
+    };
+
+    let limit = match args.value("--limit") {
+        Some(limit) => limit
+            .parse::<u64>()
+            .ok()
+            .filter(|limit| *limit > 0)
+            .ok_or_else(|| format!("Invalid limit: {}", limit))?,
+        None => DEFAULT_LIST_LIMIT,
+    };
+
+    Ok(CliCommand::List {
+        search: query,
+        limit,
+        star: args.has("--star").then_some(true),
+        img: args.has("--img").then_some(true),
+        json: args.has("--json"),
+    })
+}
+
+struct CliArgs {
+    positional: Vec<String>,
+    switches: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl CliArgs {
+    fn parse(args: &[String], switches: &[&str], options: &[&str]) -> Result<Self, String> {
+        let mut parsed = CliArgs {
+            positional: Vec::new(),
+            switches: Vec::new(),
+            options: HashMap::new(),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if switches.contains(&arg.as_str()) {
+                parsed.switches.push(arg.clone());
+            } else if options.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                parsed.options.insert(arg.clone(), value.clone());
+            } else if arg.starts_with("--") {
+                return Err(format!("Unknown option: {}", arg));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn has(&self, switch: &str) -> bool {
+        self.switches.iter().any(|s| s == switch)
+    }
+
+    fn value(&self, option: &str) -> Option<&str> {
+        self.options.get(option).map(String::as_str)
+    }
+
+    fn single(&self) -> Result<&str, String> {
+        match self.positional.as_slice() {
+            [value] => Ok(value),
+            [] => Err("Missing argument".to_string()),
+            [_, extra, ..] => Err(format!("Unexpected argument: {}", extra)),
+        }
+    }
+
+    fn single_uuid(&self) -> Result<Uuid, String> {
+        parse_uuid(self.single()?)
+    }
+
+    /// Absolute, the running app resolves paths against its own working directory.
+    fn single_path(&self) -> Result<PathBuf, String> {
+        std::path::absolute(self.single()?).map_err(|e| format!("Invalid path: {}", e))
+    }
+}
+
+fn parse_uuid(value: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(value).map_err(|_| format!("Invalid uuid: {}", value))
+}
+
+/// The passphrase comes from stdin so it never shows up in the shell history.
+fn read_passphrase(args: &CliArgs) -> Result<Option<String>, String> {
+    if !args.has("--passphrase") {
+        return Ok(None);
+    }
+
+    let mut passphrase = String::new();
+    std::io::stdin()
+        .read_line(&mut passphrase)
+        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+
+    let passphrase = passphrase.trim_end_matches(['\r', '\n']);
+    if passphrase.is_empty() {
+        return Err("--passphrase needs a passphrase on stdin".to_string());
+    }
+
+    Ok(Some(passphrase.to_string()))
+}
+
+fn parse_type(value: &str) -> Result<ClipboardType, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("Unknown clipboard type: {}", value))
+}
+
+/// `2024-01-31` or `2024-01-31T18:00:00`, a plain date covers the whole day.
+fn parse_date(value: &str, end_of_day: bool) -> Result<NaiveDateTime, String> {
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+        return Ok(date_time);
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date: {}", value))?;
+
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+
+    Ok(time.expect("Valid time of day"))
+}
+
+/// Runs a subcommand against the database and returns what the CLI prints. Works in
+/// the running app as well as headless, where there is no window, clipboard or sync.
+pub async fn execute_cli_command(command: CliCommand) -> Result<String, CommandError> {
+    match command {
+        CliCommand::List {
+            search,
+            limit,
+            star,
+            img,
+            json,
+        } => {
+            ensure_unlocked()?;
+            let clipboards = trim_clipboard_data(list_clipboards(search, limit, star, img).await?);
+
+            if json {
+                return Ok(serde_json::to_string_pretty(&clipboards)?);
+            }
+
+            Ok(clipboards
+                .iter()
+                .map(clipboard_summary)
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        CliCommand::Get { id, json } => {
+            ensure_unlocked()?;
+            let clipboard = decrypt(get_clipboard_db(id).await?)?;
+
+            if json {
+                return Ok(serde_json::to_string_pretty(&clipboard)?);
+            }
+
+            Ok(clipboard_content(&clipboard))
+        }
+        CliCommand::Copy { id, r#type } => {
+            if is_headless() {
+                return Err(CommandError::Error(
+                    "copy needs the running Clippy app".to_string(),
+                ));
+            }
+            ensure_unlocked()?;
+
+            // Default to the primary type of the clipboard, like a click in the UI
+            let r#type = match r#type {
+                Some(r#type) => r#type,
+                None => ClipboardType::from_json_value(&get_clipboard_db(id).await?.clipboard.types)
+                    .and_then(|types| types.into_iter().next())
+                    .unwrap_or(ClipboardType::Text),
+            };
+
+            if !copy_clipboard_from_id(id, r#type.clone()).await? {
+                return Err(CommandError::Error(format!(
+                    "Clipboard has no {} content",
+                    r#type.to_string()
+                )));
+            }
+
+            Ok(String::new())
+        }
+        CliCommand::Add { text } => {
+            // Headless there is no key to encrypt with, the check covers that too
+            ensure_unlocked()?;
+
+            let mut manager = FullClipboardDbo::new();
+            manager.parse_model(Some(text), None, None, None, None, None);
+
+            // parse_model drops empty content and everything over the size limits
+            let types = match &manager.clipboard_model.types {
+                sea_orm::ActiveValue::Set(types) => ClipboardType::from_json_value(types),
+                _ => None,
+            };
+            if types.is_none_or(|types| types.is_empty()) {
+                return Err(CommandError::Error(
+                    "Nothing to insert, content is empty or exceeds the size limits".to_string(),
+                ));
+            }
+
+            // Same rules as a copy, a secret added here must not end up synced
+            if !manager.apply_sensitive_rules() {
+                return Err(CommandError::Error(
+                    "Dropped, content matches a sensitive rule".to_string(),
+                ));
+            }
+
+            let clipboard = if is_headless() {
+                insert_clipboard_dbo(manager).await?
+            } else {
+                manager.save().await?
+            };
+
+            Ok(clipboard.clipboard.id.to_string())
+        }
+        CliCommand::Star { id, star } => {
+            star_clipboard_db(id, star).await?;
+            if !is_headless() {
+                init_clipboards();
+            }
+
+            Ok(String::new())
+        }
+        CliCommand::Delete { ids } => {
+            delete_clipboards_db(ids, Some(true)).await?;
+            if !is_headless() {
+                init_clipboards();
+            }
+
+            Ok(String::new())
+        }
+        CliCommand::Export {
+            path,
+            filter,
+            passphrase,
+        } => {
+            let count = export_archive_db(&path, filter, passphrase).await?;
+            Ok(format!("Exported {} clipboards to {}", count, path.display()))
+        }
+        CliCommand::Import { path, passphrase } => {
+            let summary = import_archive_db(&path, passphrase).await?;
+            Ok(format!(
+                "Imported {} clipboards, {} duplicates, {} conflicts (kept local), {} failed",
+                summary.imported, summary.duplicates, summary.conflicts, summary.failed
+            ))
+        }
+    }
+}
+
+/// Encrypted history can only be read once the password was entered in the app.
+fn ensure_unlocked() -> Result<(), CommandError> {
+    if get_global_settings().encryption && !is_encryption_key_set() {
+        return Err(CommandError::Error(
+            "Clipboard history is encrypted, unlock it in the running Clippy app first"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn decrypt(clipboard: FullClipboardDto) -> Result<FullClipboardDto, CommandError> {
+    if !clipboard.clipboard.encrypted {
+        return Ok(clipboard);
+    }
+
+    decrypt_clipboard(clipboard)
+        .map_err(|e| CommandError::Error(format!("Failed to decrypt clipboard: {}", e)))
+}
+
+async fn list_clipboards(
+    search: Option<String>,
+    limit: u64,
+    star: Option<bool>,
+    img: Option<bool>,
+) -> Result<Vec<FullClipboardDto>, CommandError> {
+    let settings = get_global_settings();
+    let limit = limit as usize;
+
+    // SQL can't look into encrypted content, page through the history and filter the
+    // decrypted pages like the UI search. Blobs are neither loaded nor decrypted.
+    if settings.encryption {
+        let key = read_encryption_key().map_err(|e| CommandError::Error(e.to_string()))?;
+        let mut paginator = clipboard::Entity::find()
+            .order_by_desc(clipboard::Column::Id)
+            .paginate(db(), LIST_PAGE_SIZE);
+
+        let mut clipboards = Vec::new();
+        while clipboards.len() < limit {
+            let Some(models) = paginator.fetch_and_next().await? else {
+                break;
+            };
+
+            let page: Vec<_> = load_clipboards_for_search(models)
+                .await?
+                .into_iter()
+                .filter_map(|clipboard| {
+                    if !clipboard.clipboard.encrypted {
+                        return Some(clipboard);
+                    }
+
+                    let id = clipboard.clipboard.id;
+                    decrypt_clipboard_search(clipboard, &key)
+                        .inspect_err(|e| printlog!("skipping clipboard {}: {}", id, e))
+                        .ok()
+                })
+                .collect();
+
+            clipboards.extend(filter_clipboards(
+                &page,
+                search.as_ref(),
+                star,
+                img,
+                false,
+                &settings,
+            ));
+        }
+
+        clipboards.truncate(limit);
+        return Ok(clipboards);
+    }
+
+    let mut clipboards = Vec::new();
+    while clipboards.len() < limit {
+        let page = get_clipboards_db(
+            Some(clipboards.len() as u64),
+            search.clone(),
+            star,
+            img,
+        )
+        .await?;
+
+        if page.is_empty() {
+            break;
+        }
+        clipboards.extend(page);
+    }
+    clipboards.truncate(limit);
+
+    Ok(clipboards)
+}
+
+/// One line per clipboard: id, star, types and a single line preview.
+fn clipboard_summary(clipboard: &FullClipboardDto) -> String {
+    let types = ClipboardType::from_json_value(&clipboard.clipboard.types)
+        .unwrap_or_default()
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let preview = clipboard
+        .clipboard
+        .name
+        .clone()
+        .unwrap_or_else(|| clipboard_content(clipboard))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let preview = match preview.char_indices().nth(PREVIEW_LENGTH) {
+        Some((end, _)) => format!("{}...", &preview[..end]),
+        None => preview,
+    };
+
+    format!(
+        "{}  {}  {:<14}  {}",
+        clipboard.clipboard.id,
+        if clipboard.clipboard.star { "*" } else { " " },
+        types,
+        preview
+    )
+}
+
+/// The content as plain text, binary clipboards are described instead.
+fn clipboard_content(clipboard: &FullClipboardDto) -> String {
+    if let Some(text) = &clipboard.text {
+        return text.data.clone();
+    }
+    if let Some(html) = &clipboard.html {
+        return html.data.clone();
+    }
+    if let Some(rtf) = &clipboard.rtf {
+        return rtf.data.clone();
+    }
+    if let Some(image) = &clipboard.image {
+        return format!(
+            "[image {}x{} {}, {} bytes]",
+            image.width, image.height, image.extension, image.size
+        );
+    }
+
+    clipboard
+        .files
+        .iter()
+        .map(|file| match &file.extension {
+            Some(extension) => format!("{}.{}", file.name, extension),
+            None => file.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,37 +1,44 @@
+use super::blob::{insert_blob_db, load_clipboard_blobs, open_blob_db, purge_blobs_db};
 use super::cipher::is_encryption_key_set;
-use super::decrypt::decrypt_clipboard;
+use super::decrypt::{
+    decrypt_clipboard, decrypt_clipboard_with_key, decrypt_data_with_key, read_encryption_key,
+};
+use super::search::{filter_clipboard_search, index_clipboard_search, reindex_clipboard_search};
 use super::settings::get_global_settings;
-use super::sync::{get_sync_manager, get_sync_provider};
+use super::sync::{get_sync_manager, get_sync_provider, insert_tombstones_db};
 use crate::prelude::*;
 use crate::tao::connection::db;
-use crate::tao::global::{get_app, get_cache, get_main_window};
+use crate::tao::global::{get_api_events, get_app, get_cache, get_main_window, is_headless};
 use crate::utils::providers::uuid_to_datetime;
-use chrono::NaiveDateTime;
-use common::builder::keyword::KeywordBuilder;
-use common::constants::CACHE_KEY;
-use common::io::clipboard::trim_clipboard_data;
-use common::types::enums::{ClipboardTextType, ClipboardType, Language, ListenEvent};
+use chrono::{NaiveDateTime, Utc};
+use common::builder::query::SearchQuery;
+use common::constants::{CACHE_KEY, FUZZY_SEARCH_LIMIT};
+use common::io::clipboard::{
+    blob_hash, clipboard_payload_hash, dbo_payload_hash, encrypted_blob_hash, trim_clipboard_data,
+};
+use common::io::fuzzy::TopRanked;
+use common::types::enums::{ClipboardType, Language, ListenEvent};
 use common::types::orm_query::{FullClipboardDbo, FullClipboardDto};
+use common::types::sync::SyncRule;
 use common::types::types::CommandError;
 use entity::clipboard::{self, Model};
 use entity::{
-    clipboard_file, clipboard_html, clipboard_image, clipboard_rtf, clipboard_text, settings,
+    clipboard_file, clipboard_html, clipboard_image, clipboard_rtf, clipboard_search,
+    clipboard_text, settings,
 };
 use sea_orm::prelude::Uuid;
-use sea_orm::RelationTrait;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, Iterable, JoinType, LoaderTrait, PaginatorTrait,
-    QueryFilter, QueryOrder, QuerySelect, QueryTrait, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, DatabaseTransaction, EntityTrait, Iterable, LoaderTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, TransactionTrait,
 };
 use std::collections::HashMap;
-use std::sync::Mutex;
 use tauri::{Emitter, Manager};
 use tauri_plugin_clipboard::Clipboard;
 use tokio::try_join;
 
 pub async fn load_clipboards_with_relations(
     clipboards: Vec<clipboard::Model>,
-) -> Vec<FullClipboardDto> {
+) -> Result<Vec<FullClipboardDto>, DbErr> {
     let db = db();
 
     let (texts, htmls, images, rtfs, files) = try_join!(
@@ -40,11 +47,10 @@ pub async fn load_clipboards_with_relations(
         clipboards.load_one(clipboard_image::Entity, db),
         clipboards.load_one(clipboard_rtf::Entity, db),
         clipboards.load_many(clipboard_file::Entity, db),
-    )
-    .expect("Failed to load clipboard relations");
+    )?;
 
     // Zip everything together, taking first item from each Vec or None if empty
-    clipboards
+    let mut clipboards: Vec<FullClipboardDto> = clipboards
         .into_iter()
         .zip(texts)
         .zip(htmls)
@@ -58,18 +64,23 @@ pub async fn load_clipboards_with_relations(
             image: i,
             rtf: r,
             files: f,
+            search_match: None,
         })
-        .collect()
+        .collect();
+
+    load_clipboard_blobs(db, &mut clipboards).await?;
+
+    Ok(clipboards)
 }
 
 /// Like `load_clipboards_with_relations` but never loads the heavy `image.data` /
 /// `file.data` blob columns (search filters only touch ocr_text/extension/text, and
 /// the list renders thumbnails). The `data` field is set to an empty Vec via a SQL
-/// literal so no blob bytes hit RAM or the decryptor. Full blobs are loaded on demand
-/// when a clipboard is actually opened.
+/// literal so no blob bytes hit RAM or the decryptor, `blob_hash` is left as loaded.
+/// Full blobs are loaded on demand when a clipboard is actually opened.
 pub async fn load_clipboards_for_search(
     clipboards: Vec<clipboard::Model>,
-) -> Vec<FullClipboardDto> {
+) -> Result<Vec<FullClipboardDto>, DbErr> {
     use sea_orm::sea_query::Expr;
 
     let db = db();
@@ -105,8 +116,7 @@ pub async fn load_clipboards_for_search(
         .all(db);
 
     let (texts, htmls, rtfs, images, files) =
-        try_join!(texts_fut, htmls_fut, rtfs_fut, images_fut, files_fut)
-            .expect("Failed to load clipboard relations for search");
+        try_join!(texts_fut, htmls_fut, rtfs_fut, images_fut, files_fut)?;
 
     // Group image/file rows by clipboard_id (one image per clipboard, many files).
     let mut image_by_clip: HashMap<Uuid, clipboard_image::Model> = HashMap::new();
@@ -118,7 +128,7 @@ pub async fn load_clipboards_for_search(
         files_by_clip.entry(file.clipboard_id).or_default().push(file);
     }
 
-    clipboards
+    Ok(clipboards
         .into_iter()
         .zip(texts)
         .zip(htmls)
@@ -133,9 +143,10 @@ pub async fn load_clipboards_for_search(
                 image,
                 rtf: r,
                 files,
+                search_match: None,
             }
         })
-        .collect()
+        .collect())
 }
 
 pub async fn get_clipboard_count_db() -> Result<u64, DbErr> {
@@ -146,9 +157,15 @@ pub async fn get_clipboard_count_db() -> Result<u64, DbErr> {
     Ok(count)
 }
 
-pub async fn insert_clipboard_dbo(model: FullClipboardDbo) -> Result<FullClipboardDto, DbErr> {
-    let db = db();
-    let clipboard = model.clipboard_model.insert(db).await?;
+pub async fn insert_clipboard_dbo(mut model: FullClipboardDbo) -> Result<FullClipboardDto, DbErr> {
+    // Set already when the duplicate check ran
+    if !model.clipboard_model.content_hash.is_set() {
+        model.clipboard_model.content_hash = Set(Some(dbo_payload_hash(&model, None)));
+    }
+
+    // One transaction, a blob must never be purged before the row pointing at it is in
+    let txn = db().begin().await?;
+    let clipboard = model.clipboard_model.insert(&txn).await?;
 
     // Insert text if data exists
     let text = match &model.clipboard_text_model.data {
@@ -156,7 +173,7 @@ pub async fn insert_clipboard_dbo(model: FullClipboardDbo) -> Result<FullClipboa
             let mut text_model = model.clipboard_text_model;
             text_model.id = Set(Uuid::now_v7());
             text_model.clipboard_id = Set(clipboard.id);
-            Some(text_model.insert(db).await?)
+            Some(text_model.insert(&txn).await?)
         }
         _ => None,
     };
@@ -167,7 +184,7 @@ pub async fn insert_clipboard_dbo(model: FullClipboardDbo) -> Result<FullClipboa
             let mut html_model = model.clipboard_html_model;
             html_model.id = Set(Uuid::now_v7());
             html_model.clipboard_id = Set(clipboard.id);
-            Some(html_model.insert(db).await?)
+            Some(html_model.insert(&txn).await?)
         }
         _ => None,
     };
@@ -178,7 +195,7 @@ pub async fn insert_clipboard_dbo(model: FullClipboardDbo) -> Result<FullClipboa
             let mut rtf_model = model.clipboard_rtf_model;
             rtf_model.id = Set(Uuid::now_v7());
             rtf_model.clipboard_id = Set(clipboard.id);
-            Some(rtf_model.insert(db).await?)
+            Some(rtf_model.insert(&txn).await?)
         }
         _ => None,
     };
@@ -187,9 +204,16 @@ pub async fn insert_clipboard_dbo(model: FullClipboardDbo) -> Result<FullClipboa
     let image = match &model.clipboard_image_model.data {
         sea_orm::ActiveValue::Set(data) if !data.is_empty() => {
             let mut image_model = model.clipboard_image_model;
+            let data = image_model.data.take().unwrap_or_default();
             image_model.id = Set(Uuid::now_v7());
             image_model.clipboard_id = Set(clipboard.id);
-            Some(image_model.insert(db).await?)
+            image_model.blob_hash = Set(Some(insert_blob_db(&txn, blob_hash(&data), &data).await?));
+            image_model.data = Set(Vec::new());
+
+            let mut image = image_model.insert(&txn).await?;
+            image.data = data;
+            image.blob_hash = None;
+            Some(image)
         }
         _ => None,
     };
@@ -198,26 +222,59 @@ pub async fn insert_clipboard_dbo(model: FullClipboardDbo) -> Result<FullClipboa
     let files = if !model.clipboard_files_model.is_empty() {
         let mut files = Vec::new();
         for mut file_model in model.clipboard_files_model {
+            let data = file_model.data.take().unwrap_or_default();
             file_model.id = Set(Uuid::now_v7());
             file_model.clipboard_id = Set(clipboard.id);
-            files.push(file_model.insert(db).await?);
+            if !data.is_empty() {
+                file_model.blob_hash =
+                    Set(Some(insert_blob_db(&txn, blob_hash(&data), &data).await?));
+            }
+            file_model.data = Set(Vec::new());
+
+            let mut file = file_model.insert(&txn).await?;
+            file.data = data;
+            file.blob_hash = None;
+            files.push(file);
         }
         files
     } else {
         Vec::new()
     };
 
-    Ok(FullClipboardDto {
+    let clipboard = FullClipboardDto {
         clipboard,
         text,
         html,
         image,
         rtf,
         files,
-    })
+        search_match: None,
+    };
+
+    index_clipboard_search(&txn, &clipboard).await?;
+
+    txn.commit().await?;
+
+    Ok(clipboard)
 }
 
-pub async fn upsert_clipboard_dto(model: FullClipboardDto) -> Result<(), DbErr> {
+pub async fn upsert_clipboard_dto(mut model: FullClipboardDto) -> Result<(), DbErr> {
+    let key = model
+        .clipboard
+        .encrypted
+        .then(|| read_encryption_key().ok())
+        .flatten();
+
+    // The hash is over the plain content, keyed for encrypted clipboards. Those without
+    // the key are hashed by `backfill_content_hashes_db` once it is set.
+    model.clipboard.content_hash = match &key {
+        _ if !model.clipboard.encrypted => Some(clipboard_payload_hash(&model, None)),
+        Some(key) => decrypt_clipboard_with_key(model.clone(), key)
+            .ok()
+            .map(|clipboard| clipboard_payload_hash(&clipboard, Some(key.as_slice()))),
+        None => None,
+    };
+
     let txn = db().begin().await?;
 
     // Delete existing clipboard and all related records through cascade
@@ -226,10 +283,13 @@ pub async fn upsert_clipboard_dto(model: FullClipboardDto) -> Result<(), DbErr>
         .await?;
 
     // Insert clipboard
-    entity::clipboard::ActiveModel::from(model.clipboard)
+    entity::clipboard::ActiveModel::from(model.clipboard.clone())
         .insert(&txn)
         .await?;
 
+    // Refresh the full-text index (removed again by the cascade above)
+    index_clipboard_search(&txn, &model).await?;
+
     // Insert text if data exists
     if let Some(text) = model.text {
         if !text.data.is_empty() {
@@ -248,9 +308,12 @@ pub async fn upsert_clipboard_dto(model: FullClipboardDto) -> Result<(), DbErr>
         }
     }
 
-    // Insert image if data exists
-    if let Some(image) = model.image {
+    // Insert image if data exists, the bytes go to the blob table
+    if let Some(mut image) = model.image {
         if !image.data.is_empty() {
+            let hash = blob_key(&image.data, key.as_ref());
+            image.blob_hash = Some(insert_blob_db(&txn, hash, &image.data).await?);
+            image.data = Vec::new();
             entity::clipboard_image::ActiveModel::from(image)
                 .insert(&txn)
                 .await?;
@@ -266,22 +329,42 @@ pub async fn upsert_clipboard_dto(model: FullClipboardDto) -> Result<(), DbErr>
         }
     }
 
-    // Batch insert files if they exist
+    // Batch insert files if they exist, the bytes go to the blob table
     if !model.files.is_empty() {
-        let file_models: Vec<entity::clipboard_file::ActiveModel> = model
-            .files
-            .into_iter()
-            .map(entity::clipboard_file::ActiveModel::from)
-            .collect();
+        let mut file_models: Vec<entity::clipboard_file::ActiveModel> = Vec::new();
+        for mut file in model.files {
+            if !file.data.is_empty() {
+                let hash = blob_key(&file.data, key.as_ref());
+                file.blob_hash = Some(insert_blob_db(&txn, hash, &file.data).await?);
+                file.data = Vec::new();
+            }
+            file_models.push(entity::clipboard_file::ActiveModel::from(file));
+        }
         entity::clipboard_file::Entity::insert_many(file_models)
             .exec(&txn)
             .await?;
     }
 
     txn.commit().await?;
+
+    // The blobs of the replaced version may have been external
+    if get_global_settings().external_blob_store {
+        purge_blobs_db().await?;
+    }
+
     Ok(())
 }
 
+/// Blob key of image or file bytes. Every encryption uses a fresh nonce, encrypted ones
+/// are keyed by their plain bytes so copying the same image again reuses its blob.
+fn blob_key(data: &[u8], key: Option<&[u8; 32]>) -> String {
+    key.and_then(|key| {
+        let plain = decrypt_data_with_key(data, key).ok()?;
+        Some(encrypted_blob_hash(key, &plain))
+    })
+    .unwrap_or_else(|| blob_hash(data))
+}
+
 pub async fn get_clipboard_db(id: Uuid) -> Result<FullClipboardDto, DbErr> {
     let db = db();
     let clipboard = clipboard::Entity::find_by_id(id)
@@ -290,7 +373,7 @@ pub async fn get_clipboard_db(id: Uuid) -> Result<FullClipboardDto, DbErr> {
         .ok_or(DbErr::RecordNotFound("Clipboard not found".into()))?;
 
     Ok(load_clipboards_with_relations(vec![clipboard])
-        .await
+        .await?
         .remove(0))
 }
 
@@ -302,7 +385,7 @@ pub async fn get_last_clipboard_db() -> Result<FullClipboardDto, DbErr> {
         .ok_or_else(|| DbErr::RecordNotFound("last clipboard not found".to_string()))?;
 
     let mut dto = load_clipboards_with_relations(vec![clipboard])
-        .await
+        .await?
         .remove(0);
 
     if dto.clipboard.encrypted && is_encryption_key_set() {
@@ -314,36 +397,169 @@ pub async fn get_last_clipboard_db() -> Result<FullClipboardDto, DbErr> {
     Ok(dto)
 }
 
-pub async fn get_recent_clipboards_db(limit: u64) -> Result<Vec<FullClipboardDto>, DbErr> {
-    let clipboards = clipboard::Entity::find()
+/// Newest clipboard with the given `content_hash`, see `clipboard_payload_hash`.
+pub async fn find_duplicate_clipboard_db(content_hash: &str) -> Result<Option<Uuid>, DbErr> {
+    clipboard::Entity::find()
+        .select_only()
+        .column(clipboard::Column::Id)
+        .filter(clipboard::Column::ContentHash.eq(content_hash))
         .order_by_desc(clipboard::Column::Id)
-        .limit(limit)
-        .all(db())
-        .await?;
+        .into_tuple()
+        .one(db())
+        .await
+}
 
-    let mut dtos = load_clipboards_with_relations(clipboards).await;
+/// Moves a clipboard to the top by giving it a new id and returns that id. The related
+/// rows are moved over by hand, their foreign keys don't follow an id change. Synced
+/// devices see the old id deleted and the new one added.
+pub async fn bump_clipboard_timestamp(id: Uuid) -> Result<Uuid, DbErr> {
+    let txn = db().begin().await?;
 
-    for dto in &mut dtos {
-        if dto.clipboard.encrypted && is_encryption_key_set() {
-            if let Ok(decrypted) = decrypt_clipboard(dto.clone()) {
-                *dto = decrypted;
-            }
-        }
+    let mut clipboard = clipboard::Entity::find_by_id(id)
+        .one(&txn)
+        .await?
+        .ok_or(DbErr::RecordNotFound("Clipboard not found".into()))?;
+    clipboard.id = Uuid::now_v7();
+    let clipboard = clipboard::ActiveModel::from(clipboard).insert(&txn).await?;
+
+    move_clipboard_rows::<clipboard_text::Entity>(
+        &txn,
+        clipboard_text::Column::ClipboardId,
+        id,
+        clipboard.id,
+    )
+    .await?;
+    move_clipboard_rows::<clipboard_html::Entity>(
+        &txn,
+        clipboard_html::Column::ClipboardId,
+        id,
+        clipboard.id,
+    )
+    .await?;
+    move_clipboard_rows::<clipboard_image::Entity>(
+        &txn,
+        clipboard_image::Column::ClipboardId,
+        id,
+        clipboard.id,
+    )
+    .await?;
+    move_clipboard_rows::<clipboard_rtf::Entity>(
+        &txn,
+        clipboard_rtf::Column::ClipboardId,
+        id,
+        clipboard.id,
+    )
+    .await?;
+    move_clipboard_rows::<clipboard_file::Entity>(
+        &txn,
+        clipboard_file::Column::ClipboardId,
+        id,
+        clipboard.id,
+    )
+    .await?;
+    move_clipboard_rows::<clipboard_search::Entity>(
+        &txn,
+        clipboard_search::Column::ClipboardId,
+        id,
+        clipboard.id,
+    )
+    .await?;
+
+    clipboard::Entity::delete_by_id(id).exec(&txn).await?;
+
+    txn.commit().await?;
+
+    if get_global_settings().sync {
+        insert_tombstones_db(vec![id]).await?;
     }
 
-    Ok(dtos)
+    get_cache().invalidate_all();
+
+    Ok(clipboard.id)
 }
 
-pub async fn bump_clipboard_timestamp(id: Uuid) -> Result<(), DbErr> {
-    let new_id = Uuid::now_v7();
-    clipboard::Entity::update_many()
-        .col_expr(clipboard::Column::Id, sea_orm::sea_query::Expr::value(new_id))
-        .filter(clipboard::Column::Id.eq(id))
-        .exec(db())
+async fn move_clipboard_rows<E: EntityTrait>(
+    txn: &DatabaseTransaction,
+    column: E::Column,
+    from: Uuid,
+    to: Uuid,
+) -> Result<(), DbErr> {
+    E::update_many()
+        .col_expr(column, sea_orm::sea_query::Expr::value(to))
+        .filter(column.eq(from))
+        .exec(txn)
         .await?;
     Ok(())
 }
 
+/// Fills in missing content hashes in the background, see `backfill_content_hashes_db`.
+pub fn setup_content_hashes() {
+    tauri::async_runtime::spawn(async {
+        if let Err(e) = backfill_content_hashes_db().await {
+            printlog!("failed to hash clipboards: {}", e);
+        }
+    });
+}
+
+/// Fills `content_hash` of clipboards stored before it existed, encrypted ones only
+/// while the key is set. Until then those don't count as duplicates.
+pub async fn backfill_content_hashes_db() -> Result<(), DbErr> {
+    let key = read_encryption_key().ok();
+    let key_set = key.is_some();
+    let mut last_id = None;
+    let mut filled = 0;
+
+    loop {
+        let clipboards = clipboard::Entity::find()
+            .filter(clipboard::Column::ContentHash.is_null())
+            .apply_if(last_id, |q, id: Uuid| {
+                q.filter(clipboard::Column::Id.gt(id))
+            })
+            .apply_if((!key_set).then_some(false), |q, encrypted| {
+                q.filter(clipboard::Column::Encrypted.eq(encrypted))
+            })
+            .order_by_asc(clipboard::Column::Id)
+            .limit(100)
+            .all(db())
+            .await?;
+
+        let Some(last) = clipboards.last() else {
+            break;
+        };
+        last_id = Some(last.id);
+
+        for clipboard in load_clipboards_with_relations(clipboards).await? {
+            let id = clipboard.clipboard.id;
+            let content_hash = match &key {
+                Some(key) if clipboard.clipboard.encrypted => {
+                    match decrypt_clipboard_with_key(clipboard, key) {
+                        Ok(clipboard) => clipboard_payload_hash(&clipboard, Some(key.as_slice())),
+                        Err(_) => continue,
+                    }
+                }
+                _ => clipboard_payload_hash(&clipboard, None),
+            };
+
+            clipboard::Entity::update_many()
+                .col_expr(
+                    clipboard::Column::ContentHash,
+                    sea_orm::sea_query::Expr::value(content_hash),
+                )
+                .filter(clipboard::Column::Id.eq(id))
+                .filter(clipboard::Column::ContentHash.is_null())
+                .exec(db())
+                .await?;
+            filled += 1;
+        }
+    }
+
+    if filled > 0 {
+        printlog!("hashed the content of {} clipboards", filled);
+    }
+
+    Ok(())
+}
+
 pub async fn get_all_clipboards_db() -> Result<Vec<FullClipboardDto>, DbErr> {
     let db = db();
     let clipboards = clipboard::Entity::find()
@@ -351,7 +567,7 @@ pub async fn get_all_clipboards_db() -> Result<Vec<FullClipboardDto>, DbErr> {
         .all(db)
         .await?;
 
-    Ok(load_clipboards_with_relations(clipboards).await)
+    load_clipboards_with_relations(clipboards).await
 }
 
 pub async fn get_clipboards_db(
@@ -361,70 +577,28 @@ pub async fn get_clipboards_db(
     img: Option<bool>,
 ) -> Result<Vec<FullClipboardDto>, DbErr> {
     let db = db();
-    let (clipboard_keywords, text_keywords) = KeywordBuilder::build_default();
-
     let settings = get_global_settings();
 
+    let search_query = SearchQuery::parse(
+        search.as_deref().unwrap_or_default(),
+        &Language::from_iso_code(&settings.language),
+    );
+
     let query = clipboard::Entity::find()
-        .distinct()
-        .join(JoinType::LeftJoin, clipboard::Relation::ClipboardText.def())
-        .join(JoinType::LeftJoin, clipboard::Relation::ClipboardHtml.def())
-        .join(
-            JoinType::LeftJoin,
-            clipboard::Relation::ClipboardImage.def(),
-        )
-        .join(JoinType::LeftJoin, clipboard::Relation::ClipboardRtf.def())
-        .join(JoinType::LeftJoin, clipboard::Relation::ClipboardFile.def())
         .apply_if(star, |q, s| q.filter(clipboard::Column::Star.eq(s)))
         .apply_if(img, |q, _| {
             q.filter(clipboard::Column::Types.contains(ClipboardType::Image.to_string()))
         })
-        .apply_if(search.as_ref().map(|s| s.to_lowercase()), |q, s| {
-            if let Some(clip_type) = KeywordBuilder::find_clipboard_type(
-                &s,
-                &Language::from_iso_code(&settings.language),
-                &clipboard_keywords,
-            ) {
-                match clip_type {
-                    ClipboardType::Text => {
-                        if let Some(text_type) = KeywordBuilder::find_text_type(
-                            &s,
-                            &Language::from_iso_code(&settings.language),
-                            &text_keywords,
-                        ) {
-                            q.filter(clipboard_text::Column::Type.eq(text_type.to_string()))
-                        } else {
-                            q.filter(
-                                clipboard_text::Column::Type
-                                    .eq(ClipboardTextType::Text.to_string()),
-                            )
-                        }
-                    }
-                    _ => q.filter(clipboard::Column::Types.contains(clip_type.to_string())),
-                }
-            } else {
-                // Fallback to full-text search
-                q.filter(
-                    clipboard_text::Column::Data
-                        .contains(&s)
-                        .or(clipboard_file::Column::Name.contains(&s))
-                        .or(clipboard_file::Column::Extension.contains(&s))
-                        .or(clipboard_file::Column::MimeType.contains(&s))
-                        .or(clipboard_image::Column::Extension.contains(&s))
-                        .or(clipboard_image::Column::OcrText.contains(&s))
-                        .or(clipboard_text::Column::Type.contains(&s))
-                        .or(clipboard_html::Column::Data.contains(&s))
-                        .or(clipboard_rtf::Column::Data.contains(&s)),
-                )
-            }
-        })
+        .filter(search_query.to_condition())
+        // Ranked full-text search over the FTS5 index
+        .apply_if(search_query.fts_query(), filter_clipboard_search)
         .offset(cursor)
         .limit(25)
         .order_by_desc(clipboard::Column::Id);
 
     let clipboards = query.all(db).await?;
 
-    Ok(load_clipboards_with_relations(clipboards).await)
+    load_clipboards_with_relations(clipboards).await
 }
 
 pub async fn get_latest_syncable_cliboards_db() -> Result<Vec<FullClipboardDto>, DbErr> {
@@ -474,7 +648,13 @@ pub async fn get_latest_syncable_cliboards_db() -> Result<Vec<FullClipboardDto>,
         );
     }
 
-    Ok(load_clipboards_with_relations(clipboards).await)
+    // Rules that need the contents can only be applied once they are loaded
+    let rules = SyncRule::from_json_value(&settings.sync_rules);
+    Ok(load_clipboards_with_relations(clipboards)
+        .await?
+        .into_iter()
+        .filter(|clipboard| SyncRule::allows(&rules, clipboard))
+        .collect())
 }
 
 pub async fn star_clipboard_db(id: Uuid, star: bool) -> Result<bool, CommandError> {
@@ -483,20 +663,24 @@ pub async fn star_clipboard_db(id: Uuid, star: bool) -> Result<bool, CommandErro
     let model = clipboard::ActiveModel {
         id: Set(id),
         star: Set(star),
+        updated_at: Set(Utc::now().naive_utc()),
         ..Default::default()
     };
 
     let clipboard = clipboard::Entity::update(model).exec(db).await?;
 
-    let settings = get_app().state::<Mutex<settings::Model>>();
-    if settings.lock().expect("Failed to lock settings").sync {
+    // Headless the process is gone before a push would finish, the new `updated_at`
+    // gets it synced by the app
+    if get_global_settings().sync && !is_headless() {
         let clipboard = load_clipboards_with_relations(vec![clipboard])
-            .await
+            .await?
             .remove(0);
 
         tauri::async_runtime::spawn(async move {
-            let provider = get_sync_provider().await;
-            provider.star_clipboard(&clipboard).await
+            match get_sync_provider().await {
+                Ok(provider) => provider.star_clipboard(&clipboard).await,
+                Err(e) => printlog!("failed to star remote clipboard: {:?}", e),
+            }
         });
     }
 
@@ -509,6 +693,7 @@ pub async fn rename_clipboard_db(id: Uuid, name: Option<String>) -> Result<bool,
     let model = clipboard::ActiveModel {
         id: Set(id),
         name: Set(name),
+        updated_at: Set(Utc::now().naive_utc()),
         ..Default::default()
     };
 
@@ -524,11 +709,29 @@ pub async fn delete_clipboards_db(
     let settings = get_global_settings();
     let db = db();
 
+    // Deleted here, not applied from the remote. The next sync publishes tombstones for
+    // the ones that existed, which deletes them on the remote and the other devices.
+    let tombstone_ids: Vec<Uuid> = if command.is_some() && settings.sync {
+        clipboard::Entity::find()
+            .filter(clipboard::Column::Id.is_in(ids.clone()))
+            .select_only()
+            .column(clipboard::Column::Id)
+            .into_tuple()
+            .all(db)
+            .await?
+    } else {
+        Vec::new()
+    };
+
     let result = clipboard::Entity::delete_many()
         .filter(clipboard::Column::Id.is_in(ids.clone()))
         .exec(db)
         .await?;
 
+    if result.rows_affected > 0 && settings.external_blob_store {
+        purge_blobs_db().await?;
+    }
+
     // Update cache by removing deleted items instead of clearing everything
     if result.rows_affected > 0 {
         if let Some(mut cached) = get_cache().get(CACHE_KEY) {
@@ -537,50 +740,31 @@ pub async fn delete_clipboards_db(
         }
     }
 
-    // Only spawn deletion task if records were actually deleted
-    if result.rows_affected > 0 && settings.sync {
-        // Get the actually deleted IDs by querying what remains
-        let remaining_ids = clipboard::Entity::find()
-            .filter(clipboard::Column::Id.is_in(ids.clone()))
-            .select_only()
-            .column(clipboard::Column::Id)
-            .into_tuple()
-            .all(db)
-            .await?;
-
-        let deleted_ids: Vec<Uuid> = ids
-            .into_iter()
-            .filter(|id| !remaining_ids.contains(id))
-            .collect();
+    if !tombstone_ids.is_empty() {
+        insert_tombstones_db(tombstone_ids).await?;
+    }
 
-        tauri::async_runtime::spawn(async move {
-            let provider = get_sync_provider().await;
+    Ok(())
+}
 
-            let clipboards = provider
-                .fetch_all_clipboards()
-                .await
-                .expect("Failed to fetch clipboards")
-                .into_iter()
-                .filter(|c| deleted_ids.contains(&c.id))
-                .collect::<Vec<_>>();
+/// Deletes the sensitive clipboards whose time is up, unless they were starred since.
+/// They never synced, so there is nothing to delete remotely.
+pub async fn delete_expired_clipboards_db() -> Result<u64, DbErr> {
+    let result = clipboard::Entity::delete_many()
+        .filter(clipboard::Column::ExpiresAt.lte(Utc::now().naive_utc()))
+        .filter(clipboard::Column::Star.eq(false))
+        .exec(db())
+        .await?;
 
-            if command.is_none() && !clipboards.is_empty() {
-                init_clipboards();
-            }
+    if result.rows_affected > 0 {
+        if get_global_settings().external_blob_store {
+            purge_blobs_db().await?;
+        }
 
-            for clippy in clipboards {
-                printlog!(
-                    "deleting remote clipboard: {:?} command: {:?}",
-                    clippy.id,
-                    command
-                );
-                provider.mark_for_deletion(&clippy).await;
-                break;
-            }
-        });
+        get_cache().invalidate_all();
     }
 
-    Ok(())
+    Ok(result.rows_affected)
 }
 
 pub async fn clear_clipboards_db(r#type: Option<ClipboardType>) -> Result<(), DbErr> {
@@ -665,22 +849,42 @@ pub async fn clear_clipboards_db(r#type: Option<ClipboardType>) -> Result<(), Db
                         let model = clipboard::ActiveModel {
                             id: Set(clipboard.id),
                             types: Set(ClipboardType::to_json_value(&types)),
+                            updated_at: Set(Utc::now().naive_utc()),
                             ..Default::default()
                         };
                         clipboard::Entity::update(model).exec(db).await?;
+                        reindex_clipboard_search(clipboard.id).await?;
                     }
                 }
             }
         }
     }
 
+    if settings.external_blob_store {
+        purge_blobs_db().await?;
+    }
+
     // Clear cache since we deleted clipboards
     get_cache().invalidate_all();
 
     // Handle remote deletion if sync is enabled
     if settings.sync && !remote_clipboards_to_delete.is_empty() {
+        insert_tombstones_db(
+            remote_clipboards_to_delete
+                .iter()
+                .map(|clipboard| clipboard.id)
+                .collect(),
+        )
+        .await?;
+
         tauri::async_runtime::spawn(async move {
-            let provider = get_sync_provider().await;
+            let provider = match get_sync_provider().await {
+                Ok(provider) => provider,
+                Err(e) => {
+                    printlog!("failed to delete remote clipboards: {:?}", e);
+                    return;
+                }
+            };
             let manager = get_sync_manager();
 
             // Fetch all remote clipboards
@@ -741,6 +945,16 @@ pub async fn get_clipboard_uuids_db() -> Result<HashMap<Uuid, (bool, NaiveDateTi
         .collect())
 }
 
+/// Every stored clipboard without its content, keyed by id.
+pub async fn get_clipboard_models_db() -> Result<HashMap<Uuid, Model>, DbErr> {
+    let clipboards = clipboard::Entity::find().all(db()).await?;
+
+    Ok(clipboards
+        .into_iter()
+        .map(|clipboard| (clipboard.id, clipboard))
+        .collect())
+}
+
 pub async fn copy_clipboard_from_index(i: u64) -> Result<Option<Model>, DbErr> {
     let db = db();
 
@@ -761,12 +975,39 @@ pub async fn copy_clipboard_from_index(i: u64) -> Result<Option<Model>, DbErr> {
     Ok(Some(model))
 }
 
+/// Writes the contents of `file` to `path`, from its blob when it was loaded without them.
+async fn write_clipboard_file(
+    file: &clipboard_file::Model,
+    path: &std::path::Path,
+) -> Result<(), CommandError> {
+    match file.blob_hash.as_deref() {
+        Some(hash) => {
+            let mut blob = open_blob_db(Some(hash)).await?;
+            std::io::copy(&mut blob, &mut std::fs::File::create(path)?)?;
+        }
+        None => std::fs::write(path, &file.data)?,
+    }
+
+    Ok(())
+}
+
 pub async fn copy_clipboard_from_id(
     id: Uuid,
     requested_type: ClipboardType,
 ) -> Result<bool, DbErr> {
     printlog!("copy clipboard type: {:?} id:{:?}", requested_type, id);
-    let mut clipboard_data = get_clipboard_db(id).await?;
+    let model = clipboard::Entity::find_by_id(id)
+        .one(db())
+        .await?
+        .ok_or(DbErr::RecordNotFound("Clipboard not found".into()))?;
+
+    // Plain files are streamed from their blobs instead of loaded into memory first
+    let stream_files = requested_type == ClipboardType::File && !model.encrypted;
+    let mut clipboard_data = if stream_files {
+        load_clipboards_for_search(vec![model]).await?.remove(0)
+    } else {
+        load_clipboards_with_relations(vec![model]).await?.remove(0)
+    };
     let clipboard = get_app().state::<Clipboard>();
 
     // Decrypt the clipboard data if it's encrypted
@@ -788,27 +1029,28 @@ pub async fn copy_clipboard_from_id(
         ClipboardType::Rtf => clipboard_data
             .rtf
             .and_then(|m| clipboard.write_rtf(m.data).ok()),
-        ClipboardType::File => Some(
-            clipboard_data
-                .files
-                .iter()
-                .filter_map(|f| {
-                    let path = std::env::temp_dir().join(format!(
-                        "{}.{}",
-                        &f.name,
-                        f.extension.as_ref().expect("Failed to get file extension")
-                    ));
-                    std::fs::write(&path, &f.data).ok()?;
-                    Some(if cfg!(windows) {
-                        path.to_string_lossy().replace('/', "\\")
-                    } else {
-                        format!("file://{}", path.to_string_lossy())
-                    })
-                })
-                .collect::<Vec<_>>(),
-        )
-        .filter(|f| !f.is_empty())
-        .and_then(|f| clipboard.write_files_uris(f).ok()),
+        ClipboardType::File => {
+            let mut uris = Vec::new();
+            for f in &clipboard_data.files {
+                let path = std::env::temp_dir().join(format!(
+                    "{}.{}",
+                    &f.name,
+                    f.extension.as_ref().expect("Failed to get file extension")
+                ));
+                if write_clipboard_file(f, &path).await.is_err() {
+                    continue;
+                }
+                uris.push(if cfg!(windows) {
+                    path.to_string_lossy().replace('/', "\\")
+                } else {
+                    format!("file://{}", path.to_string_lossy())
+                });
+            }
+
+            Some(uris)
+                .filter(|f| !f.is_empty())
+                .and_then(|f| clipboard.write_files_uris(f).ok())
+        }
     }
     .is_some();
 
@@ -819,19 +1061,24 @@ pub async fn copy_clipboard_from_id(
     Ok(success)
 }
 
-// Helper function to filter clipboards in memory
+// Helper function to filter clipboards in memory. In fuzzy mode the matches carry a
+// `search_match` and are ordered by its score instead of by id.
 pub fn filter_clipboards(
     clipboards: &[FullClipboardDto],
     search: Option<&String>,
     star: Option<bool>,
     img: Option<bool>,
+    fuzzy: bool,
     settings: &settings::Model,
 ) -> Vec<FullClipboardDto> {
-    use common::builder::keyword::KeywordBuilder;
-
-    let (clipboard_keywords, text_keywords) = KeywordBuilder::build_default();
-
-    clipboards
+    // Same parser as `get_clipboards_db`, so encrypted and plain search agree
+    let search_query = SearchQuery::parse(
+        search.map(String::as_str).unwrap_or_default(),
+        &Language::from_iso_code(&settings.language),
+    );
+    let now = Utc::now().naive_utc();
+
+    let filtered = clipboards
         .iter()
         .filter(|clipboard| {
             // Apply star filter
@@ -852,84 +1099,38 @@ pub fn filter_clipboards(
                 }
             }
 
+            true
+        })
+        .filter_map(|clipboard| {
             // Apply search filter
-            if let Some(search_text) = search {
-                let search_lower = search_text.to_lowercase();
-
-                // Try to match clipboard type from search
-                if let Some(clip_type) = KeywordBuilder::find_clipboard_type(
-                    &search_lower,
-                    &Language::from_iso_code(&settings.language),
-                    &clipboard_keywords,
-                ) {
-                    match clip_type {
-                        ClipboardType::Text => {
-                            if let Some(text) = &clipboard.text {
-                                if let Some(text_type) = KeywordBuilder::find_text_type(
-                                    &search_lower,
-                                    &Language::from_iso_code(&settings.language),
-                                    &text_keywords,
-                                ) {
-                                    return text.r#type == text_type.to_string();
-                                } else {
-                                    return text.r#type == ClipboardTextType::Text.to_string();
-                                }
-                            }
-                            return false;
-                        }
-                        _ => {
-                            if let Some(types) =
-                                ClipboardType::from_json_value(&clipboard.clipboard.types)
-                            {
-                                return types.contains(&clip_type);
-                            }
-                            return false;
-                        }
-                    }
-                }
-
-                // Fallback to full-text search
-                let contains_search = clipboard
-                    .text
-                    .as_ref()
-                    .map_or(false, |t| t.data.to_lowercase().contains(&search_lower))
-                    || clipboard
-                        .html
-                        .as_ref()
-                        .map_or(false, |h| h.data.to_lowercase().contains(&search_lower))
-                    || clipboard
-                        .rtf
-                        .as_ref()
-                        .map_or(false, |r| r.data.to_lowercase().contains(&search_lower))
-                    || clipboard.files.iter().any(|f| {
-                        f.name.to_lowercase().contains(&search_lower)
-                            || f.extension
-                                .as_ref()
-                                .map_or(false, |e| e.to_lowercase().contains(&search_lower))
-                            || f.mime_type
-                                .as_ref()
-                                .map_or(false, |m| m.to_lowercase().contains(&search_lower))
-                    })
-                    || clipboard.image.as_ref().map_or(false, |i| {
-                        i.extension.to_lowercase().contains(&search_lower)
-                            || i.ocr_text
-                                .as_ref()
-                                .map_or(false, |t| t.to_lowercase().contains(&search_lower))
-                    })
-                    || clipboard
-                        .text
-                        .as_ref()
-                        .map_or(false, |t| t.r#type.to_lowercase().contains(&search_lower));
-
-                if !contains_search {
-                    return false;
-                }
+            if fuzzy {
+                let search_match = search_query.fuzzy_match(clipboard, now)?;
+                let mut clipboard = clipboard.clone();
+                clipboard.search_match = Some(search_match);
+                Some(clipboard)
+            } else {
+                search_query.matches(clipboard).then(|| clipboard.clone())
             }
+        });
 
-            true
-        })
-        .cloned()
-        .collect()
+    if !fuzzy {
+        return filtered.collect();
+    }
+
+    let mut ranked = TopRanked::new(FUZZY_SEARCH_LIMIT);
+    for clipboard in filtered {
+        ranked.push(search_score(&clipboard), clipboard);
+    }
+    ranked.into_sorted_vec()
+}
+
+/// Fuzzy rank of a clipboard returned by `filter_clipboards`, 0 when unscored.
+pub fn search_score(clipboard: &FullClipboardDto) -> f32 {
+    clipboard
+        .search_match
+        .as_ref()
+        .map(|m| m.score)
+        .unwrap_or_default()
 }
 
 pub fn init_clipboards() {
@@ -940,13 +1141,23 @@ pub fn init_clipboards() {
 
 pub fn new_clipboard_event(mut clipboard: FullClipboardDto) {
     if clipboard.clipboard.encrypted && is_encryption_key_set() {
-        clipboard = decrypt_clipboard(clipboard).expect("Failed to decrypt clipboard");
+        clipboard = match decrypt_clipboard(clipboard) {
+            Ok(clipboard) => clipboard,
+            Err(e) => {
+                // The list reloads it through the usual decryption instead
+                printlog!("failed to decrypt new clipboard: {}", e);
+                init_clipboards();
+                return;
+            }
+        };
     }
 
+    let clipboard = trim_clipboard_data(vec![clipboard]).remove(0);
+
+    // Fails only without subscribers, i.e. no client on the local API event stream
+    let _ = get_api_events().send(clipboard.clone());
+
     get_main_window()
-        .emit(
-            ListenEvent::NewClipboard.to_string().as_str(),
-            trim_clipboard_data(vec![clipboard]).remove(0),
-        )
-        .expect("Failed to emit event");
+        .emit(ListenEvent::NewClipboard.to_string().as_str(), clipboard)
+        .ok();
 }
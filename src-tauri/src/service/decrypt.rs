@@ -42,7 +42,7 @@ where
 
     // Pull remote encrypted rows into the local DB first so pagination sees everything.
     let (provider, remote_clipboards) = if settings.sync {
-        let provider = get_sync_provider().await;
+        let provider = get_sync_provider().await?;
         let remote_clipboards = provider
             .fetch_all_clipboards()
             .await
@@ -58,6 +58,7 @@ where
                         label: "SETTINGS.ENCRYPT.DOWNLOADING_REMOTE_CLIPBOARDS".to_string(),
                         total: download_total,
                         current: index + 1,
+                        ..Default::default()
                     },
                 )
                 .map_err(|e| CommandError::new(&e.to_string()))?;
@@ -101,7 +102,7 @@ where
     let mut processed = 0usize;
 
     while let Some(models) = paginator.fetch_and_next().await? {
-        let page = load_clipboards_with_relations(models).await;
+        let page = load_clipboards_with_relations(models).await?;
 
         // Decrypt the whole page in parallel; pure CPU, no DB, no global lock.
         let decrypted: Vec<Result<FullClipboardDto, (Uuid, EncryptionError)>> =
@@ -169,6 +170,7 @@ pub async fn decrypt_all_clipboards() -> Result<(), CommandError> {
                 label: "SETTINGS.ENCRYPT.DECRYPTION_PROGRESS_LOCAL".to_string(),
                 total,
                 current,
+                ..Default::default()
             },
         ) {
             emit_err = Some(CommandError::new(&e.to_string()));
@@ -443,6 +445,7 @@ pub async fn remove_encryption(password: String) -> Result<(), CommandError> {
                     label: "SETTINGS.ENCRYPT.DECRYPTION_PROGRESS_LOCAL".to_string(),
                     total,
                     current,
+                    ..Default::default()
                 },
             )
             .ok();
@@ -43,14 +43,14 @@ async fn encrypt_all_clipboards_internal() -> Result<(), CommandError> {
             .all(db)
             .await?,
     )
-    .await;
+    .await?;
 
     // Get remote clipboards if sync enabled
     let (provider, remote_clipboards) = if settings.sync {
         // Stop the sync manager before making changes
         get_sync_manager().lock().await.stop().await;
 
-        let provider = get_sync_provider().await;
+        let provider = get_sync_provider().await?;
         let remote_clipboards = provider
             .fetch_all_clipboards()
             .await
@@ -82,6 +82,7 @@ async fn encrypt_all_clipboards_internal() -> Result<(), CommandError> {
                             label: "SETTINGS.ENCRYPT.DOWNLOADING_REMOTE_CLIPBOARDS".to_string(),
                             total: download_total,
                             current: index + 1,
+                            ..Default::default()
                         },
                     )
                     .map_err(|e| CommandError::new(&e.to_string()))?;
@@ -106,6 +107,7 @@ async fn encrypt_all_clipboards_internal() -> Result<(), CommandError> {
                     label: "SETTINGS.ENCRYPT.ENCRYPTION_PROGRESS_LOCAL".to_string(),
                     total,
                     current: index + 1,
+                    ..Default::default()
                 },
             )
             .map_err(|e| CommandError::new(&e.to_string()))?;
@@ -213,8 +215,13 @@ pub fn encrypt_data(data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
         .map_err(|_| EncryptionError::KeyLockFailed)?;
     let key_data = guard.as_ref().ok_or(EncryptionError::NoKey)?;
 
+    encrypt_data_with_key(data, &key_data.0)
+}
+
+/// `encrypt_data` with an explicit key, the counterpart of `decrypt_data_with_key`.
+pub fn encrypt_data_with_key(data: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, EncryptionError> {
     // Create unbound key from key bytes
-    let unbound_key = aead::UnboundKey::new(&aead::AES_256_GCM, &key_data.0)
+    let unbound_key = aead::UnboundKey::new(&aead::AES_256_GCM, key)
         .map_err(|_| EncryptionError::EncryptionFailed)?;
     let key = aead::LessSafeKey::new(unbound_key);
 
@@ -0,0 +1,159 @@
+use super::settings::{
+    get_global_settings, get_settings_db, init_settings_window, set_global_settings,
+};
+use super::sync::switch_sync_provider;
+use crate::prelude::*;
+use crate::tao::{config::get_data_path, connection::db, global::get_app};
+use crate::utils::{
+    lan_server::LanServer,
+    mdns,
+    providers::{
+        get_credential,
+        lan::{decode_lan_key, encode_lan_key, pair_with_host, random_bytes, LanProviderImpl},
+        local_folder::LocalFolderProviderImpl,
+        set_credential,
+    },
+};
+use common::{
+    constants::{LAN_CREDENTIAL, LAN_STORE_DIR},
+    types::{
+        enums::SyncProviderType,
+        sync::{LanPeer, SyncProvider},
+        types::CommandError,
+    },
+};
+use entity::settings;
+use sea_orm::{ActiveModelTrait, EntityTrait};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tauri::{Manager, State};
+use tokio::sync::Mutex;
+
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub fn get_lan_server() -> State<'static, Mutex<LanServer>> {
+    match get_app().try_state() {
+        Some(server) => server,
+        None => {
+            get_app().manage(Mutex::new(LanServer::new()));
+            get_app().state()
+        }
+    }
+}
+
+pub fn setup_lan_server() {
+    tauri::async_runtime::spawn(async {
+        // needs to be real db call because of setup race condition in `fn setup_settings()`
+        let settings = get_settings_db().await.expect("Failed to get settings");
+        apply_lan_settings(&settings).await;
+    });
+}
+
+/// This device hosts LAN sync when it's the provider and no host is paired.
+pub fn is_lan_host(settings: &settings::Model) -> bool {
+    settings.sync_provider == SyncProviderType::Lan.to_string() && settings.lan_peer.is_empty()
+}
+
+/// Where the host keeps the synced files, same layout as a local sync folder.
+pub fn lan_store_path() -> PathBuf {
+    PathBuf::from(get_data_path().config_path).join(LAN_STORE_DIR)
+}
+
+/// Starts, restarts (port change) or stops the LAN host to match the settings.
+pub async fn apply_lan_settings(settings: &settings::Model) {
+    let mut server = get_lan_server().lock().await;
+
+    if !is_lan_host(settings) {
+        server.stop().await;
+        return;
+    }
+
+    let result = async {
+        let key = get_or_create_lan_key()?;
+        let store_path = lan_store_path();
+        tokio::fs::create_dir_all(&store_path).await?;
+        let store: Arc<dyn SyncProvider> = Arc::new(LocalFolderProviderImpl::new(store_path));
+
+        server
+            .start(settings.lan_port as u16, key, store, whoami::devicename())
+            .await?;
+
+        Ok::<(), CommandError>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        printlog!(
+            "failed to start lan server on {}: {:?}",
+            settings.lan_port,
+            e
+        );
+    }
+}
+
+fn get_or_create_lan_key() -> Result<[u8; 32], CommandError> {
+    if let Some(key) = get_credential(LAN_CREDENTIAL)?.and_then(|value| decode_lan_key(&value)) {
+        return Ok(key);
+    }
+
+    let key = random_bytes::<32>();
+    set_credential(LAN_CREDENTIAL, &encode_lan_key(&key))?;
+
+    Ok(key)
+}
+
+/// Makes this device the LAN sync host, other devices pair with it.
+pub async fn configure_lan_host() -> Result<settings::Model, CommandError> {
+    let mut settings = get_global_settings();
+    settings.sync_provider = SyncProviderType::Lan.to_string();
+    settings.lan_peer = String::new();
+
+    save_lan_settings(settings).await
+}
+
+/// Pairing code for another device, only while this device is the host.
+pub async fn start_lan_pairing() -> Result<String, CommandError> {
+    if !is_lan_host(&get_global_settings()) {
+        return Err(CommandError::new("This device is not the LAN sync host"));
+    }
+
+    get_lan_server()
+        .lock()
+        .await
+        .start_pairing()
+        .ok_or_else(|| CommandError::new("LAN sync host is not running"))
+}
+
+pub async fn discover_lan_peers() -> Result<Vec<LanPeer>, CommandError> {
+    Ok(mdns::browse(DISCOVERY_TIMEOUT).await?)
+}
+
+/// Pairs with the host at `address` (`ip:port`) and makes it the sync provider.
+pub async fn pair_lan_peer(address: String, code: String) -> Result<settings::Model, CommandError> {
+    let address = address.trim().to_string();
+    let key = pair_with_host(&address, &code, &whoami::devicename()).await?;
+
+    if !LanProviderImpl::new(&address, key).is_authenticated().await {
+        return Err(CommandError::new("Could not reach the LAN sync host"));
+    }
+
+    set_credential(LAN_CREDENTIAL, &encode_lan_key(&key))?;
+
+    let mut settings = get_global_settings();
+    settings.sync_provider = SyncProviderType::Lan.to_string();
+    settings.lan_peer = address;
+
+    save_lan_settings(settings).await
+}
+
+async fn save_lan_settings(settings: settings::Model) -> Result<settings::Model, CommandError> {
+    let active_model: settings::ActiveModel = settings.into();
+    let settings = settings::Entity::update(active_model.reset_all())
+        .exec(db())
+        .await?;
+
+    set_global_settings(settings.clone());
+    switch_sync_provider().await;
+    init_settings_window();
+
+    Ok(settings)
+}
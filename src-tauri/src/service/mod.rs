@@ -1,8 +1,15 @@
+pub mod api;
+pub mod archive;
+pub mod blob;
+pub mod cli;
 pub mod clipboard;
 pub mod decrypt;
 pub mod encrypt;
 pub mod hotkey;
 pub mod keyboard;
+pub mod lan;
+pub mod retention;
+pub mod search;
 pub mod settings;
 pub mod sync;
 pub mod window;
@@ -0,0 +1,145 @@
+use super::clipboard::{delete_clipboards_db, delete_expired_clipboards_db, init_clipboards};
+use super::settings::get_global_settings;
+use crate::prelude::*;
+use crate::tao::connection::db;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use common::types::enums::ClipboardType;
+use common::types::types::CommandError;
+use entity::clipboard;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Condition;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, Select};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::{NoContext, Timestamp};
+
+/// How often the retention settings are applied, besides after every new clipboard
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Most clipboards a rule deletes per run, the rest goes on the next one
+const RETENTION_BATCH: u64 = 1_000;
+
+/// Held while retention runs, a run that finds it taken is skipped
+static RETENTION_LOCK: Mutex<()> = Mutex::const_new(());
+
+pub fn setup_retention() {
+    tauri::async_runtime::spawn(async {
+        let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            apply_retention().await;
+        }
+    });
+}
+
+/// Applies the retention settings in the background.
+pub fn trigger_retention() {
+    tauri::async_runtime::spawn(apply_retention());
+}
+
+/// Deletes an expiring sensitive clipboard once `expires_at` is reached. Ones still
+/// waiting when the app quits go on the first retention run.
+pub fn schedule_expiry(expires_at: NaiveDateTime) {
+    let delay = (expires_at - Utc::now().naive_utc())
+        .to_std()
+        .unwrap_or_default();
+
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(delay).await;
+        match delete_expired_clipboards_db().await {
+            Ok(0) => {}
+            Ok(_) => init_clipboards(),
+            Err(e) => printlog!("failed to delete expired clipboards: {:?}", e),
+        }
+    });
+}
+
+async fn apply_retention() {
+    let Ok(_guard) = RETENTION_LOCK.try_lock() else {
+        return;
+    };
+
+    match apply_retention_db().await {
+        Ok(0) => {}
+        Ok(deleted) => {
+            printlog!("retention deleted {} clipboards", deleted);
+            init_clipboards();
+        }
+        Err(e) => printlog!("retention failed: {:?}", e),
+    }
+}
+
+/// Deletes the clipboards the retention settings expire and returns how many, a setting
+/// of 0 turns its rule off. Age counts from the last copy, which is what the v7 id holds
+/// (a bump renews it). Starred and named clipboards are never deleted and don't count
+/// towards the maximum. The deletions sync like the ones done by hand. Expired sensitive
+/// clipboards go as well.
+pub async fn apply_retention_db() -> Result<usize, CommandError> {
+    let settings = get_global_settings();
+    let expired = delete_expired_clipboards_db().await? as usize;
+    let mut ids = HashSet::new();
+
+    if settings.retention_days > 0 {
+        ids.extend(expired_clipboard_ids_db(settings.retention_days, None).await?);
+    }
+
+    if settings.retention_image_days > 0 {
+        ids.extend(
+            expired_clipboard_ids_db(settings.retention_image_days, Some(ClipboardType::Image))
+                .await?,
+        );
+    }
+
+    if settings.retention_max_clips > 0 {
+        let surplus: Vec<Uuid> = unprotected_clipboards()
+            .select_only()
+            .column(clipboard::Column::Id)
+            .order_by_desc(clipboard::Column::Id)
+            .offset(settings.retention_max_clips as u64)
+            .limit(RETENTION_BATCH)
+            .into_tuple()
+            .all(db())
+            .await?;
+        ids.extend(surplus);
+    }
+
+    if ids.is_empty() {
+        return Ok(expired);
+    }
+
+    let deleted = ids.len();
+    delete_clipboards_db(ids.into_iter().collect(), Some(true)).await?;
+
+    Ok(expired + deleted)
+}
+
+async fn expired_clipboard_ids_db(
+    days: i32,
+    r#type: Option<ClipboardType>,
+) -> Result<Vec<Uuid>, DbErr> {
+    let cutoff = (Utc::now() - TimeDelta::days(days.into())).timestamp();
+    // v7 ids sort by their timestamp, so one made at the cutoff splits them
+    let cutoff = Uuid::new_v7(Timestamp::from_unix(NoContext, cutoff as u64, 0));
+
+    unprotected_clipboards()
+        .select_only()
+        .column(clipboard::Column::Id)
+        .filter(clipboard::Column::Id.lt(cutoff))
+        .apply_if(r#type, |q, r#type| {
+            q.filter(clipboard::Column::Types.contains(r#type.to_string()))
+        })
+        .limit(RETENTION_BATCH)
+        .into_tuple()
+        .all(db())
+        .await
+}
+
+fn unprotected_clipboards() -> Select<clipboard::Entity> {
+    clipboard::Entity::find()
+        .filter(clipboard::Column::Star.eq(false))
+        .filter(
+            Condition::any()
+                .add(clipboard::Column::Name.is_null())
+                .add(clipboard::Column::Name.eq("")),
+        )
+}
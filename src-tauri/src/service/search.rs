@@ -0,0 +1,83 @@
+use super::clipboard::load_clipboards_for_search;
+use crate::prelude::*;
+use crate::tao::connection::db;
+use common::io::search::clipboard_search_text;
+use common::types::orm_query::FullClipboardDto;
+use entity::{clipboard, clipboard_search};
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::{Alias, Expr, OnConflict};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, JoinType, Order, QueryFilter, QuerySelect,
+    QueryTrait, RelationTrait, Select,
+};
+
+/// Writes the plain-text search content of a clipboard into `clipboard_search`, whose
+/// triggers keep the `clipboard_fts` index in sync. Encrypted clipboards are never
+/// indexed, so an encrypted or empty clipboard removes its row instead.
+pub async fn index_clipboard_search<C: ConnectionTrait>(
+    conn: &C,
+    clipboard: &FullClipboardDto,
+) -> Result<(), DbErr> {
+    let content = if clipboard.clipboard.encrypted {
+        String::new()
+    } else {
+        clipboard_search_text(clipboard)
+    };
+
+    if content.is_empty() {
+        clipboard_search::Entity::delete_many()
+            .filter(clipboard_search::Column::ClipboardId.eq(clipboard.clipboard.id))
+            .exec(conn)
+            .await?;
+        return Ok(());
+    }
+
+    clipboard_search::Entity::insert(clipboard_search::ActiveModel {
+        clipboard_id: Set(clipboard.clipboard.id),
+        content: Set(content),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::column(clipboard_search::Column::ClipboardId)
+            .update_column(clipboard_search::Column::Content)
+            .to_owned(),
+    )
+    .exec_without_returning(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Reloads a stored clipboard (without blobs) and refreshes its search content,
+/// e.g. after OCR text arrived or a single type was cleared from it.
+pub async fn reindex_clipboard_search(id: Uuid) -> Result<(), DbErr> {
+    let Some(model) = clipboard::Entity::find_by_id(id).one(db()).await? else {
+        return Ok(());
+    };
+
+    let clipboard = load_clipboards_for_search(vec![model]).await?.remove(0);
+    index_clipboard_search(db(), &clipboard).await
+}
+
+/// Restricts a clipboard query to rows matching an FTS5 expression (see
+/// `SearchQuery::fts_query`) and orders them by bm25 rank, best match first.
+pub fn filter_clipboard_search(
+    query: Select<clipboard::Entity>,
+    fts_query: String,
+) -> Select<clipboard::Entity> {
+    let mut query = query.join(
+        JoinType::InnerJoin,
+        clipboard::Relation::ClipboardSearch.def(),
+    );
+
+    QueryTrait::query(&mut query)
+        .join(
+            JoinType::InnerJoin,
+            Alias::new("clipboard_fts"),
+            Expr::cust("clipboard_fts.rowid = clipboard_search.id"),
+        )
+        .and_where(Expr::cust_with_values("clipboard_fts MATCH ?", [fts_query]))
+        .order_by_expr(Expr::cust("clipboard_fts.rank"), Order::Asc);
+
+    query
+}
@@ -1,14 +1,20 @@
+use super::api::{api_port, apply_api_settings, generate_api_token};
 use super::cipher::{init_password_lock_event, is_encryption_key_set};
 use super::clipboard::get_last_clipboard_db;
 use super::decrypt::decrypt_all_clipboards;
-use super::sync::upsert_settings_sync;
+use super::sync::{switch_sync_provider, upsert_settings_sync};
 use crate::config::tray::refresh_tray_menu;
 use crate::prelude::*;
 use crate::service::window::{get_monitor_scale_factor, refresh_window_titles};
 use crate::tao::connection::db;
-use crate::tao::global::get_app;
+use crate::tao::global::{get_app, is_headless};
+use crate::tao::tao_constants::HEADLESS_SETTINGS;
+use common::constants::{API_PORT_MAX, API_PORT_MIN};
 use common::io::language::get_system_language;
+use common::types::capture::AppRule;
 use common::types::enums::{ListenEvent, PasswordAction};
+use common::types::sensitive::SensitiveRule;
+use common::types::sync::SyncRule;
 use common::types::types::{CommandError, TextMatcher};
 use entity::settings;
 use sea_orm::{ActiveModelTrait, EntityTrait};
@@ -19,6 +25,25 @@ use tauri::Manager;
 use tauri::{Emitter, EventTarget};
 use tauri_plugin_autostart::AutoLaunchManager;
 
+/// Settings that stay on this device: neither uploaded nor taken from sync.
+pub const LOCAL_ONLY_SETTINGS: [&str; 15] = [
+    "api_enabled",
+    "api_port",
+    "api_token",
+    "sync_provider",
+    "webdav_url",
+    "webdav_username",
+    "local_sync_path",
+    "s3_endpoint",
+    "s3_region",
+    "s3_bucket",
+    "s3_prefix",
+    "s3_access_key_id",
+    "lan_port",
+    "lan_peer",
+    "external_blob_store",
+];
+
 pub fn autostart() {
     tauri::async_runtime::spawn(async {
         let settings = get_global_settings();
@@ -47,14 +72,42 @@ pub async fn get_settings_db() -> Result<settings::Model, DbErr> {
 }
 
 pub async fn update_settings_db(
-    settings: settings::Model,
+    mut settings: settings::Model,
 ) -> Result<settings::Model, CommandError> {
     let db = db();
 
+    if api_port(settings.api_port).is_none() {
+        return Err(CommandError::Error(format!(
+            "API port has to be between {} and {}",
+            API_PORT_MIN, API_PORT_MAX
+        )));
+    }
+
+    // The local API is never served without a token, create one on first enable.
+    if settings.api_enabled && settings.api_token.is_empty() {
+        settings.api_token = generate_api_token();
+    }
+    let previous = get_global_settings();
+    // Only `migrate_blob_store` switches the store, it moves the existing blobs along
+    settings.external_blob_store = previous.external_blob_store;
+    let api_changed =
+        previous.api_enabled != settings.api_enabled || previous.api_port != settings.api_port;
+    let sync_provider_changed = previous.sync_provider != settings.sync_provider
+        || previous.webdav_url != settings.webdav_url
+        || previous.webdav_username != settings.webdav_username
+        || previous.local_sync_path != settings.local_sync_path
+        || previous.s3_endpoint != settings.s3_endpoint
+        || previous.s3_region != settings.s3_region
+        || previous.s3_bucket != settings.s3_bucket
+        || previous.s3_prefix != settings.s3_prefix
+        || previous.s3_access_key_id != settings.s3_access_key_id
+        || previous.lan_port != settings.lan_port
+        || previous.lan_peer != settings.lan_peer;
+
     // Re-apply the native window effect only on a glass toggle. The tint/opacity is
     // done in CSS (it layers over the native blur — see styles.css/window.rs), so the
     // tint slider needs no native re-apply; the native acrylic is just the blur body.
-    let glass_changed = get_global_settings().glass != settings.glass;
+    let glass_changed = previous.glass != settings.glass;
     let glass = settings.glass;
 
     let active_model: settings::ActiveModel = settings.into();
@@ -83,6 +136,14 @@ pub async fn update_settings_db(
     refresh_tray_menu();
     refresh_window_titles();
 
+    if api_changed {
+        apply_api_settings(&settings).await;
+    }
+
+    if sync_provider_changed {
+        switch_sync_provider().await;
+    }
+
     upsert_settings_sync(&settings, false).await?;
 
     init_settings_window();
@@ -110,6 +171,28 @@ pub async fn update_settings_synchronize_db(sync: bool) -> Result<settings::Mode
     Ok(settings)
 }
 
+pub async fn update_settings_external_blob_store_db(
+    external_blob_store: bool,
+) -> Result<settings::Model, DbErr> {
+    let db = db();
+
+    let mut settings = get_global_settings();
+
+    settings.external_blob_store = external_blob_store;
+
+    let active_model: settings::ActiveModel = settings.into();
+
+    let settings = settings::Entity::update(active_model.reset_all())
+        .exec(db)
+        .await?;
+
+    set_global_settings(settings.clone());
+
+    init_settings_window();
+
+    Ok(settings)
+}
+
 pub fn setup_settings() {
     get_app().manage(Mutex::new(settings::Model::default()));
 
@@ -156,6 +239,72 @@ pub async fn update_settings_text_matchers(
     Ok(text_matchers)
 }
 
+pub async fn update_settings_sync_rules(
+    sync_rules: Vec<SyncRule>,
+) -> Result<Vec<SyncRule>, CommandError> {
+    let mut settings = get_global_settings();
+
+    settings.sync_rules = json!(sync_rules);
+
+    let active_model: settings::ActiveModel = settings.into();
+
+    let settings = settings::Entity::update(active_model.reset_all())
+        .exec(db())
+        .await?;
+
+    set_global_settings(settings.clone());
+
+    init_settings_window();
+
+    upsert_settings_sync(&settings, false).await?;
+
+    Ok(sync_rules)
+}
+
+pub async fn update_settings_sensitive_rules(
+    sensitive_rules: Vec<SensitiveRule>,
+) -> Result<Vec<SensitiveRule>, CommandError> {
+    let mut settings = get_global_settings();
+
+    settings.sensitive_rules = json!(sensitive_rules);
+
+    let active_model: settings::ActiveModel = settings.into();
+
+    let settings = settings::Entity::update(active_model.reset_all())
+        .exec(db())
+        .await?;
+
+    set_global_settings(settings.clone());
+
+    init_settings_window();
+
+    upsert_settings_sync(&settings, false).await?;
+
+    Ok(sensitive_rules)
+}
+
+pub async fn update_settings_app_rules(
+    app_rules: Vec<AppRule>,
+) -> Result<Vec<AppRule>, CommandError> {
+    let mut settings = get_global_settings();
+
+    settings.app_rules = json!(app_rules);
+
+    let active_model: settings::ActiveModel = settings.into();
+
+    let settings = settings::Entity::update(active_model.reset_all())
+        .exec(db())
+        .await?;
+
+    set_global_settings(settings.clone());
+
+    init_settings_window();
+
+    upsert_settings_sync(&settings, false).await?;
+
+    Ok(app_rules)
+}
+
 pub async fn update_settings_from_sync(
     remote_settings: HashMap<String, serde_json::Value>,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -181,6 +330,10 @@ pub async fn update_settings_from_sync(
     remote_settings.remove("display_scale");
     // Skip startup as it users choice
     remote_settings.remove("startup");
+    // Skip the local API, it is per device and the token is never synced
+    for key in LOCAL_ONLY_SETTINGS {
+        remote_settings.remove(key);
+    }
 
     let local_encryption = current_settings.encryption;
 
@@ -262,14 +415,21 @@ pub fn init_settings_window() {
         .expect("Failed to emit download progress event");
 }
 
+fn global_settings_state() -> &'static Mutex<settings::Model> {
+    if is_headless() {
+        return HEADLESS_SETTINGS.get_or_init(|| Mutex::new(settings::Model::default()));
+    }
+    get_app().state::<Mutex<settings::Model>>().inner()
+}
+
 pub fn get_global_settings() -> settings::Model {
-    let state = get_app().state::<Mutex<settings::Model>>();
+    let state = global_settings_state();
     let locked_settings = state.lock().expect("Failed to lock settings");
     locked_settings.clone()
 }
 
 pub fn set_global_settings(settings: settings::Model) {
-    let state = get_app().state::<Mutex<settings::Model>>();
+    let state = global_settings_state();
     let mut locked_settings = state.lock().expect("Failed to lock settings");
     *locked_settings = settings;
 }
@@ -1,59 +1,503 @@
+use super::lan::{apply_lan_settings, lan_store_path};
 use super::settings::{
-    get_global_settings, get_settings_db, update_settings_from_sync, update_settings_synchronize_db,
+    get_global_settings, get_settings_db, init_settings_window, set_global_settings,
+    update_settings_from_sync, update_settings_synchronize_db, LOCAL_ONLY_SETTINGS,
 };
 use crate::{
     prelude::*,
-    tao::global::get_app,
-    utils::{providers::google_drive::GoogleDriveProviderImpl, sync_manager::SyncManager},
+    tao::{config::get_device_id, connection::db, global::get_app, tao_constants::SYNC_PROVIDER},
+    utils::{
+        providers::{
+            google_drive::GoogleDriveProviderImpl, lan::LanProviderImpl,
+            local_folder::LocalFolderProviderImpl, s3::S3ProviderImpl, set_credential,
+            webdav::WebDavProviderImpl,
+        },
+        sync_manager::SyncManager,
+    },
+};
+use chrono::Utc;
+use common::constants::{
+    DEVICE_MANIFEST_NAME, S3_CREDENTIAL, SYNC_CONFLICT_LOG_LIMIT, WEBDAV_CREDENTIAL,
+};
+use common::types::{
+    enums::{SyncConflictField, SyncConflictWinner, SyncProviderType},
+    sync::{SyncDevice, SyncDevices, SyncProvider},
+    types::CommandError,
+};
+use entity::{clipboard, settings, sync_conflict, sync_state, tombstone};
+use sea_orm::{
+    prelude::{Expr, Uuid},
+    sea_query::OnConflict,
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
 };
-use common::types::{enums::SyncProviderType, sync::SyncProvider, types::CommandError};
-use entity::settings;
 use std::{collections::HashMap, sync::Arc};
 use tauri::{Manager, State};
 use tokio::sync::Mutex;
 
+/// Writes of the device manifest before giving up on devices writing at the same time
+const DEVICE_MANIFEST_ATTEMPTS: usize = 3;
+
 pub async fn upsert_settings_sync(
     settings: &settings::Model,
     block_main_thread: bool,
 ) -> Result<(), CommandError> {
     if settings.sync {
-        let settings_map: HashMap<String, serde_json::Value> =
+        let mut settings_map: HashMap<String, serde_json::Value> =
             serde_json::from_value(serde_json::to_value(settings.clone())?)?;
+        for key in LOCAL_ONLY_SETTINGS {
+            settings_map.remove(key);
+        }
         if block_main_thread {
             get_sync_provider()
-                .await
+                .await?
                 .upsert_settings(&settings_map)
                 .await
                 .expect("Failed to upsert settings");
         } else {
             tauri::async_runtime::spawn(async move {
-                get_sync_provider()
-                    .await
-                    .upsert_settings(&settings_map)
-                    .await
-                    .expect("Failed to upsert settings");
+                match get_sync_provider().await {
+                    Ok(provider) => provider
+                        .upsert_settings(&settings_map)
+                        .await
+                        .expect("Failed to upsert settings"),
+                    Err(e) => printlog!("failed to upload settings: {:?}", e),
+                }
             });
         }
     }
     Ok(())
 }
 
-pub async fn get_sync_provider() -> State<'static, Arc<dyn SyncProvider>> {
-    if let Some(sync_state) = get_app().try_state() {
-        return sync_state;
+/// The provider from the settings, created on first use. Fails on incomplete settings,
+/// like a WebDAV password missing from the keychain, nothing is cached then.
+pub async fn get_sync_provider() -> Result<Arc<dyn SyncProvider>, CommandError> {
+    let mut sync_provider = SYNC_PROVIDER.lock().await;
+    if let Some(provider) = sync_provider.as_ref() {
+        return Ok(provider.clone());
     }
 
-    let provider = match get_global_settings().sync_provider.as_str() {
-        s if s == SyncProviderType::GoogleDrive.to_string() => Arc::new(
-            GoogleDriveProviderImpl::new()
-                .await
-                .expect("Failed to create Google Drive provider"),
-        ),
-        _ => panic!("Provider type not implemented"),
+    let provider: Arc<dyn SyncProvider> = match get_global_settings().sync_provider.as_str() {
+        s if s == SyncProviderType::GoogleDrive.to_string() => {
+            Arc::new(GoogleDriveProviderImpl::new().await?)
+        }
+        s if s == SyncProviderType::WebDav.to_string() => {
+            Arc::new(WebDavProviderImpl::from_settings()?)
+        }
+        s if s == SyncProviderType::LocalFolder.to_string() => {
+            Arc::new(LocalFolderProviderImpl::from_settings()?)
+        }
+        s if s == SyncProviderType::S3.to_string() => Arc::new(S3ProviderImpl::from_settings()?),
+        // The host syncs with its own store, paired devices with the host
+        s if s == SyncProviderType::Lan.to_string() => {
+            if get_global_settings().lan_peer.is_empty() {
+                Arc::new(LocalFolderProviderImpl::new(lan_store_path()))
+            } else {
+                Arc::new(LanProviderImpl::from_settings()?)
+            }
+        }
+        s => {
+            return Err(CommandError::Error(format!(
+                "Sync provider {} not implemented",
+                s
+            )))
+        }
     };
 
-    get_app().manage(provider.clone() as Arc<dyn SyncProvider>);
-    get_app().state()
+    *sync_provider = Some(provider.clone());
+    Ok(provider)
+}
+
+/// Drops the cached provider and its sync state so the next sync uses the one from the
+/// settings, a running sync loop is restarted with it. The LAN host follows the settings too.
+pub async fn switch_sync_provider() {
+    *SYNC_PROVIDER.lock().await = None;
+
+    // The sync state describes the old provider's files, the new one starts from scratch
+    if let Err(e) = sync_state::Entity::delete_many().exec(db()).await {
+        printlog!("failed to clear sync state: {:?}", e);
+    }
+    apply_lan_settings(&get_global_settings()).await;
+
+    if get_global_settings().sync {
+        let mut manager = get_sync_manager().lock().await;
+        manager.stop().await;
+        manager.start().await;
+    }
+}
+
+/// Checks the WebDAV folder is reachable with the credentials, keeps the password in
+/// the keychain and makes WebDAV the sync provider.
+pub async fn configure_webdav_sync(
+    url: String,
+    username: String,
+    password: String,
+) -> Result<settings::Model, CommandError> {
+    let provider = WebDavProviderImpl::new(&url, &username, &password)?;
+    if !provider.is_authenticated().await {
+        return Err(CommandError::new("Could not connect to the WebDAV server"));
+    }
+
+    set_credential(WEBDAV_CREDENTIAL, &password)?;
+
+    let mut settings = get_global_settings();
+    settings.sync_provider = SyncProviderType::WebDav.to_string();
+    settings.webdav_url = provider.url().to_string();
+    settings.webdav_username = username;
+
+    let active_model: settings::ActiveModel = settings.into();
+    let settings = settings::Entity::update(active_model.reset_all())
+        .exec(db())
+        .await?;
+
+    set_global_settings(settings.clone());
+    switch_sync_provider().await;
+    init_settings_window();
+
+    Ok(settings)
+}
+
+/// Makes a local directory the sync provider, creating it if needed. Whatever keeps
+/// that directory in sync between devices is up to the user.
+pub async fn configure_local_folder_sync(path: String) -> Result<settings::Model, CommandError> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err(CommandError::new("Sync folder must not be empty"));
+    }
+
+    tokio::fs::create_dir_all(&path).await?;
+
+    let provider = LocalFolderProviderImpl::new(&path);
+    if !provider.is_authenticated().await {
+        return Err(CommandError::new("Sync folder is not writable"));
+    }
+
+    let mut settings = get_global_settings();
+    settings.sync_provider = SyncProviderType::LocalFolder.to_string();
+    settings.local_sync_path = provider.path().to_string_lossy().to_string();
+
+    let active_model: settings::ActiveModel = settings.into();
+    let settings = settings::Entity::update(active_model.reset_all())
+        .exec(db())
+        .await?;
+
+    set_global_settings(settings.clone());
+    switch_sync_provider().await;
+    init_settings_window();
+
+    Ok(settings)
+}
+
+/// Checks the bucket can be listed with the keys, keeps the secret key in the keychain
+/// and makes S3 the sync provider.
+pub async fn configure_s3_sync(
+    endpoint: String,
+    region: String,
+    bucket: String,
+    prefix: String,
+    access_key_id: String,
+    secret_access_key: String,
+) -> Result<settings::Model, CommandError> {
+    let provider = S3ProviderImpl::new(
+        &endpoint,
+        &region,
+        &bucket,
+        &prefix,
+        &access_key_id,
+        &secret_access_key,
+    )?;
+    if !provider.is_authenticated().await {
+        return Err(CommandError::new("Could not access the S3 bucket"));
+    }
+
+    set_credential(S3_CREDENTIAL, &secret_access_key)?;
+
+    let mut settings = get_global_settings();
+    settings.sync_provider = SyncProviderType::S3.to_string();
+    settings.s3_endpoint = provider.endpoint().to_string();
+    settings.s3_region = provider.region().to_string();
+    settings.s3_bucket = bucket.trim().to_string();
+    settings.s3_prefix = provider.prefix().to_string();
+    settings.s3_access_key_id = access_key_id.trim().to_string();
+
+    let active_model: settings::ActiveModel = settings.into();
+    let settings = settings::Entity::update(active_model.reset_all())
+        .exec(db())
+        .await?;
+
+    set_global_settings(settings.clone());
+    switch_sync_provider().await;
+    init_settings_window();
+
+    Ok(settings)
+}
+
+/// What the provider last saw of each clipboard, keyed by clipboard id.
+pub async fn get_sync_states_db() -> Result<HashMap<Uuid, sync_state::Model>, DbErr> {
+    let states = sync_state::Entity::find().all(db()).await?;
+
+    Ok(states
+        .into_iter()
+        .map(|state| (state.clipboard_id, state))
+        .collect())
+}
+
+/// Records `clipboard` as synced, as stored under `provider_id` with `content_hash`.
+pub async fn upsert_sync_state_db(
+    clipboard: &clipboard::Model,
+    provider_id: &str,
+    content_hash: &str,
+) -> Result<(), DbErr> {
+    sync_state::Entity::insert(sync_state::ActiveModel {
+        clipboard_id: Set(clipboard.id),
+        provider_id: Set(provider_id.to_string()),
+        star: Set(clipboard.star),
+        name: Set(clipboard.name.clone()),
+        encrypted: Set(clipboard.encrypted),
+        content_hash: Set(content_hash.to_string()),
+        synced_at: Set(Utc::now().naive_utc()),
+    })
+    .on_conflict(
+        OnConflict::column(sync_state::Column::ClipboardId)
+            .update_columns([
+                sync_state::Column::ProviderId,
+                sync_state::Column::Star,
+                sync_state::Column::Name,
+                sync_state::Column::Encrypted,
+                sync_state::Column::ContentHash,
+                sync_state::Column::SyncedAt,
+            ])
+            .to_owned(),
+    )
+    .exec_without_returning(db())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_sync_states_db(ids: Vec<Uuid>) -> Result<(), DbErr> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    sync_state::Entity::delete_many()
+        .filter(sync_state::Column::ClipboardId.is_in(ids))
+        .exec(db())
+        .await?;
+
+    Ok(())
+}
+
+/// Logs how a clipboard changed on both sides was merged, keeping only the newest
+/// `SYNC_CONFLICT_LOG_LIMIT` entries.
+pub async fn insert_sync_conflict_db(
+    clipboard_id: Uuid,
+    field: SyncConflictField,
+    local_value: Option<String>,
+    remote_value: Option<String>,
+    winner: SyncConflictWinner,
+) -> Result<(), DbErr> {
+    let db = db();
+
+    sync_conflict::ActiveModel {
+        clipboard_id: Set(clipboard_id),
+        field: Set(field.to_string()),
+        local_value: Set(local_value),
+        remote_value: Set(remote_value),
+        winner: Set(winner.to_string()),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    let keep = sync_conflict::Entity::find()
+        .select_only()
+        .column(sync_conflict::Column::Id)
+        .order_by_desc(sync_conflict::Column::Id)
+        .limit(SYNC_CONFLICT_LOG_LIMIT)
+        .into_tuple::<i32>()
+        .all(db)
+        .await?;
+
+    if keep.len() as u64 == SYNC_CONFLICT_LOG_LIMIT {
+        if let Some(oldest) = keep.last() {
+            sync_conflict::Entity::delete_many()
+                .filter(sync_conflict::Column::Id.lt(*oldest))
+                .exec(db)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Conflict log, newest first.
+pub async fn get_sync_conflicts_db() -> Result<Vec<sync_conflict::Model>, DbErr> {
+    sync_conflict::Entity::find()
+        .order_by_desc(sync_conflict::Column::Id)
+        .all(db())
+        .await
+}
+
+pub async fn clear_sync_conflicts_db() -> Result<(), DbErr> {
+    sync_conflict::Entity::delete_many().exec(db()).await?;
+
+    Ok(())
+}
+
+/// Records clipboards deleted on this device, the next sync publishes them.
+pub async fn insert_tombstones_db(ids: Vec<Uuid>) -> Result<(), DbErr> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let deleted_at = Utc::now().naive_utc();
+    tombstone::Entity::insert_many(ids.into_iter().map(|id| tombstone::ActiveModel {
+        clipboard_id: Set(id),
+        deleted_at: Set(deleted_at),
+        published: Set(false),
+    }))
+    .on_conflict(
+        OnConflict::column(tombstone::Column::ClipboardId)
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_tombstones_db() -> Result<Vec<tombstone::Model>, DbErr> {
+    tombstone::Entity::find().all(db()).await
+}
+
+pub async fn publish_tombstones_db(ids: Vec<Uuid>) -> Result<(), DbErr> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    tombstone::Entity::update_many()
+        .col_expr(tombstone::Column::Published, Expr::value(true))
+        .filter(tombstone::Column::ClipboardId.is_in(ids))
+        .exec(db())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_tombstones_db(ids: Vec<Uuid>) -> Result<(), DbErr> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    tombstone::Entity::delete_many()
+        .filter(tombstone::Column::ClipboardId.is_in(ids))
+        .exec(db())
+        .await?;
+
+    Ok(())
+}
+
+/// Devices in the provider's manifest, empty until the first device synced.
+pub async fn read_sync_devices(
+    provider: &dyn SyncProvider,
+) -> Result<Vec<SyncDevice>, Box<dyn std::error::Error>> {
+    Ok(match provider.read_document(DEVICE_MANIFEST_NAME).await? {
+        Some(content) => serde_json::from_str(&content)?,
+        None => Vec::new(),
+    })
+}
+
+/// Merges `changed` into the manifest by id, the newer entry of a device wins and a
+/// revoked device stays revoked. Providers have no atomic update of a document, so it
+/// is read back after writing and written again while another device's write dropped
+/// one of the changes. Returns the manifest as written.
+pub async fn merge_sync_devices(
+    provider: &dyn SyncProvider,
+    changed: &[SyncDevice],
+) -> Result<Vec<SyncDevice>, Box<dyn std::error::Error>> {
+    for _ in 0..DEVICE_MANIFEST_ATTEMPTS {
+        let mut devices = read_sync_devices(provider).await?;
+        for device in changed {
+            match devices.iter_mut().find(|existing| existing.id == device.id) {
+                Some(existing) => {
+                    let revoked = existing.revoked || device.revoked;
+                    if device.last_seen >= existing.last_seen {
+                        *existing = device.clone();
+                    }
+                    existing.revoked = revoked;
+                }
+                None => devices.push(device.clone()),
+            }
+        }
+
+        provider
+            .write_document(DEVICE_MANIFEST_NAME, serde_json::to_string(&devices)?)
+            .await?;
+
+        let written = read_sync_devices(provider).await?;
+        let kept = changed.iter().all(|device| {
+            written.iter().any(|existing| {
+                existing.id == device.id
+                    && existing.last_seen >= device.last_seen
+                    && (existing.revoked || !device.revoked)
+            })
+        });
+        if kept {
+            return Ok(written);
+        }
+    }
+
+    Err("Device manifest keeps changing, try again".into())
+}
+
+/// Manifest entry of this device, seen now.
+pub fn current_sync_device() -> SyncDevice {
+    SyncDevice {
+        id: get_device_id(),
+        name: whoami::devicename(),
+        os: std::env::consts::OS.to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        last_seen: Utc::now().naive_utc(),
+        revoked: false,
+    }
+}
+
+/// Device manifest of the configured provider, most recently seen first.
+pub async fn get_sync_devices() -> Result<SyncDevices, CommandError> {
+    let provider = get_sync_provider().await?;
+    let mut devices = read_sync_devices(provider.as_ref()).await?;
+    devices.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+
+    Ok(SyncDevices {
+        device_id: get_device_id(),
+        devices,
+    })
+}
+
+/// Revokes another device, e.g. a lost or retired laptop.
+///
+/// Revoking is cooperative: the device turns sync off when it reads the manifest, it
+/// keeps the provider credentials, the encryption password and the LAN key it has.
+/// Shutting out a device that doesn't cooperate takes changing those.
+pub async fn revoke_sync_device(device_id: String) -> Result<SyncDevices, CommandError> {
+    if device_id == get_device_id() {
+        return Err(CommandError::new("This device can't be revoked"));
+    }
+
+    let provider = get_sync_provider().await?;
+    let mut device = read_sync_devices(provider.as_ref())
+        .await?
+        .into_iter()
+        .find(|device| device.id == device_id)
+        .ok_or_else(|| CommandError::new("Device not found"))?;
+    device.revoked = true;
+
+    merge_sync_devices(provider.as_ref(), &[device]).await?;
+    printlog!("(remote) revoked device {}", device_id);
+
+    get_sync_devices().await
 }
 
 pub fn get_sync_manager() -> State<'static, Mutex<SyncManager>> {
@@ -83,7 +527,7 @@ pub async fn sync_toggle() -> Result<bool, CommandError> {
 
     if new_sync_state {
         // Trying to enable sync
-        let provider = get_sync_provider().await;
+        let provider = get_sync_provider().await?;
         if !provider.is_authenticated().await {
             update_settings_synchronize_db(false).await?;
             return Err(CommandError::Error("Authentication failed".to_string()));
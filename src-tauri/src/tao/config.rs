@@ -1,9 +1,11 @@
 use crate::tao::global::get_app;
+use crate::tao::tao_constants::{APP, DEVICE_ID};
 use common::{
-    constants::{CONFIG_NAME, DB_NAME},
+    constants::{APP_IDENTIFIER, CONFIG_NAME, DB_NAME},
     printlog,
     types::types::{Config, DataPath},
 };
+use sea_orm::prelude::Uuid;
 use std::{
     fs,
     path::{Path, PathBuf},
@@ -20,6 +22,7 @@ pub fn setup_config() {
 
     let config = Config {
         db: format!("{}", &data_path.db_file_path),
+        device_id: Uuid::now_v7().to_string(),
     };
 
     fs::write(
@@ -40,13 +43,14 @@ pub fn get_data_path() -> DataPath {
 
         current_dir.to_string_lossy().to_string()
     } else {
-        // Use app data dir in production
-        get_app()
-            .path()
-            .app_data_dir()
-            .expect("Failed to get app data dir")
-            .to_string_lossy()
-            .to_string()
+        // Use app data dir in production, the headless CLI resolves it like tauri does
+        match APP.get() {
+            Some(app) => app.path().app_data_dir().ok(),
+            None => dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER)),
+        }
+        .expect("Failed to get app data dir")
+        .to_string_lossy()
+        .to_string()
     };
 
     fs::create_dir_all(&config_path).expect("Failed to create config directory");
@@ -80,6 +84,25 @@ pub fn get_config() -> (Config, DataPath) {
     (config, data_path)
 }
 
+/// Id of this installation, generated and written to the config if it has none yet.
+pub fn get_device_id() -> String {
+    DEVICE_ID
+        .get_or_init(|| {
+            let (mut config, data_path) = get_config();
+
+            if config.device_id.is_empty() {
+                config.device_id = Uuid::now_v7().to_string();
+                let _ = fs::write(
+                    &data_path.config_file_path,
+                    serde_json::to_string(&config).expect("Failed to serialize config"),
+                );
+            }
+
+            config.device_id
+        })
+        .clone()
+}
+
 pub fn change_clipboard_db_location_enable() {
     // get local config from app data
     let (mut config, data_path) = get_config();
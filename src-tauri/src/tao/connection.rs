@@ -8,11 +8,7 @@ use sea_orm::{DbConn, SqlxSqliteConnector};
 use std::time::Duration;
 
 pub async fn init_db() -> Result<(), DbErr> {
-    let database_url = if cfg!(debug_assertions) {
-        get_debug_database_url()
-    } else {
-        get_prod_database_url()
-    };
+    let database_url = get_database_url();
 
     // Try the existing database first. If connecting or migrating fails (e.g. a
     // schema from an incompatible version, or a corrupt file), we don't want to
@@ -34,6 +30,15 @@ pub async fn init_db() -> Result<(), DbErr> {
     Ok(())
 }
 
+/// Opens the database for a headless `clippy <subcommand>` run. Unlike `init_db` a
+/// broken database is reported instead of being moved aside, the CLI must never
+/// replace the user's history behind the app's back.
+pub async fn init_headless_db() -> Result<(), DbErr> {
+    let conn = connect_and_migrate(&get_database_url()).await?;
+    set_db(conn);
+    Ok(())
+}
+
 async fn connect_and_migrate(database_url: &str) -> Result<DbConn, DbErr> {
     // sqlx only accepts mode/cache/immutable/vfs as URL query params; pragmas like
     // journal_mode MUST be set via SqliteConnectOptions (applied to every pooled
@@ -47,7 +52,9 @@ async fn connect_and_migrate(database_url: &str) -> Result<DbConn, DbErr> {
         .journal_mode(SqliteJournalMode::Wal)
         .busy_timeout(Duration::from_secs(5))
         // Statement logging floods the log file with every query otherwise.
-        .disable_statement_logging();
+        .disable_statement_logging()
+        // REGEXP() for `/regex/` search terms (see SearchQuery::to_condition).
+        .with_regexp();
 
     // Small pool: SQLite is single-writer, but >1 connection stops one long-running
     // reader (sync loop, search scan) from starving clipboard inserts.
@@ -117,6 +124,14 @@ pub fn db() -> &'static DbConn {
 /// in connect_and_migrate via SqliteConnectOptions; putting them here makes connect fail.
 const SQLITE_PARAMS: &str = "mode=rwc";
 
+fn get_database_url() -> String {
+    if cfg!(debug_assertions) {
+        get_debug_database_url()
+    } else {
+        get_prod_database_url()
+    }
+}
+
 fn get_prod_database_url() -> String {
     let data_path = get_data_path();
 
@@ -1,13 +1,13 @@
 use super::tao_constants::{
-    APP, CLIPBOARD_CACHE, GLOBAL_HOTKEYS, GLOBAL_HOTKEY_MANAGER, HOTKEY_RUNNING, HOTKEY_STOP_TX,
-    MAIN_WINDOW, WINDOW_STOP_TX,
+    API_EVENTS, APP, CLIPBOARD_CACHE, GLOBAL_HOTKEYS, GLOBAL_HOTKEY_MANAGER, HOTKEY_RUNNING,
+    HOTKEY_STOP_TX, MAIN_WINDOW, WINDOW_STOP_TX,
 };
 use crate::tao::tao_constants::{WINDOW_HOTKEYS, WINDOW_HOTKEY_MANAGER};
 use common::types::{hotkey::SafeHotKeyManager, orm_query::FullClipboardDto, types::Key};
 use moka::sync::Cache;
 use std::{collections::HashMap, sync::MutexGuard, time::Duration};
 use tauri::{AppHandle, WebviewWindow};
-use tokio::sync::oneshot;
+use tokio::sync::{broadcast, oneshot};
 
 pub fn get_main_window() -> MutexGuard<'static, WebviewWindow> {
     MAIN_WINDOW
@@ -77,6 +77,11 @@ pub fn get_app() -> &'static AppHandle {
     APP.get().expect("Failed to get APP")
 }
 
+/// True for `clippy <subcommand>` runs that found no running app to forward to.
+pub fn is_headless() -> bool {
+    APP.get().is_none()
+}
+
 pub fn get_cache() -> &'static Cache<String, Vec<FullClipboardDto>> {
     CLIPBOARD_CACHE.get_or_init(|| {
         Cache::builder()
@@ -84,3 +89,8 @@ pub fn get_cache() -> &'static Cache<String, Vec<FullClipboardDto>> {
             .build()
     })
 }
+
+pub fn get_api_events() -> &'static broadcast::Sender<FullClipboardDto> {
+    // Slow SSE clients lag behind and skip events instead of blocking the sender
+    API_EVENTS.get_or_init(|| broadcast::channel(64).0)
+}
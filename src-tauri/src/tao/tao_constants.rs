@@ -1,15 +1,16 @@
 use common::types::{
-    enums::WebWindow, hotkey::SafeHotKeyManager, orm_query::FullClipboardDto, types::Key,
+    enums::WebWindow, hotkey::SafeHotKeyManager, orm_query::FullClipboardDto, sync::SyncProvider,
+    types::Key,
 };
 use global_hotkey::GlobalHotKeyManager;
 use moka::sync::Cache;
-use sea_orm::Iden;
+use sea_orm::{prelude::Uuid, Iden};
 use std::{
     collections::HashMap,
     sync::{atomic::AtomicU64, Arc, Mutex, OnceLock},
 };
 use tauri::{Manager, WebviewWindow};
-use tokio::sync::oneshot;
+use tokio::sync::{broadcast, mpsc, oneshot};
 
 pub static DB: OnceLock<sea_orm::DbConn> = OnceLock::new();
 pub static APP: OnceLock<tauri::AppHandle> = OnceLock::new();
@@ -25,6 +26,18 @@ pub static WINDOW_HOTKEYS: OnceLock<Arc<Mutex<HashMap<u32, Key>>>> = OnceLock::n
 pub static HOTKEY_STOP_TX: OnceLock<Mutex<Option<oneshot::Sender<()>>>> = OnceLock::new();
 pub static WINDOW_STOP_TX: OnceLock<Mutex<Option<oneshot::Sender<()>>>> = OnceLock::new();
 pub static CLIPBOARD_CACHE: OnceLock<Cache<String, Vec<FullClipboardDto>>> = OnceLock::new();
+/// New clipboards for the local API's event stream, every SSE client subscribes to it.
+pub static API_EVENTS: OnceLock<broadcast::Sender<FullClipboardDto>> = OnceLock::new();
+/// Provider of the configured sync backend, built on first use and dropped when the
+/// provider settings change.
+pub static SYNC_PROVIDER: tokio::sync::Mutex<Option<Arc<dyn SyncProvider>>> =
+    tokio::sync::Mutex::const_new(None);
+/// New clipboards waiting for upload, set while the sync loop runs.
+pub static SYNC_PUSH_QUEUE: Mutex<Option<mpsc::UnboundedSender<Uuid>>> = Mutex::new(None);
+/// Cached `Config::device_id`, see `get_device_id`.
+pub static DEVICE_ID: OnceLock<String> = OnceLock::new();
+/// Settings of a headless `clippy <subcommand>` run, the app manages them as state otherwise.
+pub static HEADLESS_SETTINGS: OnceLock<Mutex<entity::settings::Model>> = OnceLock::new();
 
 /// Monotonic search generation. Each search_clipboards call bumps it; an in-flight
 /// stream bails when it sees a newer generation (cancels superseded searches).
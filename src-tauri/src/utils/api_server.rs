@@ -0,0 +1,369 @@
+use crate::prelude::*;
+use crate::service::cipher::is_encryption_key_set;
+use crate::service::clipboard::{
+    copy_clipboard_from_id, delete_clipboards_db, get_clipboard_db, get_clipboards_db,
+    init_clipboards, rename_clipboard_db, star_clipboard_db,
+};
+use crate::service::decrypt::decrypt_clipboard;
+use crate::service::settings::get_global_settings;
+use crate::tao::global::get_api_events;
+use crate::utils::clipboard_manager::ClipboardManagerExt;
+use axum::extract::{Path, Query, Request};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::io::clipboard::trim_clipboard_data;
+use common::types::enums::{ClipboardType, ListenEvent};
+use common::types::orm_query::{FullClipboardDbo, FullClipboardDto};
+use common::types::types::CommandError;
+use futures_util::stream::{self, Stream, StreamExt};
+use sea_orm::prelude::Uuid;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+
+/// Local HTTP/JSON API for scripts and editor plugins. Bound to 127.0.0.1 only and
+/// every request needs the `api_token` setting, as `Authorization: Bearer <token>`.
+/// The event stream takes `?token=<token>` too, `EventSource` can't set headers.
+pub struct ApiServer {
+    job_handle: Option<JoinHandle<()>>,
+    shutdown_tx: Option<watch::Sender<bool>>,
+    port: Option<u16>,
+}
+
+impl ApiServer {
+    pub fn new() -> Self {
+        Self {
+            job_handle: None,
+            shutdown_tx: None,
+            port: None,
+        }
+    }
+
+    pub async fn start(&mut self, port: u16) -> Result<(), std::io::Error> {
+        if self.port == Some(port) {
+            printlog!("api server already running on port {}", port);
+            return Ok(());
+        }
+
+        self.stop().await;
+
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port))).await?;
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        let handle = tokio::spawn(async move {
+            let mut signal = shutdown_rx.clone();
+            let result = axum::serve(listener, router(shutdown_rx))
+                .with_graceful_shutdown(async move {
+                    let _ = signal.changed().await;
+                })
+                .await;
+
+            if let Err(e) = result {
+                printlog!("api server failed: {:?}", e);
+            }
+        });
+
+        self.job_handle = Some(handle);
+        self.shutdown_tx = Some(shutdown_tx);
+        self.port = Some(port);
+        printlog!("api server listening on 127.0.0.1:{}", port);
+
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) {
+        // Closes open event streams too, so graceful shutdown doesn't wait on them
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            let _ = shutdown_tx.send(true);
+        }
+
+        if let Some(handle) = self.job_handle.take() {
+            let _ = handle.await;
+            printlog!("api server stopped");
+        }
+
+        self.port = None;
+    }
+}
+
+const EVENTS_ROUTE: &str = "/events";
+
+fn router(shutdown_rx: watch::Receiver<bool>) -> Router {
+    Router::new()
+        .route("/clipboards", get(list_clipboards).post(add_clipboard))
+        .route(
+            "/clipboards/{id}",
+            get(get_clipboard).delete(delete_clipboard),
+        )
+        .route("/clipboards/{id}/copy", post(copy_clipboard))
+        .route("/clipboards/{id}/star", put(star_clipboard))
+        .route("/clipboards/{id}/name", put(rename_clipboard))
+        .route(
+            EVENTS_ROUTE,
+            get(move || clipboard_events(shutdown_rx.clone())),
+        )
+        .layer(middleware::from_fn(authorize))
+}
+
+struct ApiError(StatusCode, String);
+
+type ApiResult<T> = Result<T, ApiError>;
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<DbErr> for ApiError {
+    fn from(err: DbErr) -> Self {
+        match err {
+            DbErr::RecordNotFound(msg) => ApiError(StatusCode::NOT_FOUND, msg),
+            err => ApiError(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+        }
+    }
+}
+
+impl From<CommandError> for ApiError {
+    fn from(err: CommandError) -> Self {
+        let CommandError::Error(msg) = err;
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, msg)
+    }
+}
+
+async fn authorize(request: Request, next: Next) -> ApiResult<Response> {
+    let token = get_global_settings().api_token;
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or_else(|| {
+            // Query strings end up in logs and history, only where there is no other way
+            if request.uri().path() != EVENTS_ROUTE {
+                return None;
+            }
+
+            request.uri().query().and_then(|query| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("token="))
+                    .and_then(|value| urlencoding::decode(value).ok())
+                    .map(|value| value.into_owned())
+            })
+        });
+
+    match provided {
+        Some(provided) if !token.is_empty() && tokens_match(&provided, &token) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid API token".to_string(),
+        )),
+    }
+}
+
+/// Constant time comparison, the response time must not leak the token prefix.
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Encrypted history can only be served once the password was entered.
+fn ensure_unlocked() -> ApiResult<()> {
+    if get_global_settings().encryption && !is_encryption_key_set() {
+        return Err(ApiError(
+            StatusCode::LOCKED,
+            "Clipboard history is locked, enter the password in Clippy first".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn decrypt(clipboard: FullClipboardDto) -> ApiResult<FullClipboardDto> {
+    if !clipboard.clipboard.encrypted {
+        return Ok(clipboard);
+    }
+
+    decrypt_clipboard(clipboard).map_err(|e| {
+        ApiError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to decrypt clipboard: {}", e),
+        )
+    })
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    cursor: Option<u64>,
+    search: Option<String>,
+    star: Option<bool>,
+    img: Option<bool>,
+}
+
+async fn list_clipboards(Query(query): Query<ListQuery>) -> ApiResult<Json<Vec<FullClipboardDto>>> {
+    ensure_unlocked()?;
+
+    let clipboards = get_clipboards_db(query.cursor, query.search, query.star, query.img)
+        .await?
+        .into_iter()
+        .map(decrypt)
+        .collect::<ApiResult<Vec<_>>>()?;
+
+    Ok(Json(trim_clipboard_data(clipboards)))
+}
+
+async fn get_clipboard(Path(id): Path<Uuid>) -> ApiResult<Json<FullClipboardDto>> {
+    ensure_unlocked()?;
+    Ok(Json(decrypt(get_clipboard_db(id).await?)?))
+}
+
+#[derive(Deserialize)]
+struct AddClipboard {
+    text: Option<String>,
+    html: Option<String>,
+    rtf: Option<String>,
+    /// Base64 encoded image bytes
+    image: Option<String>,
+}
+
+async fn add_clipboard(
+    Json(body): Json<AddClipboard>,
+) -> ApiResult<(StatusCode, Json<FullClipboardDto>)> {
+    ensure_unlocked()?;
+
+    let image = body
+        .image
+        .map(|image| STANDARD.decode(image))
+        .transpose()
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("Invalid image: {}", e)))?;
+
+    let mut manager = FullClipboardDbo::new();
+    manager.parse_model(body.text, body.html, body.rtf, image, None, None);
+
+    // parse_model drops empty content and everything over the size limits
+    let types = match &manager.clipboard_model.types {
+        sea_orm::ActiveValue::Set(types) => ClipboardType::from_json_value(types),
+        _ => None,
+    };
+    if types.is_none_or(|types| types.is_empty()) {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            "Nothing to insert, content is empty or exceeds the size limits".to_string(),
+        ));
+    }
+
+    // Same rules as a copy, a secret sent here must not end up synced
+    if !manager.apply_sensitive_rules() {
+        return Err(ApiError(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Dropped, content matches a sensitive rule".to_string(),
+        ));
+    }
+
+    let clipboard = manager.save().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(trim_clipboard_data(vec![clipboard]).remove(0)),
+    ))
+}
+
+#[derive(Deserialize)]
+struct CopyQuery {
+    r#type: Option<ClipboardType>,
+}
+
+async fn copy_clipboard(
+    Path(id): Path<Uuid>,
+    Query(query): Query<CopyQuery>,
+) -> ApiResult<Json<bool>> {
+    ensure_unlocked()?;
+
+    // Default to the primary type of the clipboard, like a click in the UI
+    let r#type = match query.r#type {
+        Some(r#type) => r#type,
+        None => {
+            let clipboard = get_clipboard_db(id).await?;
+            ClipboardType::from_json_value(&clipboard.clipboard.types)
+                .and_then(|types| types.into_iter().next())
+                .unwrap_or(ClipboardType::Text)
+        }
+    };
+
+    Ok(Json(copy_clipboard_from_id(id, r#type).await?))
+}
+
+#[derive(Deserialize)]
+struct StarBody {
+    star: bool,
+}
+
+async fn star_clipboard(Path(id): Path<Uuid>, Json(body): Json<StarBody>) -> ApiResult<Json<bool>> {
+    let starred = star_clipboard_db(id, body.star).await?;
+    init_clipboards();
+    Ok(Json(starred))
+}
+
+#[derive(Deserialize)]
+struct RenameBody {
+    name: Option<String>,
+}
+
+async fn rename_clipboard(
+    Path(id): Path<Uuid>,
+    Json(body): Json<RenameBody>,
+) -> ApiResult<Json<bool>> {
+    let renamed = rename_clipboard_db(id, body.name).await?;
+    init_clipboards();
+    Ok(Json(renamed))
+}
+
+async fn delete_clipboard(Path(id): Path<Uuid>) -> ApiResult<StatusCode> {
+    delete_clipboards_db(vec![id], Some(true)).await?;
+    init_clipboards();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Server-sent `new_clipboard` events with the same payload the UI receives.
+async fn clipboard_events(
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = stream::unfold(get_api_events().subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(clipboard) => return Some((clipboard, rx)),
+                // Skip what a slow client missed instead of closing its stream
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+    .filter_map(|clipboard| async move {
+        Event::default()
+            .event(ListenEvent::NewClipboard.to_string())
+            .json_data(clipboard)
+            .ok()
+            .map(Ok)
+    })
+    .take_until(async move {
+        let _ = shutdown_rx.changed().await;
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,154 @@
+use crate::prelude::*;
+use crate::service::cli::{execute_cli_command, CliCommand};
+use crate::service::settings::get_settings_db;
+use crate::tao::config::setup_config;
+use crate::tao::connection::init_headless_db;
+use common::constants::CLI_REPLY_TIMEOUT_SECS;
+use common::types::types::CommandError;
+use sea_orm::prelude::Uuid;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Argument of the probe process, followed by the path of the request file.
+pub const CLI_REQUEST_ARG: &str = "--cli-request";
+/// Exit code of the probe when it became the first instance, i.e. no app is running.
+pub const EXIT_NOT_RUNNING: i32 = 3;
+
+/// The CLI can't talk to the running app itself: the single-instance plugin only
+/// forwards argv and then exits the process. So the command is written to a request
+/// file and a probe process (this binary with `--cli-request <file>`) is started. If
+/// the app runs, the plugin hands the probe's argv to it, the app executes the request
+/// and writes `<file>.reply`. Otherwise the probe exits with `EXIT_NOT_RUNNING` and the
+/// command runs against the database directly.
+pub async fn run_cli_command(command: CliCommand) -> i32 {
+    let request_path = cli_dir().join(format!("clippy-cli-{}.json", Uuid::now_v7()));
+
+    let result = if forward_cli_command(&command, &request_path) {
+        wait_for_reply(&request_path).await
+    } else {
+        execute_headless(command).await
+    };
+
+    let _ = std::fs::remove_file(&request_path);
+    let _ = std::fs::remove_file(reply_path(&request_path));
+
+    match result {
+        Ok(output) => {
+            if !output.is_empty() {
+                println!("{}", output);
+            }
+            0
+        }
+        Err(CommandError::Error(e)) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Returns true when a running app accepted the request.
+fn forward_cli_command(command: &CliCommand, request_path: &Path) -> bool {
+    let request = serde_json::to_vec(command).expect("Failed to serialize cli command");
+    if write_private(request_path, &request).is_err() {
+        return false;
+    }
+
+    let Ok(exe) = std::env::current_exe() else {
+        return false;
+    };
+
+    // Anything but a clean exit (no display, crash, ...) means we're on our own
+    Command::new(exe)
+        .arg(CLI_REQUEST_ARG)
+        .arg(request_path)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Folder of the request and reply files. The per-user runtime dir where there is one,
+/// the temp dir is per-user on Windows and macOS already.
+fn cli_dir() -> PathBuf {
+    dirs::runtime_dir().unwrap_or_else(std::env::temp_dir)
+}
+
+/// Requests may carry an archive passphrase and replies clipboard content, keep both
+/// private to the user.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)?.write_all(data)
+}
+
+async fn wait_for_reply(request_path: &Path) -> Result<String, CommandError> {
+    let reply_path = reply_path(request_path);
+    let deadline = Instant::now() + Duration::from_secs(CLI_REPLY_TIMEOUT_SECS);
+
+    loop {
+        if let Ok(reply) = std::fs::read(&reply_path) {
+            let reply: Result<String, String> = serde_json::from_slice(&reply)?;
+            return reply.map_err(CommandError::Error);
+        }
+
+        if Instant::now() > deadline {
+            return Err(CommandError::Error(
+                "Clippy is running but did not answer".to_string(),
+            ));
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+async fn execute_headless(command: CliCommand) -> Result<String, CommandError> {
+    setup_config();
+    init_headless_db().await?;
+    get_settings_db().await?;
+
+    execute_cli_command(command).await
+}
+
+/// Called by the single-instance plugin of the running app with the argv of a probe.
+pub fn handle_cli_request(argv: &[String]) {
+    let Some(request_path) = argv
+        .iter()
+        .position(|arg| arg == CLI_REQUEST_ARG)
+        .and_then(|i| argv.get(i + 1))
+        .map(PathBuf::from)
+    else {
+        return;
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let result = match std::fs::read(&request_path) {
+            Ok(request) => match serde_json::from_slice::<CliCommand>(&request) {
+                Ok(command) => execute_cli_command(command).await,
+                Err(e) => Err(e.into()),
+            },
+            Err(e) => Err(e.into()),
+        };
+
+        let reply: Result<String, String> = result.map_err(|CommandError::Error(e)| e);
+        let reply_path = reply_path(&request_path);
+
+        // Written aside and renamed, the CLI must never read a half written reply
+        let partial_path = reply_path.with_extension("partial");
+        let written = serde_json::to_vec(&reply)
+            .map_err(std::io::Error::from)
+            .and_then(|reply| write_private(&partial_path, &reply))
+            .and_then(|_| std::fs::rename(&partial_path, &reply_path));
+
+        if let Err(e) = written {
+            printlog!("failed to write cli reply: {:?}", e);
+        }
+    });
+}
+
+fn reply_path(request_path: &Path) -> PathBuf {
+    request_path.with_extension("reply")
+}
@@ -1,20 +1,34 @@
 use crate::prelude::*;
 use crate::service::cipher::is_encryption_key_set;
 use crate::service::clipboard::{init_clipboards, new_clipboard_event, upsert_clipboard_dto};
+use crate::service::decrypt::read_encryption_key;
 use crate::service::encrypt::encrypt_clipboard;
+use crate::service::retention::{schedule_expiry, trigger_retention};
+use crate::service::search::reindex_clipboard_search;
 use crate::service::settings::get_global_settings;
 use crate::service::{
-    clipboard::{bump_clipboard_timestamp, get_recent_clipboards_db, insert_clipboard_dbo},
+    clipboard::{bump_clipboard_timestamp, find_duplicate_clipboard_db, insert_clipboard_dbo},
     window::calculate_thumbnail_dimensions,
 };
+use crate::tao::config::get_device_id;
 use crate::tao::connection::db;
 use crate::tao::global::{get_app, get_cache};
-use crate::utils::ocr;
+use crate::utils::{
+    clipboard_reader::{capture_decision, Capture, ClipboardReader},
+    foreground_window::ForegroundWindow,
+    ocr,
+    sync_manager::push_clipboard,
+};
 use base64::{engine::general_purpose::STANDARD, Engine};
-use chrono::DateTime;
-use common::constants::CACHE_KEY;
-use common::types::enums::{ClipboardTextType, ClipboardType};
-use common::types::orm_query::FullClipboardDbo;
+use chrono::{DateTime, TimeDelta, Utc};
+use common::constants::{CACHE_KEY, SENSITIVE_EXPIRE_AFTER};
+use common::io::clipboard::{dbo_payload_hash, html_source_url};
+use common::types::capture::AppRule;
+use common::types::enums::{
+    ClipboardHint, ClipboardTextType, ClipboardType, DuplicateAction, SensitiveAction,
+};
+use common::types::orm_query::{FullClipboardDbo, FullClipboardDto};
+use common::types::sensitive::SensitiveRules;
 use common::types::types::TextMatcher;
 use image::imageops;
 use regex::Regex;
@@ -40,7 +54,12 @@ use urlencoding::decode;
 
 pub trait ClipboardManagerExt {
     fn new() -> FullClipboardDbo;
-    fn upsert_clipboard() -> impl std::future::Future<Output = ()> + Send;
+    /// Stores what `reader` holds unless it is empty, dropped by a setting or a duplicate.
+    fn upsert_clipboard(
+        reader: impl ClipboardReader + Send,
+    ) -> impl std::future::Future<Output = ()> + Send;
+    /// Stores a parsed clipboard: insert, OCR, encryption, cache, `NewClipboard` event and sync push.
+    fn save(self) -> impl std::future::Future<Output = Result<FullClipboardDto, DbErr>> + Send;
     fn check_if_duplicate(&mut self) -> impl std::future::Future<Output = Option<Uuid>> + Send;
     fn parse_model(
         &mut self,
@@ -49,10 +68,15 @@ pub trait ClipboardManagerExt {
         rtf: Option<String>,
         image_data: Option<Vec<u8>>,
         files: Option<Vec<String>>,
+        source: Option<ForegroundWindow>,
     ) -> ();
     fn parse_image_model(&mut self, img_bytes: Vec<u8>);
     fn parse_file_models(&mut self, file_paths: Vec<String>) -> std::io::Result<()>;
     fn apply_text_matchers(&mut self) -> bool;
+    /// Marks a clipboard that looks like a secret as local only or expiring, returns false if it has to be dropped.
+    fn apply_sensitive_rules(&mut self) -> bool;
+    /// Keeps a clipboard a password manager marked local only, and expiring if set so.
+    fn apply_clipboard_hint(&mut self, hint: ClipboardHint);
 }
 
 impl ClipboardManagerExt for FullClipboardDbo {
@@ -67,7 +91,7 @@ impl ClipboardManagerExt for FullClipboardDbo {
         }
     }
 
-    async fn upsert_clipboard() {
+    async fn upsert_clipboard(reader: impl ClipboardReader + Send) {
         let settings = get_global_settings();
 
         // If clipboards are encypted but not saved before unlocking, return
@@ -76,17 +100,33 @@ impl ClipboardManagerExt for FullClipboardDbo {
             return;
         }
 
+        let app_rules = AppRule::from_json_value(&settings.app_rules);
+        let Capture::Store {
+            source,
+            hint,
+            no_sync,
+        } = capture_decision(&reader, &settings.concealed_action, &app_rules)
+        else {
+            return;
+        };
+
         let clipboard = get_app().state::<Clipboard>();
         let mut manager = Self::new();
 
         manager.parse_model(
-            clipboard.read_text().ok(),
-            clipboard.read_html().ok(),
-            clipboard.read_rtf().ok(),
-            clipboard.read_image_binary().ok(),
-            clipboard.read_files().ok(),
+            reader.text(),
+            reader.html(),
+            reader.rtf(),
+            reader.image(),
+            reader.files(),
+            Some(source),
         );
 
+        // The header `parse_model` looks for may be missing from the plugin's html
+        if let Some(source_url) = reader.source_url() {
+            manager.clipboard_model.source_url = Set(Some(source_url));
+        }
+
         // Add check for empty types
         if let sea_orm::ActiveValue::Set(types_json) = &manager.clipboard_model.types {
             if let Some(types) = ClipboardType::from_json_value(types_json) {
@@ -96,13 +136,30 @@ impl ClipboardManagerExt for FullClipboardDbo {
             }
         }
 
+        if no_sync {
+            manager.clipboard_model.no_sync = Set(true);
+        }
+
         let content_changed = manager.apply_text_matchers();
 
-        // Check for duplicates in recent clipboard history
+        if let Some(hint) = hint {
+            manager.apply_clipboard_hint(hint);
+        }
+
+        if !manager.apply_sensitive_rules() {
+            return;
+        }
+
+        // Check for duplicates in the whole clipboard history
         if let Some(existing_id) = manager.check_if_duplicate().await {
-            // Bump existing entry to the top instead of creating a duplicate
-            let _ = bump_clipboard_timestamp(existing_id).await;
-            init_clipboards();
+            if settings.duplicate_action == DuplicateAction::Bump.to_string() {
+                // Bump existing entry to the top instead of creating a duplicate
+                match bump_clipboard_timestamp(existing_id).await {
+                    Ok(id) => push_clipboard(id),
+                    Err(e) => log::error!("Failed to bump clipboard: {e:?}"),
+                }
+                init_clipboards();
+            }
             return;
         }
 
@@ -120,153 +177,91 @@ impl ClipboardManagerExt for FullClipboardDbo {
                 }
             }
 
-            // insert default not encrypted clipboard. Do NOT panic on failure: a
-            // transient DB error (e.g. pool acquire timeout under contention) must not
-            // crash the whole app - just log and skip storing this one clipboard.
-            let clipboard = match insert_clipboard_dbo(manager).await {
-                Ok(clipboard) => clipboard,
-                Err(e) => {
-                    log::error!("Failed to insert clipboard: {e:?}");
-                    return;
-                }
-            };
-
-            // Run OCR in background for image clipboards
-            if let Some(ref image) = clipboard.image {
-                let image_data = image.data.clone();
-                let clipboard_id = clipboard.clipboard.id;
-                let should_encrypt = settings.encryption && is_encryption_key_set();
-                tokio::spawn(async move {
-                    if let Some(ocr_text) = ocr::extract_text_from_image(&image_data) {
-                        let stored_text = if should_encrypt {
-                            use crate::service::encrypt::encrypt_data;
-                            STANDARD.encode(
-                                encrypt_data(ocr_text.as_bytes())
-                                    .expect("OCR text encryption failed"),
-                            )
-                        } else {
-                            ocr_text
-                        };
-                        let conn = db();
-                        let update = entity::clipboard_image::ActiveModel {
-                            ocr_text: Set(Some(stored_text)),
-                            ..Default::default()
-                        };
-                        let _ = entity::clipboard_image::Entity::update_many()
-                            .set(update)
-                            .filter(entity::clipboard_image::Column::ClipboardId.eq(clipboard_id))
-                            .exec(conn)
-                            .await;
-                    }
-                });
+            // Do NOT panic on failure: a transient DB error (e.g. pool acquire timeout
+            // under contention) must not crash the whole app - just log and skip
+            // storing this one clipboard.
+            if let Err(e) = manager.save().await {
+                log::error!("Failed to insert clipboard: {e:?}");
             }
+        }
+    }
 
-            // If encryption is enabled and key is set, encrypt clipboard before upsert.
-            // Log instead of panicking on a transient DB error so a failed encrypt-upsert
-            // can't crash the app.
-            if settings.encryption && is_encryption_key_set() {
-                if let Err(e) = upsert_clipboard_dto(encrypt_clipboard(clipboard.clone())).await {
-                    log::error!("Failed to upsert encrypted clipboard: {e:?}");
-                    return;
-                }
-            }
+    async fn save(self) -> Result<FullClipboardDto, DbErr> {
+        let settings = get_global_settings();
 
-            if let Some(mut cached) = get_cache().get(CACHE_KEY) {
-                if clipboard.image.is_none() {
-                    cached.insert(0, clipboard.clone());
-                    get_cache().insert(CACHE_KEY.to_string(), cached);
+        // insert default not encrypted clipboard
+        let clipboard = insert_clipboard_dbo(self).await?;
+
+        // Run OCR in background for image clipboards
+        if let Some(ref image) = clipboard.image {
+            let image_data = image.data.clone();
+            let clipboard_id = clipboard.clipboard.id;
+            let should_encrypt = settings.encryption && is_encryption_key_set();
+            tokio::spawn(async move {
+                if let Some(ocr_text) = ocr::extract_text_from_image(&image_data) {
+                    let stored_text = if should_encrypt {
+                        use crate::service::encrypt::encrypt_data;
+                        STANDARD.encode(
+                            encrypt_data(ocr_text.as_bytes()).expect("OCR text encryption failed"),
+                        )
+                    } else {
+                        ocr_text
+                    };
+                    let conn = db();
+                    let update = entity::clipboard_image::ActiveModel {
+                        ocr_text: Set(Some(stored_text)),
+                        ..Default::default()
+                    };
+                    let _ = entity::clipboard_image::Entity::update_many()
+                        .set(update)
+                        .filter(entity::clipboard_image::Column::ClipboardId.eq(clipboard_id))
+                        .exec(conn)
+                        .await;
+
+                    // Make the recognized text searchable (no-op for encrypted clips)
+                    if !should_encrypt {
+                        let _ = reindex_clipboard_search(clipboard_id).await;
+                    }
                 }
-            }
+            });
+        }
 
-            new_clipboard_event(clipboard);
+        // If encryption is enabled and key is set, encrypt clipboard before upsert.
+        if settings.encryption && is_encryption_key_set() {
+            upsert_clipboard_dto(encrypt_clipboard(clipboard.clone())).await?;
         }
-    }
 
-    async fn check_if_duplicate(&mut self) -> Option<Uuid> {
-        let recent = get_recent_clipboards_db(10).await.ok()?;
-        let curr_types = ClipboardType::from_json_value(&self.clipboard_model.types.as_ref())?;
-
-        for entry in recent {
-            let entry_types = match ClipboardType::from_json_value(&entry.clipboard.types) {
-                Some(t) if t == curr_types => t,
-                _ => continue,
-            };
-
-            let mut is_match = true;
-            for t in &entry_types {
-                match t {
-                    ClipboardType::Text => {
-                        if let (Some(last), sea_orm::ActiveValue::Set(curr)) =
-                            (&entry.text, &self.clipboard_text_model.data)
-                        {
-                            if curr != &last.data {
-                                is_match = false;
-                                break;
-                            }
-                        }
-                    }
-                    ClipboardType::Html => {
-                        if let (Some(last), sea_orm::ActiveValue::Set(curr)) =
-                            (&entry.html, &self.clipboard_html_model.data)
-                        {
-                            if curr != &last.data {
-                                is_match = false;
-                                break;
-                            }
-                        }
-                    }
-                    ClipboardType::Rtf => {
-                        if let (Some(last), sea_orm::ActiveValue::Set(curr)) =
-                            (&entry.rtf, &self.clipboard_rtf_model.data)
-                        {
-                            if curr != &last.data {
-                                is_match = false;
-                                break;
-                            }
-                        }
-                    }
-                    ClipboardType::Image => {
-                        match (&entry.image, &self.clipboard_image_model.data) {
-                            (Some(last_img), sea_orm::ActiveValue::Set(curr_data)) => {
-                                if curr_data != &last_img.data {
-                                    is_match = false;
-                                    break;
-                                }
-                            }
-                            _ => {
-                                is_match = false;
-                                break;
-                            }
-                        }
-                    }
-                    ClipboardType::File => {
-                        if self.clipboard_files_model.len() != entry.files.len() {
-                            is_match = false;
-                            break;
-                        }
-                        for (curr_file, last_file) in
-                            self.clipboard_files_model.iter().zip(&entry.files)
-                        {
-                            if let sea_orm::ActiveValue::Set(curr_data) = &curr_file.data {
-                                if curr_data != &last_file.data {
-                                    is_match = false;
-                                    break;
-                                }
-                            } else {
-                                is_match = false;
-                                break;
-                            }
-                        }
-                    }
-                }
+        if let Some(mut cached) = get_cache().get(CACHE_KEY) {
+            if clipboard.image.is_none() {
+                cached.insert(0, clipboard.clone());
+                get_cache().insert(CACHE_KEY.to_string(), cached);
             }
+        }
 
-            if is_match {
-                return Some(entry.clipboard.id);
-            }
+        new_clipboard_event(clipboard.clone());
+
+        // Upload right away when sync runs, the sync loop only catches up on the rest
+        push_clipboard(clipboard.clipboard.id);
+
+        trigger_retention();
+
+        if let Some(expires_at) = clipboard.clipboard.expires_at {
+            schedule_expiry(expires_at);
         }
 
-        None
+        Ok(clipboard)
+    }
+
+    async fn check_if_duplicate(&mut self) -> Option<Uuid> {
+        // Keyed like the encrypted history it is compared against
+        let key = get_global_settings()
+            .encryption
+            .then(|| read_encryption_key().ok())
+            .flatten();
+        let content_hash = dbo_payload_hash(self, key.as_ref().map(|key| key.as_slice()));
+        self.clipboard_model.content_hash = Set(Some(content_hash.clone()));
+
+        find_duplicate_clipboard_db(&content_hash).await.ok()?
     }
 
     fn parse_model(
@@ -276,9 +271,12 @@ impl ClipboardManagerExt for FullClipboardDbo {
         rtf: Option<String>,
         image: Option<Vec<u8>>,
         files: Option<Vec<String>>,
+        source: Option<ForegroundWindow>,
     ) {
         let settings = get_global_settings();
         let mut types = vec![];
+        let source = source.unwrap_or_default();
+        let source_url = html.as_deref().and_then(html_source_url);
 
         if let Some(text) =
             text.filter(|t| !t.is_empty() && t.len() <= settings.max_text_size as usize)
@@ -347,6 +345,11 @@ impl ClipboardManagerExt for FullClipboardDbo {
         self.clipboard_model = entity::clipboard::ActiveModel {
             id: Set(Uuid::now_v7()),
             types: Set(ClipboardType::to_json_value(&types)),
+            updated_at: Set(chrono::Utc::now().naive_utc()),
+            origin_device: Set(Some(get_device_id())),
+            source_app: Set(source.app),
+            source_window_title: Set(source.title),
+            source_url: Set(source_url),
             ..Default::default()
         };
     }
@@ -523,4 +526,54 @@ impl ClipboardManagerExt for FullClipboardDbo {
 
         content_changed
     }
+
+    fn apply_sensitive_rules(&mut self) -> bool {
+        let rules = SensitiveRules::from_json_value(&get_global_settings().sensitive_rules);
+
+        let texts: Vec<&str> = [
+            self.clipboard_text_model.data.try_as_ref(),
+            self.clipboard_html_model.data.try_as_ref(),
+            self.clipboard_rtf_model.data.try_as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+
+        let Some(rule) = rules.detect(&texts) else {
+            return true;
+        };
+
+        printlog!(
+            "sensitive clipboard ({:?}), {:?}",
+            rule.detector,
+            rule.action
+        );
+
+        match rule.action {
+            SensitiveAction::Drop => return false,
+            SensitiveAction::Expire => {
+                self.clipboard_model.expires_at = Set(Some(
+                    Utc::now().naive_utc() + TimeDelta::seconds(rule.expire_after() as i64),
+                ));
+            }
+            SensitiveAction::NoSync => {}
+        }
+
+        self.clipboard_model.no_sync = Set(true);
+        true
+    }
+
+    fn apply_clipboard_hint(&mut self, hint: ClipboardHint) {
+        let action = get_global_settings().concealed_action;
+        printlog!("{:?} clipboard, {}", hint, action);
+
+        if action == SensitiveAction::Expire.to_string() {
+            self.clipboard_model.expires_at = Set(Some(
+                Utc::now().naive_utc() + TimeDelta::seconds(SENSITIVE_EXPIRE_AFTER as i64),
+            ));
+        }
+
+        self.clipboard_model.no_sync = Set(true);
+    }
 }
@@ -0,0 +1,267 @@
+use crate::prelude::*;
+use crate::tao::global::get_app;
+use crate::utils::foreground_window::{foreground_window, ForegroundWindow};
+use clipboard_rs::{Clipboard as _, ClipboardContext};
+use common::constants::CF_HTML_FORMAT;
+use common::io::clipboard::html_source_url;
+use common::types::capture::AppRule;
+use common::types::enums::{AppAction, ClipboardHint, SensitiveAction};
+use tauri::Manager;
+use tauri_plugin_clipboard::Clipboard;
+
+/// What `upsert_clipboard` reads a new clipboard from, the OS clipboard or a stand-in.
+pub trait ClipboardReader {
+    /// Names of every format on the clipboard, marker formats without content included
+    fn formats(&self) -> Vec<String>;
+    fn text(&self) -> Option<String>;
+    fn html(&self) -> Option<String>;
+    fn rtf(&self) -> Option<String>;
+    fn image(&self) -> Option<Vec<u8>>;
+    fn files(&self) -> Option<Vec<String>>;
+    /// Page the HTML was copied from
+    fn source_url(&self) -> Option<String>;
+    /// Window the clipboard was copied in
+    fn source(&self) -> ForegroundWindow;
+}
+
+/// What happens to a new clipboard, decided from its formats and source before any of
+/// its content is read.
+#[derive(Debug, PartialEq)]
+pub enum Capture {
+    /// Not stored, a concealed secret set to be dropped or an app rule skips it
+    Skip,
+    Store {
+        source: ForegroundWindow,
+        /// Marker the copying app put on it, see `apply_clipboard_hint`
+        hint: Option<ClipboardHint>,
+        /// Kept on this device only by an app rule
+        no_sync: bool,
+    },
+}
+
+/// Applies `settings.concealed_action` and the app rules to the clipboard on `reader`.
+pub fn capture_decision(
+    reader: &impl ClipboardReader,
+    concealed_action: &str,
+    app_rules: &[AppRule],
+) -> Capture {
+    // Secrets a password manager marked are dropped before their content is even read
+    let hint = ClipboardHint::from_formats(&reader.formats());
+    if hint.is_some() && concealed_action == SensitiveAction::Drop.to_string() {
+        return Capture::Skip;
+    }
+
+    // Asked right away, before the user can switch to another window
+    let source = reader.source();
+    match AppRule::action_for(app_rules, source.app.as_deref()) {
+        AppAction::Skip => Capture::Skip,
+        action => Capture::Store {
+            source,
+            hint,
+            no_sync: action == AppAction::NoSync,
+        },
+    }
+}
+
+/// The OS clipboard, read through the clipboard plugin.
+pub struct SystemClipboard;
+
+impl ClipboardReader for SystemClipboard {
+    /// The plugin only knows the formats it reads, so this asks the OS directly. On Linux
+    /// that is X11, under Wayland through XWayland, which mirrors the mime types of the
+    /// Wayland selection. Without XWayland no formats are read and no hints detected,
+    /// the sensitive rules still apply to the content.
+    fn formats(&self) -> Vec<String> {
+        ClipboardContext::new()
+            .and_then(|context| context.available_formats())
+            .unwrap_or_default()
+    }
+
+    fn text(&self) -> Option<String> {
+        get_app().state::<Clipboard>().read_text().ok()
+    }
+
+    fn html(&self) -> Option<String> {
+        get_app().state::<Clipboard>().read_html().ok()
+    }
+
+    fn rtf(&self) -> Option<String> {
+        get_app().state::<Clipboard>().read_rtf().ok()
+    }
+
+    fn image(&self) -> Option<Vec<u8>> {
+        get_app().state::<Clipboard>().read_image_binary().ok()
+    }
+
+    fn files(&self) -> Option<Vec<String>> {
+        get_app().state::<Clipboard>().read_files().ok()
+    }
+
+    /// Read from the raw CF_HTML format, `read_html` may hand out the markup without its
+    /// header. Only Windows has the format.
+    fn source_url(&self) -> Option<String> {
+        let raw = ClipboardContext::new()
+            .and_then(|context| context.get_buffer(CF_HTML_FORMAT))
+            .ok()?;
+        html_source_url(&String::from_utf8_lossy(&raw))
+    }
+
+    fn source(&self) -> ForegroundWindow {
+        foreground_window()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MockReader {
+        formats: Vec<String>,
+        app: Option<String>,
+    }
+
+    impl ClipboardReader for MockReader {
+        fn formats(&self) -> Vec<String> {
+            self.formats.clone()
+        }
+
+        fn text(&self) -> Option<String> {
+            Some("secret".to_owned())
+        }
+
+        fn html(&self) -> Option<String> {
+            None
+        }
+
+        fn rtf(&self) -> Option<String> {
+            None
+        }
+
+        fn image(&self) -> Option<Vec<u8>> {
+            None
+        }
+
+        fn files(&self) -> Option<Vec<String>> {
+            None
+        }
+
+        fn source_url(&self) -> Option<String> {
+            None
+        }
+
+        fn source(&self) -> ForegroundWindow {
+            ForegroundWindow {
+                app: self.app.clone(),
+                title: None,
+            }
+        }
+    }
+
+    fn reader(formats: &[&str], app: Option<&str>) -> MockReader {
+        MockReader {
+            formats: formats.iter().copied().map(String::from).collect(),
+            app: app.map(str::to_owned),
+        }
+    }
+
+    fn rule(app: &str, action: AppAction) -> AppRule {
+        AppRule {
+            app: app.to_owned(),
+            action,
+            enabled: true,
+        }
+    }
+
+    fn store(app: Option<&str>, hint: Option<ClipboardHint>, no_sync: bool) -> Capture {
+        Capture::Store {
+            source: reader(&[], app).source(),
+            hint,
+            no_sync,
+        }
+    }
+
+    const DROP: &str = "drop";
+    const EXPIRE: &str = "expire";
+
+    #[test]
+    fn stores_plain_clipboards() {
+        let reader = reader(&["text/plain"], Some("firefox"));
+        assert_eq!(
+            capture_decision(&reader, DROP, &[]),
+            store(Some("firefox"), None, false)
+        );
+    }
+
+    #[test]
+    fn concealed_clipboards_follow_the_setting() {
+        let reader = reader(&["text/plain", "x-kde-passwordManagerHint"], None);
+
+        assert_eq!(capture_decision(&reader, DROP, &[]), Capture::Skip);
+        assert_eq!(
+            capture_decision(&reader, EXPIRE, &[]),
+            store(None, Some(ClipboardHint::Concealed), false)
+        );
+    }
+
+    #[test]
+    fn transient_clipboards_follow_the_setting() {
+        let reader = reader(&["org.nspasteboard.TransientType"], Some("1password"));
+
+        assert_eq!(capture_decision(&reader, DROP, &[]), Capture::Skip);
+        assert_eq!(
+            capture_decision(&reader, "no_sync", &[]),
+            store(Some("1password"), Some(ClipboardHint::Transient), false)
+        );
+    }
+
+    #[test]
+    fn app_rules_skip_or_keep_local() {
+        let rules = [
+            rule("KeePassXC", AppAction::Skip),
+            rule("slack.exe", AppAction::NoSync),
+        ];
+
+        assert_eq!(
+            capture_decision(&reader(&[], Some("keepassxc")), DROP, &rules),
+            Capture::Skip
+        );
+        assert_eq!(
+            capture_decision(&reader(&[], Some("Slack")), DROP, &rules),
+            store(Some("Slack"), None, true)
+        );
+        assert_eq!(
+            capture_decision(&reader(&[], None), DROP, &rules),
+            store(None, None, false)
+        );
+    }
+
+    #[test]
+    fn allow_rules_skip_every_other_app() {
+        let rules = [rule("code", AppAction::Allow)];
+
+        assert_eq!(
+            capture_decision(&reader(&[], Some("code")), DROP, &rules),
+            store(Some("code"), None, false)
+        );
+        assert_eq!(
+            capture_decision(&reader(&[], Some("firefox")), DROP, &rules),
+            Capture::Skip
+        );
+        assert_eq!(
+            capture_decision(&reader(&[], None), DROP, &rules),
+            Capture::Skip
+        );
+    }
+
+    #[test]
+    fn concealed_hint_is_kept_for_app_rules() {
+        let rules = [rule("slack", AppAction::NoSync)];
+        let reader = reader(&["org.nspasteboard.ConcealedType"], Some("slack"));
+
+        assert_eq!(
+            capture_decision(&reader, EXPIRE, &rules),
+            store(Some("slack"), Some(ClipboardHint::Concealed), true)
+        );
+    }
+}
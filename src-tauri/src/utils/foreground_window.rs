@@ -0,0 +1,162 @@
+/// The window in the foreground, the one a new clipboard was just copied in. Fields are
+/// `None` where they can't be detected, like for native Wayland windows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ForegroundWindow {
+    /// Process or app name
+    pub app: Option<String>,
+    /// Window title, on macOS only with the screen recording permission
+    pub title: Option<String>,
+}
+
+pub fn foreground_window() -> ForegroundWindow {
+    #[cfg(target_os = "windows")]
+    return foreground_window_windows();
+
+    #[cfg(target_os = "macos")]
+    return foreground_window_macos();
+
+    #[cfg(target_os = "linux")]
+    return foreground_window_linux();
+}
+
+#[cfg(target_os = "windows")]
+fn foreground_window_windows() -> ForegroundWindow {
+    use crate::utils::window_ffi::windows::*;
+    use std::path::Path;
+
+    unsafe fn app(foreground: HWND) -> Option<String> {
+        let mut process_id = 0;
+        GetWindowThreadProcessId(foreground, &mut process_id);
+        if process_id == 0 {
+            return None;
+        }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+        if process.is_null() {
+            return None;
+        }
+
+        let mut buffer = [0u16; 1024];
+        let mut size = buffer.len() as u32;
+        let found = QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut size);
+        CloseHandle(process);
+        if found == 0 {
+            return None;
+        }
+
+        // The executable name without `.exe`
+        let path = String::from_utf16_lossy(&buffer[..size as usize]);
+        Path::new(&path)
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+
+    unsafe fn title(foreground: HWND) -> Option<String> {
+        let mut buffer = [0u16; 512];
+        let length = GetWindowTextW(foreground, buffer.as_mut_ptr(), buffer.len() as i32);
+        (length > 0).then(|| String::from_utf16_lossy(&buffer[..length as usize]))
+    }
+
+    unsafe {
+        let foreground = GetForegroundWindow();
+        if foreground.is_null() {
+            return ForegroundWindow::default();
+        }
+
+        ForegroundWindow {
+            app: app(foreground),
+            title: title(foreground),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn foreground_window_macos() -> ForegroundWindow {
+    use crate::utils::window_ffi::macos::*;
+    use std::ffi::{c_char, CStr};
+    use std::ptr::null;
+
+    unsafe {
+        // On screen windows without desktop elements, front to back
+        let window_list = CGWindowListCopyWindowInfo(ON_SCREEN_ONLY | EXCLUDE_DESKTOP_ELEMENTS, 0);
+        if window_list.is_null() {
+            return ForegroundWindow::default();
+        }
+
+        let layer_key = CFStringCreateWithCString(null(), c"kCGWindowLayer".as_ptr(), UTF8);
+        let owner_key = CFStringCreateWithCString(null(), c"kCGWindowOwnerName".as_ptr(), UTF8);
+        let name_key = CFStringCreateWithCString(null(), c"kCGWindowName".as_ptr(), UTF8);
+
+        let string_value = |dict: CFTypeRef, key: CFTypeRef| {
+            let value = CFDictionaryGetValue(dict, key);
+            let mut buffer = [0 as c_char; 512];
+            (!value.is_null()
+                && CFStringGetCString(value, buffer.as_mut_ptr(), buffer.len() as isize, UTF8) != 0)
+                .then(|| {
+                    CStr::from_ptr(buffer.as_ptr())
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .filter(|value| !value.is_empty())
+        };
+
+        let mut window = ForegroundWindow::default();
+        for i in 0..CFArrayGetCount(window_list) {
+            let window_info = CFArrayGetValueAtIndex(window_list, i);
+            if window_info.is_null() {
+                continue;
+            }
+
+            // Menu bar, dock and overlays sit above layer 0, app windows on it
+            if window_layer(window_info, layer_key) != Some(0) {
+                continue;
+            }
+
+            window = ForegroundWindow {
+                app: string_value(window_info, owner_key),
+                title: string_value(window_info, name_key),
+            };
+            break;
+        }
+
+        CFRelease(name_key);
+        CFRelease(owner_key);
+        CFRelease(layer_key);
+        CFRelease(window_list);
+        window
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn foreground_window_linux() -> ForegroundWindow {
+    use crate::utils::window_ffi::linux::*;
+    use std::ptr::null_mut;
+
+    unsafe {
+        let display = XOpenDisplay(null_mut());
+        if display.is_null() {
+            return ForegroundWindow::default();
+        }
+
+        let Some(active) = active_window(display) else {
+            XCloseDisplay(display);
+            return ForegroundWindow::default();
+        };
+
+        // WM_CLASS holds the instance and then the class name, both NUL terminated
+        let app = window_property(display, active, c"WM_CLASS").and_then(|class| {
+            class
+                .split(|byte| *byte == 0)
+                .rfind(|part| !part.is_empty())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+        });
+
+        // The UTF-8 EWMH title, WM_NAME for windows that don't set it
+        let title = window_property(display, active, c"_NET_WM_NAME")
+            .or_else(|| window_property(display, active, c"WM_NAME"))
+            .map(|title| String::from_utf8_lossy(&title).into_owned());
+
+        XCloseDisplay(display);
+        ForegroundWindow { app, title }
+    }
+}
@@ -0,0 +1,569 @@
+use crate::prelude::*;
+use crate::utils::mdns;
+use crate::utils::providers::lan::{
+    agree, encode_lan_key, ephemeral_key, pairing_key, random_bytes, session_key, LanChannel,
+    LanRequest, LanResponse, LanRole, LAN_MODE_PAIR, LAN_MODE_SYNC, LAN_NONCE_LEN, LAN_SALT_LEN,
+    LAN_TIMEOUT,
+};
+use common::types::sync::SyncProvider;
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Component, Path},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Semaphore,
+    task::JoinHandle,
+};
+
+/// Connections served at once, further ones are closed right away
+const LAN_MAX_CONNECTIONS: usize = 16;
+const PAIRING_CODE_TTL: Duration = Duration::from_secs(5 * 60);
+const PAIRING_MAX_ATTEMPTS: u8 = 5;
+/// No 0/O or 1/I, codes are read off one screen and typed on another
+const PAIRING_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+struct PairingCode {
+    code: String,
+    expires_at: Instant,
+    attempts: u8,
+}
+
+struct LanContext {
+    key: [u8; 32],
+    store: Arc<dyn SyncProvider>,
+    pairing: Mutex<Option<PairingCode>>,
+}
+
+/// Host side of LAN sync: serves `store` to paired devices over TCP on every
+/// interface, hands out the LAN key while a pairing code is active and announces
+/// itself via mDNS.
+pub struct LanServer {
+    job_handle: Option<JoinHandle<()>>,
+    mdns_handle: Option<JoinHandle<()>>,
+    context: Option<Arc<LanContext>>,
+    port: Option<u16>,
+}
+
+impl LanServer {
+    pub fn new() -> Self {
+        Self {
+            job_handle: None,
+            mdns_handle: None,
+            context: None,
+            port: None,
+        }
+    }
+
+    pub async fn start(
+        &mut self,
+        port: u16,
+        key: [u8; 32],
+        store: Arc<dyn SyncProvider>,
+        instance: String,
+    ) -> Result<(), io::Error> {
+        if self.port == Some(port) {
+            printlog!("lan server already running on port {}", port);
+            return Ok(());
+        }
+
+        self.stop().await;
+
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))).await?;
+        let port = listener.local_addr()?.port();
+        let context = Arc::new(LanContext {
+            key,
+            store,
+            pairing: Mutex::new(None),
+        });
+
+        let server_context = context.clone();
+        let connections = Arc::new(Semaphore::new(LAN_MAX_CONNECTIONS));
+        self.job_handle = Some(tokio::spawn(async move {
+            loop {
+                let (stream, address) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        printlog!("lan server accept failed: {:?}", e);
+                        continue;
+                    }
+                };
+
+                let Ok(permit) = connections.clone().try_acquire_owned() else {
+                    printlog!("lan server busy, closing connection from {}", address);
+                    continue;
+                };
+
+                let context = server_context.clone();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    let result =
+                        tokio::time::timeout(LAN_TIMEOUT, handle_connection(stream, &context))
+                            .await;
+                    match result {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => printlog!("lan connection from {} failed: {}", address, e),
+                        Err(_) => printlog!("lan connection from {} timed out", address),
+                    }
+                });
+            }
+        }));
+
+        self.mdns_handle = Some(tokio::spawn(async move {
+            if let Err(e) = mdns::run_responder(instance, port).await {
+                printlog!("mdns responder failed: {:?}", e);
+            }
+        }));
+
+        self.context = Some(context);
+        self.port = Some(port);
+        printlog!("lan server listening on 0.0.0.0:{}", port);
+
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) {
+        for handle in [self.job_handle.take(), self.mdns_handle.take()]
+            .into_iter()
+            .flatten()
+        {
+            handle.abort();
+            let _ = handle.await;
+        }
+
+        if self.port.take().is_some() {
+            printlog!("lan server stopped");
+        }
+        self.context = None;
+    }
+
+    /// New single-use pairing code, valid for a few minutes. Replaces an earlier one.
+    pub fn start_pairing(&self) -> Option<String> {
+        let context = self.context.as_ref()?;
+
+        let code: String = random_bytes::<8>()
+            .iter()
+            .map(|byte| PAIRING_ALPHABET[*byte as usize % PAIRING_ALPHABET.len()] as char)
+            .collect();
+        let code = format!("{}-{}", &code[..4], &code[4..]);
+
+        *context.pairing.lock().expect("Failed to lock pairing code") = Some(PairingCode {
+            code: code.clone(),
+            expires_at: Instant::now() + PAIRING_CODE_TTL,
+            attempts: 0,
+        });
+
+        Some(code)
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, context: &LanContext) -> io::Result<()> {
+    let mut mode = [0u8; 1];
+    stream.read_exact(&mut mode).await?;
+
+    match mode[0] {
+        LAN_MODE_SYNC => serve_sync(stream, context).await,
+        LAN_MODE_PAIR => serve_pairing(stream, context).await,
+        _ => Ok(()),
+    }
+}
+
+async fn serve_sync(mut stream: TcpStream, context: &LanContext) -> io::Result<()> {
+    let mut client_nonce = [0u8; LAN_NONCE_LEN];
+    stream.read_exact(&mut client_nonce).await?;
+
+    let host_nonce = random_bytes::<LAN_NONCE_LEN>();
+    stream.write_all(&host_nonce).await?;
+
+    let mut channel = LanChannel::new(
+        stream,
+        session_key(&context.key, &client_nonce, &host_nonce),
+        LanRole::Host,
+    );
+
+    // Fails right here for devices with another key
+    let request: LanRequest = channel.receive().await?;
+    let response = handle_request(request, context.store.as_ref()).await;
+
+    channel.send(&response).await
+}
+
+async fn handle_request(request: LanRequest, store: &dyn SyncProvider) -> LanResponse {
+    // Names from the peer are joined onto the store path, refused before the store sees them
+    let name = match &request {
+        LanRequest::Download(name)
+        | LanRequest::ReadDocument(name)
+        | LanRequest::WriteDocument(name, _) => Some(name),
+        LanRequest::Update(_, clippy)
+        | LanRequest::MarkForDeletion(clippy)
+        | LanRequest::Delete(clippy) => Some(&clippy.provider_id),
+        _ => None,
+    };
+    if let Some(name) = name.filter(|name| !is_store_name(name)) {
+        return LanResponse::Error(format!("Invalid name {:?}", name));
+    }
+
+    let result = match request {
+        LanRequest::Ping => Ok(LanResponse::Done),
+        LanRequest::FetchAll => store
+            .fetch_all_clipboards()
+            .await
+            .map(LanResponse::Clipboards),
+        LanRequest::Download(id) => store
+            .download_by_id(&id)
+            .await
+            .map(|clipboard| LanResponse::Clipboard(Box::new(clipboard))),
+        LanRequest::Upload(clipboard) => store
+            .upload_clipboard(&clipboard)
+            .await
+            .map(LanResponse::Uploaded),
+        LanRequest::Update(clipboard, clippy) => store
+            .update_clipboard(&clipboard, &clippy)
+            .await
+            .map(|_| LanResponse::Done),
+        LanRequest::MarkForDeletion(clippy) => {
+            store.mark_for_deletion(&clippy).await;
+            Ok(LanResponse::Done)
+        }
+        LanRequest::Delete(clippy) => {
+            store.delete_clipboard(&clippy).await;
+            Ok(LanResponse::Done)
+        }
+        LanRequest::GetSettings => store.get_settings().await.map(LanResponse::Settings),
+        LanRequest::UpsertSettings(settings) => store
+            .upsert_settings(&settings)
+            .await
+            .map(|_| LanResponse::Done),
+        LanRequest::ReadDocument(name) => {
+            store.read_document(&name).await.map(LanResponse::Document)
+        }
+        LanRequest::WriteDocument(name, content) => store
+            .write_document(&name, content)
+            .await
+            .map(|_| LanResponse::Done),
+    };
+
+    result.unwrap_or_else(|e| LanResponse::Error(e.to_string()))
+}
+
+/// A single plain file name: no separators, no `..`, not absolute and not one of the
+/// store's hidden temp files.
+fn is_store_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_)))
+        && components.next().is_none()
+        && !name.contains(['/', '\\', ':'])
+        && !name.starts_with('.')
+}
+
+async fn serve_pairing(mut stream: TcpStream, context: &LanContext) -> io::Result<()> {
+    let (private_key, host_public) = ephemeral_key()?;
+    let mut client_public = vec![0u8; host_public.len()];
+    stream.read_exact(&mut client_public).await?;
+
+    let Some(code) = active_pairing_code(context) else {
+        stream.write_all(&[1]).await?;
+        return Ok(());
+    };
+
+    let salt = random_bytes::<LAN_SALT_LEN>();
+    stream.write_all(&[0]).await?;
+    stream.write_all(&host_public).await?;
+    stream.write_all(&salt).await?;
+
+    let shared_secret = agree(private_key, &client_public)?;
+    let pairing_code = code.clone();
+    let key = tokio::task::spawn_blocking(move || {
+        pairing_key(
+            &shared_secret,
+            &pairing_code,
+            &salt,
+            &client_public,
+            &host_public,
+        )
+    })
+    .await
+    .map_err(|e| io::Error::other(e.to_string()))?;
+
+    let mut channel = LanChannel::new(stream, key, LanRole::Host);
+    let device = match channel.receive::<String>().await {
+        Ok(device) => device,
+        Err(e) => {
+            count_failed_pairing(context);
+            return Err(e);
+        }
+    };
+
+    // Single use, whoever paired first wins
+    let paired = context
+        .pairing
+        .lock()
+        .expect("Failed to lock pairing code")
+        .take_if(|pairing| pairing.code == code)
+        .is_some();
+    if !paired {
+        return Ok(());
+    }
+
+    channel.send(&encode_lan_key(&context.key)).await?;
+    printlog!("lan paired with {}", device);
+
+    Ok(())
+}
+
+fn active_pairing_code(context: &LanContext) -> Option<String> {
+    let mut pairing = context.pairing.lock().expect("Failed to lock pairing code");
+    if pairing
+        .as_ref()
+        .is_some_and(|pairing| pairing.expires_at < Instant::now())
+    {
+        *pairing = None;
+    }
+
+    pairing.as_ref().map(|pairing| pairing.code.clone())
+}
+
+/// A handful of wrong guesses burn the code, a new one has to be started.
+fn count_failed_pairing(context: &LanContext) {
+    let mut pairing = context.pairing.lock().expect("Failed to lock pairing code");
+    if let Some(active) = pairing.as_mut() {
+        active.attempts += 1;
+        if active.attempts >= PAIRING_MAX_ATTEMPTS {
+            printlog!(
+                "lan pairing code revoked after {} failed attempts",
+                active.attempts
+            );
+            *pairing = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::providers::lan::{pair_with_host, LanProviderImpl};
+    use crate::utils::providers::local_folder::LocalFolderProviderImpl;
+    use crate::utils::providers::tests::text_clipboard;
+    use sea_orm::prelude::Uuid;
+
+    const CODE: &str = "ABCD-EFGH";
+
+    /// Host serving a store in the temp directory on a loopback port, pairing with `CODE`.
+    struct TestHost {
+        address: String,
+        context: Arc<LanContext>,
+        path: std::path::PathBuf,
+        handle: JoinHandle<()>,
+    }
+
+    impl TestHost {
+        async fn start() -> Self {
+            let path = std::env::temp_dir().join(format!("clippy-lan-test-{}", Uuid::now_v7()));
+            std::fs::create_dir_all(&path).unwrap();
+
+            let context = Arc::new(LanContext {
+                key: random_bytes::<32>(),
+                store: Arc::new(LocalFolderProviderImpl::new(path.clone())),
+                pairing: Mutex::new(Some(PairingCode {
+                    code: CODE.to_string(),
+                    expires_at: Instant::now() + PAIRING_CODE_TTL,
+                    attempts: 0,
+                })),
+            });
+
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let server_context = context.clone();
+            let handle = tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let context = server_context.clone();
+                    tokio::spawn(async move { handle_connection(stream, &context).await.ok() });
+                }
+            });
+
+            Self {
+                address,
+                context,
+                path,
+                handle,
+            }
+        }
+    }
+
+    impl Drop for TestHost {
+        fn drop(&mut self) {
+            self.handle.abort();
+            std::fs::remove_dir_all(&self.path).ok();
+        }
+    }
+
+    async fn connected_streams() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (host, _) = listener.accept().await.unwrap();
+        (client, host)
+    }
+
+    /// Raw bytes of the first frame a client sends with `key`.
+    async fn client_frame(key: [u8; 32]) -> Vec<u8> {
+        let (client, mut host) = connected_streams().await;
+        let mut channel = LanChannel::new(client, key, LanRole::Client);
+        channel.send(&"hello".to_string()).await.unwrap();
+        drop(channel);
+
+        let mut frame = Vec::new();
+        host.read_to_end(&mut frame).await.unwrap();
+        frame
+    }
+
+    #[tokio::test]
+    async fn pairs_with_the_right_code() {
+        let host = TestHost::start().await;
+
+        let key = pair_with_host(&host.address, "abcd efgh", "laptop")
+            .await
+            .unwrap();
+        assert_eq!(key, host.context.key);
+
+        // Single use
+        let error = pair_with_host(&host.address, CODE, "desktop")
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert!(active_pairing_code(&host.context).is_none());
+    }
+
+    #[tokio::test]
+    async fn refuses_a_wrong_code() {
+        let host = TestHost::start().await;
+
+        let error = pair_with_host(&host.address, "ABCD-EFGX", "laptop")
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+
+        let pairing = host.context.pairing.lock().unwrap();
+        assert_eq!(pairing.as_ref().map(|pairing| pairing.attempts), Some(1));
+    }
+
+    #[tokio::test]
+    async fn revokes_the_code_after_too_many_attempts() {
+        let host = TestHost::start().await;
+
+        for _ in 0..PAIRING_MAX_ATTEMPTS {
+            assert!(pair_with_host(&host.address, "ZZZZ-ZZZZ", "laptop")
+                .await
+                .is_err());
+        }
+
+        assert!(active_pairing_code(&host.context).is_none());
+        assert!(pair_with_host(&host.address, CODE, "laptop").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn syncs_with_the_host_store() {
+        let host = TestHost::start().await;
+        let provider = LanProviderImpl::new(&host.address, host.context.key);
+        assert!(provider.is_authenticated().await);
+
+        let mut clipboard = text_clipboard("hello");
+        let uploaded = provider.upload_clipboard(&clipboard).await.unwrap();
+        clipboard.clipboard.star = true;
+        provider
+            .update_clipboard(&clipboard, &uploaded)
+            .await
+            .unwrap();
+
+        let remote = host.context.store.fetch_all_clipboards().await.unwrap();
+        assert_eq!(remote.len(), 1);
+        assert!(remote[0].star);
+        let downloaded = provider
+            .download_by_id(&remote[0].provider_id)
+            .await
+            .unwrap();
+        assert_eq!(downloaded.clipboard, clipboard.clipboard);
+        assert_eq!(downloaded.text, clipboard.text);
+
+        provider
+            .write_document("tombstones.json", "{}".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            provider.read_document("tombstones.json").await.unwrap(),
+            Some("{}".to_string())
+        );
+        assert!(provider.read_document("../tombstones.json").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_a_device_with_another_key() {
+        let host = TestHost::start().await;
+        let provider = LanProviderImpl::new(&host.address, random_bytes::<32>());
+
+        assert!(!provider.is_authenticated().await);
+    }
+
+    #[tokio::test]
+    async fn channel_rejects_replayed_frames() {
+        let key = random_bytes::<32>();
+        let frame = client_frame(key).await;
+
+        let (mut client, host) = connected_streams().await;
+        let mut channel = LanChannel::new(host, key, LanRole::Host);
+        client.write_all(&frame).await.unwrap();
+        client.write_all(&frame).await.unwrap();
+
+        assert_eq!(channel.receive::<String>().await.unwrap(), "hello");
+        let error = channel.receive::<String>().await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn channel_rejects_reflected_frames() {
+        let key = random_bytes::<32>();
+        let frame = client_frame(key).await;
+
+        let (client, mut host) = connected_streams().await;
+        let mut channel = LanChannel::new(client, key, LanRole::Client);
+        host.write_all(&frame).await.unwrap();
+
+        let error = channel.receive::<String>().await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn accepts_plain_store_names() {
+        for name in [
+            "clippy_0190f7a4-1c2b-7000-8000-000000000000_false_false_20250101000000.json",
+            "settings.json",
+            "tombstones.json",
+            "devices.json",
+        ] {
+            assert!(is_store_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn refuses_names_outside_the_store() {
+        for name in [
+            "",
+            ".",
+            "..",
+            "../../.ssh/id_rsa",
+            "sub/settings.json",
+            "..\\settings.json",
+            "/etc/passwd",
+            "C:\\Windows\\win.ini",
+            "C:settings.json",
+            ".settings.json.partial",
+        ] {
+            assert!(!is_store_name(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::prelude::*;
+use common::types::sync::LanPeer;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    collections::HashMap,
+    io,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+use tokio::{net::UdpSocket, time::Instant};
+
+/// DNS-SD service type the LAN sync hosts announce.
+pub const LAN_SERVICE_TYPE: &str = "_clippy-sync._tcp.local";
+
+const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set on a question: "answer me directly", on a record: "flush your cache"
+const CLASS_TOP_BIT: u16 = 0x8000;
+const RECORD_TTL: u32 = 120;
+
+/// Minimal mDNS responder for `_clippy-sync._tcp.local`, just enough for other
+/// clippy instances to find this one. Answers PTR queries with PTR, SRV and TXT records;
+/// there is no A record, browsers take the address from the answering packet.
+pub async fn run_responder(instance: String, port: u16) -> io::Result<()> {
+    let socket = bind_multicast()?;
+    let instance_name = format!("{}.{}", dns_label(&instance), LAN_SERVICE_TYPE);
+    let mut buffer = [0u8; 9000];
+
+    printlog!("mdns announcing {} on port {}", instance_name, port);
+
+    loop {
+        let (len, source) = socket.recv_from(&mut buffer).await?;
+        let Some(query_id) = matching_query(&buffer[..len]) else {
+            continue;
+        };
+
+        // One-shot queries from a random port get a unicast answer echoing their id
+        // (RFC 6762 6.7), proper mDNS queriers the multicast one.
+        let (id, target) = if source.port() == MDNS_PORT {
+            (0, SocketAddr::V4(SocketAddrV4::new(MDNS_ADDR, MDNS_PORT)))
+        } else {
+            (query_id, source)
+        };
+
+        let response = build_response(id, &instance_name, &instance, port);
+        if let Err(e) = socket.send_to(&response, target).await {
+            printlog!("mdns failed to answer {}: {}", source, e);
+        }
+    }
+}
+
+/// Asks the network for LAN sync hosts and collects the answers for `timeout`.
+pub async fn browse(timeout: Duration) -> io::Result<Vec<LanPeer>> {
+    let socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))).await?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_multicast_ttl_v4(255)?;
+
+    let query_id = (std::process::id() & 0xffff) as u16 | 1;
+    socket
+        .send_to(
+            &build_query(query_id),
+            SocketAddr::V4(SocketAddrV4::new(MDNS_ADDR, MDNS_PORT)),
+        )
+        .await?;
+
+    let deadline = Instant::now() + timeout;
+    let mut peers: HashMap<String, LanPeer> = HashMap::new();
+    let mut buffer = [0u8; 9000];
+
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await
+    {
+        let (len, source) = received?;
+        for (name, port) in parse_services(&buffer[..len]) {
+            let address = SocketAddr::new(source.ip(), port).to_string();
+            peers.insert(address.clone(), LanPeer { name, address });
+        }
+    }
+
+    Ok(peers.into_values().collect())
+}
+
+/// Port 5353 is shared with the system responder (avahi, mDNSResponder, ...) and other
+/// clippy instances on the same machine, hence the reuse flags.
+fn bind_multicast() -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())?;
+    socket.join_multicast_v4(&MDNS_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_multicast_ttl_v4(255)?;
+
+    UdpSocket::from_std(socket.into())
+}
+
+/// Id of a query asking for the service type, `None` for anything else.
+fn matching_query(packet: &[u8]) -> Option<u16> {
+    let id = read_u16(packet, 0)?;
+    let flags = read_u16(packet, 2)?;
+    if flags & 0x8000 != 0 {
+        return None;
+    }
+
+    let questions = read_u16(packet, 4)?;
+    let mut offset = 12;
+    for _ in 0..questions {
+        let (name, next) = read_name(packet, offset)?;
+        let record_type = read_u16(packet, next)?;
+        offset = next + 4;
+
+        if name.eq_ignore_ascii_case(LAN_SERVICE_TYPE)
+            && (record_type == TYPE_PTR || record_type == TYPE_ANY)
+        {
+            return Some(id);
+        }
+    }
+
+    None
+}
+
+fn build_query(id: u16) -> Vec<u8> {
+    let mut packet = header(id, 0, 1, 0, 0);
+    write_name(&mut packet, LAN_SERVICE_TYPE);
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&(CLASS_IN | CLASS_TOP_BIT).to_be_bytes());
+    packet
+}
+
+fn build_response(id: u16, instance_name: &str, instance: &str, port: u16) -> Vec<u8> {
+    let mut packet = header(id, 0x8400, 0, 1, 2);
+
+    // PTR service -> instance
+    let mut rdata = Vec::new();
+    write_name(&mut rdata, instance_name);
+    write_record(&mut packet, LAN_SERVICE_TYPE, TYPE_PTR, CLASS_IN, &rdata);
+
+    // SRV instance -> port
+    let mut rdata = Vec::new();
+    rdata.extend_from_slice(&0u16.to_be_bytes());
+    rdata.extend_from_slice(&0u16.to_be_bytes());
+    rdata.extend_from_slice(&port.to_be_bytes());
+    write_name(
+        &mut rdata,
+        &format!("{}.local", dns_label(instance).replace(' ', "-")),
+    );
+    write_record(
+        &mut packet,
+        instance_name,
+        TYPE_SRV,
+        CLASS_IN | CLASS_TOP_BIT,
+        &rdata,
+    );
+
+    // TXT, required by DNS-SD even if empty
+    let txt = b"v=1";
+    let mut rdata = vec![txt.len() as u8];
+    rdata.extend_from_slice(txt);
+    write_record(
+        &mut packet,
+        instance_name,
+        TYPE_TXT,
+        CLASS_IN | CLASS_TOP_BIT,
+        &rdata,
+    );
+
+    packet
+}
+
+/// `(instance label, port)` of every service instance in a response.
+fn parse_services(packet: &[u8]) -> Vec<(String, u16)> {
+    let Some(flags) = read_u16(packet, 2) else {
+        return Vec::new();
+    };
+    if flags & 0x8000 == 0 {
+        return Vec::new();
+    }
+
+    let mut instances = Vec::new();
+    let mut ports = HashMap::new();
+
+    let records = [6, 8, 10]
+        .iter()
+        .filter_map(|offset| read_u16(packet, *offset))
+        .map(usize::from)
+        .sum::<usize>();
+
+    let mut offset = 12;
+    for _ in 0..read_u16(packet, 4).unwrap_or_default() {
+        let Some((_, next)) = read_name(packet, offset) else {
+            return Vec::new();
+        };
+        offset = next + 4;
+    }
+
+    for _ in 0..records {
+        let Some((name, next)) = read_name(packet, offset) else {
+            break;
+        };
+        let (Some(record_type), Some(rdlength)) =
+            (read_u16(packet, next), read_u16(packet, next + 8))
+        else {
+            break;
+        };
+        let rdata = next + 10;
+        offset = rdata + rdlength as usize;
+        if offset > packet.len() {
+            break;
+        }
+
+        match record_type {
+            TYPE_PTR if name.eq_ignore_ascii_case(LAN_SERVICE_TYPE) => {
+                if let Some((instance, _)) = read_name(packet, rdata) {
+                    instances.push(instance);
+                }
+            }
+            TYPE_SRV => {
+                if let Some(port) = read_u16(packet, rdata + 4) {
+                    ports.insert(name.to_lowercase(), port);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let suffix = format!(".{}", LAN_SERVICE_TYPE);
+    instances
+        .into_iter()
+        .filter_map(|instance| {
+            let port = *ports.get(&instance.to_lowercase())?;
+            let label = instance.strip_suffix(&suffix).unwrap_or(&instance);
+            Some((label.to_string(), port))
+        })
+        .collect()
+}
+
+fn header(id: u16, flags: u16, questions: u16, answers: u16, additional: u16) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(512);
+    for value in [id, flags, questions, answers, 0, additional] {
+        packet.extend_from_slice(&value.to_be_bytes());
+    }
+    packet
+}
+
+fn write_record(packet: &mut Vec<u8>, name: &str, record_type: u16, class: u16, rdata: &[u8]) {
+    write_name(packet, name);
+    packet.extend_from_slice(&record_type.to_be_bytes());
+    packet.extend_from_slice(&class.to_be_bytes());
+    packet.extend_from_slice(&RECORD_TTL.to_be_bytes());
+    packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    packet.extend_from_slice(rdata);
+}
+
+/// Writes the service type labels split on dots, the instance label may contain
+/// anything but a dot (see `dns_label`).
+fn write_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+}
+
+/// Reads a possibly compressed name, returns it dotted and the offset after it.
+fn read_name(packet: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+
+    // Bounded so a pointer loop in a malformed packet can't spin forever
+    for _ in 0..128 {
+        let len = *packet.get(offset)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(offset + 1)));
+        }
+
+        if len & 0xc0 == 0xc0 {
+            let pointer = (read_u16(packet, offset)? & 0x3fff) as usize;
+            end.get_or_insert(offset + 2);
+            offset = pointer;
+            continue;
+        }
+
+        let label = packet.get(offset + 1..offset + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        offset += 1 + len;
+    }
+
+    None
+}
+
+fn read_u16(packet: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        packet.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// A single DNS label: no dots, at most 63 bytes.
+fn dns_label(value: &str) -> String {
+    let mut label = String::new();
+    for c in value.replace('.', "-").chars() {
+        if label.len() + c.len_utf8() > 63 {
+            break;
+        }
+        label.push(c);
+    }
+
+    if label.is_empty() {
+        "clippy".to_string()
+    } else {
+        label
+    }
+}
@@ -1,6 +1,13 @@
+pub mod api_server;
+pub mod cli;
 pub mod clipboard_manager;
+pub mod clipboard_reader;
+pub mod foreground_window;
 pub mod fullscreen_detector;
 pub mod hotkey_manager;
+pub mod lan_server;
+pub mod mdns;
 pub mod ocr;
 pub mod providers;
 pub mod sync_manager;
+pub mod window_ffi;
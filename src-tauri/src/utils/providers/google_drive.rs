@@ -1,19 +1,21 @@
-use super::parse_clipboard_info;
+use super::{
+    cleanup_remote_clipboards, clipboard_content_hash, collect_remote_clipboards,
+    parse_clipboard_info, star_remote_clipboard,
+};
 use crate::prelude::*;
 use crate::{
-    service::settings::{get_global_settings, update_settings_synchronize_db},
+    service::settings::update_settings_synchronize_db,
     tao::{config::get_data_path, global::get_app},
     utils::providers::{create_clipboard_filename, uuid_to_datetime},
 };
-use chrono::{NaiveDateTime, TimeZone, Utc};
+use chrono::{TimeZone, Utc};
 use common::{
     constants::{BACKUP_FILE_PREFIX, BACKUP_SETTINGS_PREFIX, TOKEN_NAME},
     printlog,
     types::{
-        enums::ListenEvent,
         orm_query::FullClipboardDto,
-        sync::{Clippy, GoogleDriveProvider, SyncProvider},
-        types::{CommandError, Progress},
+        sync::{Clippy, GoogleDriveProvider, RemoteChanges, SyncProvider},
+        types::CommandError,
     },
 };
 use google_drive3::{
@@ -24,10 +26,9 @@ use google_drive3::{
 };
 use http_body_util::BodyExt;
 use migration::async_trait;
-use sea_orm::prelude::Uuid;
 use serde_json::Value;
 use std::{collections::HashMap, future::Future, io::Cursor, pin::Pin, time::Duration};
-use tauri::{Emitter, EventTarget, Manager};
+use tauri::Manager;
 use tauri_plugin_clipboard::Clipboard;
 use tauri_plugin_opener::OpenerExt;
 
@@ -208,6 +209,22 @@ impl GoogleDriveProviderImpl {
         Ok(all_files)
     }
 
+    /// File with exactly `name` in the app data folder.
+    async fn find_file(&self, name: &str) -> Result<Option<File>, Box<dyn std::error::Error>> {
+        let (_, file_list) = self
+            .0
+            .hub
+            .files()
+            .list()
+            .q(&format!("name = '{}'", name))
+            .spaces("appDataFolder")
+            .add_scope(Scope::Appdata.as_ref())
+            .doit()
+            .await?;
+
+        Ok(file_list.files.and_then(|files| files.into_iter().next()))
+    }
+
     async fn find_settings_file(&self) -> Result<Option<File>, Box<dyn std::error::Error>> {
         let (_, file_list) = self
             .0
@@ -227,119 +244,13 @@ impl GoogleDriveProviderImpl {
 #[async_trait::async_trait]
 impl SyncProvider for GoogleDriveProviderImpl {
     async fn fetch_all_clipboards(&self) -> Result<Vec<Clippy>, Box<dyn std::error::Error>> {
-        let filelist = self.fetch_all_clipboard_files().await?;
-        let mut clipboards = Vec::new();
-        let mut seen_ids = std::collections::HashSet::new();
-
-        for file in filelist {
-            if let Some(remote) =
-                parse_clipboard_info(&file.name.expect("No name"), &file.id.expect("No id"))
-            {
-                // Only add if we haven't seen this ID before
-                if seen_ids.insert(remote.id) {
-                    clipboards.push(remote);
-                } else {
-                    printlog!(
-                        "Found duplicate clipboard: {} from {}, deleting duplicate",
-                        remote.id,
-                        uuid_to_datetime(&remote.id)
-                    );
-                    // Delete the duplicate file
-                    self.delete_clipboard(&remote).await;
-                }
-            }
-        }
-
-        // Sort by created_at
-        clipboards.sort_by(|a, b| b.id.cmp(&a.id));
-
-        if !clipboards.is_empty() {
-            let newest = &clipboards[0];
-            let oldest = &clipboards[clipboards.len() - 1];
-            printlog!(
-                "(remote) found {} clipboards from {} to {}",
-                clipboards.len(),
-                uuid_to_datetime(&oldest.id),
-                uuid_to_datetime(&newest.id)
-            );
-        } else {
-            printlog!("(remote) found no clipboards");
-        }
-
-        Ok(clipboards)
-    }
+        let files = self
+            .fetch_all_clipboard_files()
+            .await?
+            .into_iter()
+            .map(|file| (file.name.expect("No name"), file.id.expect("No id")));
 
-    async fn compare_and_fetch_new_clipboards(
-        &self,
-        local_clipboards: &HashMap<Uuid, (bool, NaiveDateTime)>,
-        remote_clipboards: &Vec<Clippy>,
-    ) -> Result<Vec<FullClipboardDto>, Box<dyn std::error::Error>> {
-        let mut new_clipboards = Vec::new();
-
-        let total = remote_clipboards.len();
-        for (index, file) in remote_clipboards.iter().enumerate() {
-            // Skip if the clipboard is marked for deletion
-            if file.deleted_at.is_some() {
-                continue;
-            }
-
-            if let Some((local_star, _local_timestamp)) = local_clipboards.get(&file.id) {
-                // star status is different
-                if local_star == &file.star {
-                    continue;
-                }
-            }
-
-            printlog!(
-                "downloading clipboard: {} from {} star: {} encrypted: {}",
-                file.id,
-                uuid_to_datetime(&file.id),
-                file.star,
-                file.encrypted
-            );
-
-            get_app().emit_to(
-                EventTarget::any(),
-                ListenEvent::Progress.to_string().as_str(),
-                Progress {
-                    label: "SETTINGS.ENCRYPT.DOWNLOADING_REMOTE_CLIPBOARDS".to_string(),
-                    total,
-                    current: index + 1,
-                },
-            )?;
-
-            new_clipboards.push(self.download_by_id(&file.provider_id).await?);
-        }
-
-        Ok(new_clipboards)
-    }
-
-    async fn upload_new_clipboards(
-        &self,
-        new_local_clipboards: &[FullClipboardDto],
-        remote_clipboards: &Vec<Clippy>,
-    ) -> Result<Vec<Clippy>, Box<dyn std::error::Error>> {
-        let mut new_clipboards = Vec::new();
-
-        // Skip clipboards that were recently deleted
-        let recently_deleted: Vec<_> = remote_clipboards
-            .iter()
-            .filter(|c| c.deleted_at.is_some())
-            .map(|c| c.id)
-            .collect();
-
-        for clipboard in new_local_clipboards {
-            if remote_clipboards
-                .iter()
-                .any(|clip| clip.id == clipboard.clipboard.id)
-                || recently_deleted.contains(&clipboard.clipboard.id)
-            {
-                continue;
-            }
-            new_clipboards.push(self.upload_clipboard(clipboard).await?);
-        }
-
-        Ok(new_clipboards)
+        Ok(collect_remote_clipboards(self, files).await)
     }
 
     async fn mark_for_deletion(&self, clippy: &Clippy) {
@@ -349,6 +260,7 @@ impl SyncProvider for GoogleDriveProviderImpl {
             &clippy.encrypted,
             &clippy.created_at,
             Some(Utc::now().naive_utc()),
+            clippy.content_hash.as_deref(),
         );
 
         let file = google_drive3::api::File {
@@ -402,79 +314,21 @@ impl SyncProvider for GoogleDriveProviderImpl {
         &self,
         remote_clipboards: &Vec<Clippy>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let sync_limit = get_global_settings().sync_limit as usize;
-
-        // Get all non-starred clipboards
-        let mut all_clipboards: Vec<_> =
-            remote_clipboards.iter().filter(|clip| !clip.star).collect();
-
-        // Sort by creation date
-        all_clipboards.sort_by(|a, b| a.id.cmp(&b.id));
-
-        // Find all marked-for-deletion indices
-        let marked_indices: Vec<usize> = all_clipboards
-            .iter()
-            .enumerate()
-            .filter(|(_, clip)| clip.deleted_at.is_some())
-            .map(|(idx, _)| idx)
-            .collect();
-
-        // Only delete if we have clipboards beyond the sync limit
-        if all_clipboards.len() <= sync_limit {
-            return Ok(());
-        }
-
-        if let Some(&last_marked_idx) = marked_indices.last() {
-            // Calculate how many clipboards we need to delete
-
-            let total_to_delete = all_clipboards.len() - sync_limit + marked_indices.len();
-
-            printlog!(
-                "total clipboards: {}, marked clipboards: {}, total to delete: {} (last marked: {})",
-                all_clipboards.len(),
-                marked_indices.len(),
-                total_to_delete,
-                last_marked_idx
-            );
-            // Only delete if we can remove everything up to and including the last marked clipboard
-            if total_to_delete > last_marked_idx {
-                // Delete oldest clipboards including the marked ones
-                for clippy in all_clipboards.iter().take(total_to_delete) {
-                    printlog!(
-                        "deleting clipboard: {} from {} (marked: {})",
-                        clippy.id,
-                        uuid_to_datetime(&clippy.id),
-                        clippy.deleted_at.is_some()
-                    );
-                    self.delete_clipboard(clippy).await;
-                }
-            }
-        } else {
-            // No marked clipboards - normal cleanup
-            let to_delete = all_clipboards.len() - sync_limit;
-            for clippy in all_clipboards.iter().take(to_delete) {
-                printlog!(
-                    "deleting clipboard: {} from {}",
-                    clippy.id,
-                    uuid_to_datetime(&clippy.id),
-                );
-                self.delete_clipboard(clippy).await;
-            }
-        }
-
-        Ok(())
+        cleanup_remote_clipboards(self, remote_clipboards).await
     }
 
     async fn upload_clipboard(
         &self,
         clipboard: &FullClipboardDto,
     ) -> Result<Clippy, Box<dyn std::error::Error>> {
+        let content_hash = clipboard_content_hash(clipboard);
         let file_name = create_clipboard_filename(
             &clipboard.clipboard.id,
             &clipboard.clipboard.star,
             &clipboard.clipboard.encrypted,
             &clipboard.clipboard.created_at,
             None,
+            Some(&content_hash),
         );
 
         printlog!(
@@ -524,6 +378,7 @@ impl SyncProvider for GoogleDriveProviderImpl {
             &local_clipboard.clipboard.encrypted,
             &local_clipboard.clipboard.created_at,
             None,
+            Some(&clipboard_content_hash(local_clipboard)),
         );
 
         printlog!(
@@ -557,32 +412,7 @@ impl SyncProvider for GoogleDriveProviderImpl {
     }
 
     async fn star_clipboard(&self, clippy: &FullClipboardDto) {
-        let clipboards = self
-            .fetch_all_clipboards()
-            .await
-            .expect("Failed to star fetch clipboards");
-
-        let remote_clipboards = clipboards
-            .iter()
-            .find(|clip| clip.id == clippy.clipboard.id);
-
-        if let Some(remote_clipboard) = remote_clipboards {
-            printlog!(
-                "starring clipboard: {} from {} star: {} encrypted: {}",
-                remote_clipboard.id,
-                uuid_to_datetime(&remote_clipboard.id),
-                clippy.clipboard.star,
-                clippy.clipboard.encrypted
-            );
-
-            self.update_clipboard(clippy, remote_clipboard)
-                .await
-                .expect("Failed to star update clipboard");
-        } else {
-            self.upload_clipboard(clippy)
-                .await
-                .expect("Failed to star upload clipboard");
-        }
+        star_remote_clipboard(self, clippy).await
     }
 
     async fn upsert_settings(
@@ -646,4 +476,118 @@ impl SyncProvider for GoogleDriveProviderImpl {
             Err(_) => false,
         }
     }
+
+    async fn fetch_changes(
+        &self,
+        cursor: Option<&str>,
+    ) -> Result<Option<RemoteChanges>, Box<dyn std::error::Error>> {
+        let Some(cursor) = cursor else {
+            let (_, token) = self
+                .0
+                .hub
+                .changes()
+                .get_start_page_token()
+                .add_scope(Scope::Appdata.as_ref())
+                .doit()
+                .await?;
+
+            return Ok(Some(RemoteChanges {
+                cursor: token.start_page_token.ok_or("No start page token")?,
+                changed: true,
+            }));
+        };
+
+        let mut changed = false;
+        let mut page_token = cursor.to_string();
+
+        loop {
+            let (_, change_list) = self
+                .0
+                .hub
+                .changes()
+                .list(&page_token)
+                .spaces("appDataFolder")
+                .add_scope(Scope::Appdata.as_ref())
+                .doit()
+                .await?;
+
+            changed |= change_list
+                .changes
+                .is_some_and(|changes| !changes.is_empty());
+
+            // The last page carries the cursor for the next call instead of a next page
+            match (
+                change_list.next_page_token,
+                change_list.new_start_page_token,
+            ) {
+                (Some(next), _) => page_token = next,
+                (None, Some(cursor)) => {
+                    return Ok(Some(RemoteChanges { cursor, changed }));
+                }
+                (None, None) => return Err("Change list without page token".into()),
+            }
+        }
+    }
+
+    async fn read_document(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(file) = self.find_file(name).await? else {
+            return Ok(None);
+        };
+
+        let (mut response, _) = self
+            .0
+            .hub
+            .files()
+            .get(&file.id.expect("No id"))
+            .param("alt", "media")
+            .acknowledge_abuse(true)
+            .add_scope(Scope::Appdata.as_ref())
+            .doit()
+            .await?;
+
+        let content = String::from_utf8(response.body_mut().collect().await?.to_bytes().to_vec())?;
+
+        Ok(Some(content))
+    }
+
+    /// Updates the existing file in place, `create` would add a second one with the
+    /// same name.
+    async fn write_document(
+        &self,
+        name: &str,
+        content: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.find_file(name).await?.and_then(|file| file.id) {
+            Some(id) => {
+                self.0
+                    .hub
+                    .files()
+                    .update(File::default(), &id)
+                    .add_scope(Scope::Appdata.as_ref())
+                    .upload(Cursor::new(content), "application/json".parse()?)
+                    .await?;
+            }
+            None => {
+                let file = File {
+                    name: Some(name.to_string()),
+                    mime_type: Some("application/json".into()),
+                    parents: Some(vec!["appDataFolder".into()]),
+                    ..Default::default()
+                };
+
+                self.0
+                    .hub
+                    .files()
+                    .create(file)
+                    .add_scope(Scope::Appdata.as_ref())
+                    .upload(Cursor::new(content), "application/json".parse()?)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
 }
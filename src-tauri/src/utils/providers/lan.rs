@@ -0,0 +1,486 @@
+use super::{cleanup_remote_clipboards, get_credential, star_remote_clipboard};
+use crate::prelude::*;
+use crate::service::{
+    cipher::{derive_key, PBKDF2_ITERATIONS},
+    decrypt::decrypt_data_with_key,
+    encrypt::encrypt_data_with_key,
+    settings::get_global_settings,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::NaiveDateTime;
+use common::{
+    constants::{ENCRYPTION_MAGIC_STRING, LAN_CREDENTIAL},
+    types::{
+        orm_query::FullClipboardDto,
+        sync::{Clippy, SyncProvider},
+        types::CommandError,
+    },
+};
+use migration::async_trait;
+use ring::{agreement, hmac, rand::SecureRandom, rand::SystemRandom};
+use sea_orm::prelude::Uuid;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, io, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+pub const LAN_MODE_SYNC: u8 = b'S';
+pub const LAN_MODE_PAIR: u8 = b'P';
+pub const LAN_NONCE_LEN: usize = 16;
+pub const LAN_SALT_LEN: usize = 16;
+pub const LAN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Big enough for any clipboard the size limits let through
+const LAN_MAX_FRAME_SIZE: usize = 512 * 1024 * 1024;
+/// Sender role, sequence number and body length
+const LAN_HEADER_LEN: usize = 1 + 8 + 4;
+/// Nonce and tag `encrypt_data_with_key` adds besides the magic string
+const LAN_SEAL_OVERHEAD: usize = 12 + 16;
+const SESSION_LABEL: &[u8] = b"clippy-lan-session";
+const PAIRING_LABEL: &[u8] = b"clippy-lan-pairing";
+
+/// Which side wrote a frame, so a frame can't be reflected back to its sender.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LanRole {
+    Client = 0,
+    Host = 1,
+}
+
+/// Calls a paired device makes on the host, one per connection. They map 1:1 onto
+/// the host's own store provider.
+#[derive(Serialize, Deserialize)]
+pub enum LanRequest {
+    Ping,
+    FetchAll,
+    Download(String),
+    Upload(FullClipboardDto),
+    Update(FullClipboardDto, Clippy),
+    MarkForDeletion(Clippy),
+    Delete(Clippy),
+    GetSettings,
+    UpsertSettings(HashMap<String, Value>),
+    ReadDocument(String),
+    WriteDocument(String, String),
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum LanResponse {
+    Done,
+    Clipboards(Vec<Clippy>),
+    Clipboard(Box<FullClipboardDto>),
+    Uploaded(Clippy),
+    Settings(HashMap<String, Value>),
+    Document(Option<String>),
+    Error(String),
+}
+
+/// Frames sealed with `encrypt_data_with_key`, each a sealed fixed size header with the
+/// body length followed by the sealed body. Nothing is allocated for a body before its
+/// header authenticated, so a peer without the key can't make the other side buffer a
+/// large frame. Header and body carry the sender role and a sequence number, replayed,
+/// reordered or reflected frames are rejected.
+pub struct LanChannel {
+    stream: TcpStream,
+    key: [u8; 32],
+    role: LanRole,
+    sent: u64,
+    received: u64,
+}
+
+impl LanChannel {
+    pub fn new(stream: TcpStream, key: [u8; 32], role: LanRole) -> Self {
+        Self {
+            stream,
+            key,
+            role,
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    pub async fn send<T: Serialize>(&mut self, message: &T) -> io::Result<()> {
+        let mut plain = self.frame_prefix(self.role);
+        plain.extend_from_slice(&serde_json::to_vec(message)?);
+        let body = encrypt_data_with_key(&plain, &self.key).map_err(invalid_data)?;
+
+        let mut header = self.frame_prefix(self.role);
+        header.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        let header = encrypt_data_with_key(&header, &self.key).map_err(invalid_data)?;
+
+        self.stream.write_all(&header).await?;
+        self.stream.write_all(&body).await?;
+        self.stream.flush().await?;
+        self.sent += 1;
+
+        Ok(())
+    }
+
+    pub async fn receive<T: DeserializeOwned>(&mut self) -> io::Result<T> {
+        let expected_role = match self.role {
+            LanRole::Client => LanRole::Host,
+            LanRole::Host => LanRole::Client,
+        };
+        let prefix = self.frame_prefix(expected_role);
+
+        let mut header =
+            vec![0u8; ENCRYPTION_MAGIC_STRING.len() + LAN_SEAL_OVERHEAD + LAN_HEADER_LEN];
+        self.stream.read_exact(&mut header).await?;
+        let header = decrypt_data_with_key(&header, &self.key).map_err(invalid_data)?;
+        let Some(len) = header
+            .strip_prefix(prefix.as_slice())
+            .and_then(|len| len.try_into().ok())
+            .map(|len| u32::from_be_bytes(len) as usize)
+        else {
+            return Err(invalid_data("unexpected frame"));
+        };
+        if len > LAN_MAX_FRAME_SIZE {
+            return Err(invalid_data("frame too large"));
+        }
+
+        let mut body = vec![0u8; len];
+        self.stream.read_exact(&mut body).await?;
+        let body = decrypt_data_with_key(&body, &self.key).map_err(invalid_data)?;
+        let Some(message) = body.strip_prefix(prefix.as_slice()) else {
+            return Err(invalid_data("unexpected frame"));
+        };
+        self.received += 1;
+
+        Ok(serde_json::from_slice(message)?)
+    }
+
+    /// Role and sequence number a frame of `role` has to start with.
+    fn frame_prefix(&self, role: LanRole) -> Vec<u8> {
+        let sequence = if role == self.role {
+            self.sent
+        } else {
+            self.received
+        };
+
+        let mut prefix = vec![role as u8];
+        prefix.extend_from_slice(&sequence.to_be_bytes());
+        prefix
+    }
+}
+
+pub fn invalid_data(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to generate random bytes");
+    bytes
+}
+
+/// Per connection key from the shared LAN key and both nonces.
+pub fn session_key(
+    lan_key: &[u8; 32],
+    client_nonce: &[u8; LAN_NONCE_LEN],
+    host_nonce: &[u8; LAN_NONCE_LEN],
+) -> [u8; 32] {
+    hmac_key(lan_key, &[SESSION_LABEL, client_nonce, host_nonce])
+}
+
+/// Pairing key from the X25519 secret and the pairing code. Without the code an
+/// eavesdropper has nothing to go on, a man in the middle has to guess it; PBKDF2
+/// makes every guess as slow as unlocking the history.
+pub fn pairing_key(
+    shared_secret: &[u8],
+    code: &str,
+    salt: &[u8; LAN_SALT_LEN],
+    client_public: &[u8],
+    host_public: &[u8],
+) -> [u8; 32] {
+    let code_key = derive_key(&normalize_pairing_code(code), salt, PBKDF2_ITERATIONS);
+    hmac_key(
+        &code_key.0,
+        &[PAIRING_LABEL, shared_secret, client_public, host_public],
+    )
+}
+
+/// Codes are shown as `ABCD-EFGH`, typed any which way.
+pub fn normalize_pairing_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn hmac_key(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut context = hmac::Context::with_key(&hmac::Key::new(hmac::HMAC_SHA256, key));
+    for part in parts {
+        context.update(part);
+    }
+
+    let mut output = [0u8; 32];
+    output.copy_from_slice(context.sign().as_ref());
+    output
+}
+
+pub fn ephemeral_key() -> io::Result<(agreement::EphemeralPrivateKey, Vec<u8>)> {
+    let private_key =
+        agreement::EphemeralPrivateKey::generate(&agreement::X25519, &SystemRandom::new())
+            .map_err(invalid_data)?;
+    let public_key = private_key
+        .compute_public_key()
+        .map_err(invalid_data)?
+        .as_ref()
+        .to_vec();
+
+    Ok((private_key, public_key))
+}
+
+pub fn agree(
+    private_key: agreement::EphemeralPrivateKey,
+    peer_public: &[u8],
+) -> io::Result<Vec<u8>> {
+    agreement::agree_ephemeral(
+        private_key,
+        &agreement::UnparsedPublicKey::new(&agreement::X25519, peer_public),
+        |secret| secret.to_vec(),
+    )
+    .map_err(invalid_data)
+}
+
+pub fn encode_lan_key(key: &[u8; 32]) -> String {
+    STANDARD.encode(key)
+}
+
+pub fn decode_lan_key(value: &str) -> Option<[u8; 32]> {
+    STANDARD.decode(value).ok()?.try_into().ok()
+}
+
+/// Pairs with the host at `address` using the code it shows, returns the LAN key
+/// to keep in the keychain.
+pub async fn pair_with_host(address: &str, code: &str, device: &str) -> io::Result<[u8; 32]> {
+    tokio::time::timeout(LAN_TIMEOUT, async {
+        let mut stream = TcpStream::connect(address).await?;
+        let (private_key, client_public) = ephemeral_key()?;
+
+        stream.write_all(&[LAN_MODE_PAIR]).await?;
+        stream.write_all(&client_public).await?;
+
+        let mut status = [0u8; 1];
+        stream.read_exact(&mut status).await?;
+        if status[0] != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Host is not pairing, start pairing on it first",
+            ));
+        }
+
+        let mut host_public = vec![0u8; client_public.len()];
+        stream.read_exact(&mut host_public).await?;
+        let mut salt = [0u8; LAN_SALT_LEN];
+        stream.read_exact(&mut salt).await?;
+
+        let shared_secret = agree(private_key, &host_public)?;
+        let code = code.to_string();
+        let key = tokio::task::spawn_blocking(move || {
+            pairing_key(&shared_secret, &code, &salt, &client_public, &host_public)
+        })
+        .await
+        .map_err(invalid_data)?;
+
+        let mut channel = LanChannel::new(stream, key, LanRole::Client);
+        channel.send(&device).await?;
+
+        // The host hangs up on a wrong code
+        let lan_key: String = channel
+            .receive()
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::PermissionDenied, "Wrong pairing code"))?;
+
+        decode_lan_key(&lan_key).ok_or_else(|| invalid_data("invalid LAN key"))
+    })
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Pairing timed out"))?
+}
+
+/// Syncs through another clippy instance on the local network (the host), which
+/// keeps the store every paired device syncs against. Clipboards and settings go over
+/// the same `Clippy`/`FullClipboardDto` shapes the other providers use, encrypted with
+/// the key exchanged while pairing.
+pub struct LanProviderImpl {
+    /// `ip:port` of the host
+    address: String,
+    key: [u8; 32],
+}
+
+impl LanProviderImpl {
+    pub fn new(address: &str, key: [u8; 32]) -> Self {
+        Self {
+            address: address.trim().to_string(),
+            key,
+        }
+    }
+
+    /// Provider for the host in the settings and the key in the keychain.
+    pub fn from_settings() -> Result<Self, CommandError> {
+        let settings = get_global_settings();
+        if settings.lan_peer.is_empty() {
+            return Err(CommandError::new("LAN sync is not paired"));
+        }
+
+        let key = get_credential(LAN_CREDENTIAL)?
+            .and_then(|value| decode_lan_key(&value))
+            .ok_or_else(|| CommandError::new("LAN sync key missing, pair again"))?;
+
+        Ok(Self::new(&settings.lan_peer, key))
+    }
+
+    async fn call(&self, request: LanRequest) -> Result<LanResponse, Box<dyn std::error::Error>> {
+        let response = tokio::time::timeout(LAN_TIMEOUT, async {
+            let mut stream = TcpStream::connect(&self.address).await?;
+
+            let client_nonce = random_bytes::<LAN_NONCE_LEN>();
+            stream.write_all(&[LAN_MODE_SYNC]).await?;
+            stream.write_all(&client_nonce).await?;
+
+            let mut host_nonce = [0u8; LAN_NONCE_LEN];
+            stream.read_exact(&mut host_nonce).await?;
+
+            let mut channel = LanChannel::new(
+                stream,
+                session_key(&self.key, &client_nonce, &host_nonce),
+                LanRole::Client,
+            );
+            channel.send(&request).await?;
+            channel.receive::<LanResponse>().await
+        })
+        .await
+        .map_err(|_| format!("LAN host {} timed out", self.address))??;
+
+        match response {
+            LanResponse::Error(e) => Err(e.into()),
+            response => Ok(response),
+        }
+    }
+}
+
+fn unexpected_response() -> Box<dyn std::error::Error> {
+    "Unexpected answer from LAN host".into()
+}
+
+#[async_trait::async_trait]
+impl SyncProvider for LanProviderImpl {
+    async fn fetch_all_clipboards(&self) -> Result<Vec<Clippy>, Box<dyn std::error::Error>> {
+        match self.call(LanRequest::FetchAll).await? {
+            LanResponse::Clipboards(clipboards) => Ok(clipboards),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    async fn mark_for_deletion(&self, clippy: &Clippy) {
+        match self.call(LanRequest::MarkForDeletion(clippy.clone())).await {
+            Ok(_) => printlog!("(remote) marked clipboard for deletion: {}", clippy.id),
+            Err(e) => printlog!("(remote) failed to mark {} for deletion: {}", clippy.id, e),
+        }
+    }
+
+    async fn delete_clipboard(&self, clippy: &Clippy) {
+        self.call(LanRequest::Delete(clippy.clone())).await.ok();
+    }
+
+    async fn download_by_id(
+        &self,
+        id: &String,
+    ) -> Result<FullClipboardDto, Box<dyn std::error::Error>> {
+        match self.call(LanRequest::Download(id.clone())).await? {
+            LanResponse::Clipboard(clipboard) => Ok(*clipboard),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    async fn upload_clipboard(
+        &self,
+        clipboard: &FullClipboardDto,
+    ) -> Result<Clippy, Box<dyn std::error::Error>> {
+        match self.call(LanRequest::Upload(clipboard.clone())).await? {
+            LanResponse::Uploaded(clippy) => Ok(clippy),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    async fn update_clipboard(
+        &self,
+        local_clipboard: &FullClipboardDto,
+        remote_clipboard: &Clippy,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(LanRequest::Update(
+            local_clipboard.clone(),
+            remote_clipboard.clone(),
+        ))
+        .await?;
+
+        Ok(())
+    }
+
+    async fn star_clipboard(&self, clippy: &FullClipboardDto) {
+        star_remote_clipboard(self, clippy).await
+    }
+
+    async fn cleanup_old_clipboards(
+        &self,
+        remote_clipboards: &Vec<Clippy>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        cleanup_remote_clipboards(self, remote_clipboards).await
+    }
+
+    async fn upsert_settings(
+        &self,
+        settings: &HashMap<String, Value>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(LanRequest::UpsertSettings(settings.clone()))
+            .await?;
+
+        printlog!("(remote) uploaded settings");
+
+        Ok(())
+    }
+
+    async fn get_settings(&self) -> Result<HashMap<String, Value>, Box<dyn std::error::Error>> {
+        match self.call(LanRequest::GetSettings).await? {
+            LanResponse::Settings(settings) => Ok(settings),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    async fn is_authenticated(&self) -> bool {
+        match self.call(LanRequest::Ping).await {
+            Ok(_) => true,
+            Err(e) => {
+                printlog!("(remote) lan host {} not reachable: {}", self.address, e);
+                false
+            }
+        }
+    }
+
+    async fn read_document(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        match self
+            .call(LanRequest::ReadDocument(name.to_string()))
+            .await?
+        {
+            LanResponse::Document(content) => Ok(content),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    async fn write_document(
+        &self,
+        name: &str,
+        content: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(LanRequest::WriteDocument(name.to_string(), content))
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,440 @@
+use super::{
+    cleanup_remote_clipboards, clipboard_content_hash, collect_remote_clipboards,
+    create_clipboard_filename, star_remote_clipboard, uuid_to_datetime,
+};
+use crate::prelude::*;
+use crate::service::settings::get_global_settings;
+use chrono::{NaiveDateTime, Utc};
+use common::{
+    constants::{BACKUP_FILE_PREFIX, BACKUP_SETTINGS_PREFIX},
+    types::{
+        orm_query::FullClipboardDto,
+        sync::{Clippy, SyncProvider},
+        types::CommandError,
+    },
+};
+use migration::async_trait;
+use sea_orm::prelude::Uuid;
+use serde_json::Value;
+use std::{collections::HashMap, io::ErrorKind, path::PathBuf};
+
+/// Syncs through a plain directory that something else keeps in sync between devices
+/// (Syncthing, Dropbox, an SMB/NFS mount, ...). Same file names as on Google Drive,
+/// the file name is the provider id.
+pub struct LocalFolderProviderImpl {
+    path: PathBuf,
+}
+
+impl LocalFolderProviderImpl {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Provider for the folder in the settings.
+    pub fn from_settings() -> Result<Self, CommandError> {
+        let settings = get_global_settings();
+        if settings.local_sync_path.is_empty() {
+            return Err(CommandError::new("Folder sync is not configured"));
+        }
+
+        Ok(Self::new(settings.local_sync_path))
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Names of all clipboard files in the folder.
+    async fn list_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut entries = tokio::fs::read_dir(&self.path).await?;
+        let mut names = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(name) = entry.file_name().to_str() {
+                if name.starts_with(BACKUP_FILE_PREFIX) && name.ends_with(".json") {
+                    names.push(name.to_string());
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Returns `None` if the file doesn't exist.
+    async fn read_file(&self, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        match tokio::fs::read_to_string(self.path.join(name)).await {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes through a hidden temp file and renames it, so other devices never see a
+    /// half written file while the folder is being synced.
+    async fn write_file(
+        &self,
+        name: &str,
+        content: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let partial_path = self.path.join(format!(".{}.partial", name));
+
+        tokio::fs::write(&partial_path, content).await?;
+        tokio::fs::rename(&partial_path, self.path.join(name)).await?;
+
+        Ok(())
+    }
+
+    async fn rename_file(&self, from: &str, to: &str) -> Result<(), Box<dyn std::error::Error>> {
+        tokio::fs::rename(self.path.join(from), self.path.join(to)).await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl SyncProvider for LocalFolderProviderImpl {
+    async fn fetch_all_clipboards(&self) -> Result<Vec<Clippy>, Box<dyn std::error::Error>> {
+        let files = self
+            .list_files()
+            .await?
+            .into_iter()
+            .map(|name| (name.clone(), name));
+
+        Ok(collect_remote_clipboards(self, files).await)
+    }
+
+    async fn mark_for_deletion(&self, clippy: &Clippy) {
+        let new_name = create_clipboard_filename(
+            &clippy.id,
+            &clippy.star,
+            &clippy.encrypted,
+            &clippy.created_at,
+            Some(Utc::now().naive_utc()),
+            clippy.content_hash.as_deref(),
+        );
+
+        match self.rename_file(&clippy.provider_id, &new_name).await {
+            Ok(()) => printlog!("(remote) marked clipboard for deletion: {}", clippy.id),
+            Err(e) => printlog!("(remote) failed to mark {} for deletion: {}", clippy.id, e),
+        }
+    }
+
+    async fn delete_clipboard(&self, clippy: &Clippy) {
+        tokio::fs::remove_file(self.path.join(&clippy.provider_id))
+            .await
+            .ok();
+    }
+
+    async fn download_by_id(
+        &self,
+        id: &String,
+    ) -> Result<FullClipboardDto, Box<dyn std::error::Error>> {
+        let content = self
+            .read_file(id)
+            .await?
+            .ok_or_else(|| format!("Clipboard file {} not found", id))?;
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    async fn upload_clipboard(
+        &self,
+        clipboard: &FullClipboardDto,
+    ) -> Result<Clippy, Box<dyn std::error::Error>> {
+        let content_hash = clipboard_content_hash(clipboard);
+        let file_name = create_clipboard_filename(
+            &clipboard.clipboard.id,
+            &clipboard.clipboard.star,
+            &clipboard.clipboard.encrypted,
+            &clipboard.clipboard.created_at,
+            None,
+            Some(&content_hash),
+        );
+
+        printlog!(
+            "uploading clipboard: {} from {} star: {} encrypted: {}",
+            clipboard.clipboard.id,
+            uuid_to_datetime(&clipboard.clipboard.id),
+            clipboard.clipboard.star,
+            clipboard.clipboard.encrypted
+        );
+
+        self.write_file(&file_name, serde_json::to_string(&clipboard)?)
+            .await?;
+
+        Ok(Clippy {
+            id: clipboard.clipboard.id,
+            provider_id: file_name,
+            star: clipboard.clipboard.star,
+            encrypted: clipboard.clipboard.encrypted,
+            created_at: clipboard.clipboard.created_at,
+            deleted_at: None,
+            content_hash: Some(content_hash),
+        })
+    }
+
+    async fn update_clipboard(
+        &self,
+        local_clipboard: &FullClipboardDto,
+        remote_clipboard: &Clippy,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let new_name = create_clipboard_filename(
+            &remote_clipboard.id,
+            &local_clipboard.clipboard.star, // Use local star status
+            &local_clipboard.clipboard.encrypted,
+            &local_clipboard.clipboard.created_at,
+            None,
+            Some(&clipboard_content_hash(local_clipboard)),
+        );
+
+        printlog!(
+            "updating clipboard: {} from {} star: {} encrypted: {}",
+            remote_clipboard.id,
+            uuid_to_datetime(&remote_clipboard.id),
+            local_clipboard.clipboard.star,
+            local_clipboard.clipboard.encrypted
+        );
+
+        // Written under the new name first, the old file only goes once that worked
+        self.write_file(&new_name, serde_json::to_string(&local_clipboard)?)
+            .await?;
+
+        if new_name != remote_clipboard.provider_id {
+            tokio::fs::remove_file(self.path.join(&remote_clipboard.provider_id))
+                .await
+                .ok();
+        }
+
+        Ok(())
+    }
+
+    async fn star_clipboard(&self, clippy: &FullClipboardDto) {
+        star_remote_clipboard(self, clippy).await
+    }
+
+    async fn cleanup_old_clipboards(
+        &self,
+        remote_clipboards: &Vec<Clippy>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        cleanup_remote_clipboards(self, remote_clipboards).await
+    }
+
+    async fn upsert_settings(
+        &self,
+        settings: &HashMap<String, Value>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_file(
+            &format!("{}.json", BACKUP_SETTINGS_PREFIX),
+            serde_json::to_string(settings)?,
+        )
+        .await?;
+
+        printlog!("(remote) uploaded settings");
+
+        Ok(())
+    }
+
+    async fn get_settings(&self) -> Result<HashMap<String, Value>, Box<dyn std::error::Error>> {
+        match self
+            .read_file(&format!("{}.json", BACKUP_SETTINGS_PREFIX))
+            .await?
+        {
+            Some(content) => Ok(serde_json::from_str(&content)?),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// The folder has to exist and be writable, an unmounted share or a removed drive
+    /// must not look like an empty history.
+    async fn is_authenticated(&self) -> bool {
+        match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => metadata.is_dir() && !metadata.permissions().readonly(),
+            Err(e) => {
+                printlog!(
+                    "(remote) sync folder {} not available: {}",
+                    self.path.display(),
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    async fn read_document(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        self.read_file(name).await
+    }
+
+    async fn write_document(
+        &self,
+        name: &str,
+        content: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_file(name, content).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::providers::tests::{round_trip, text_clipboard};
+    use common::{
+        constants::TOMBSTONE_DOCUMENT_NAME,
+        types::sync::{Tombstone, TombstoneDocument},
+    };
+    use std::collections::HashSet;
+
+    /// Sync folder in the temp directory, removed again when dropped.
+    struct TestFolder(LocalFolderProviderImpl);
+
+    impl TestFolder {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("clippy-sync-test-{}", Uuid::now_v7()));
+            std::fs::create_dir_all(&path).expect("Failed to create sync folder");
+            Self(LocalFolderProviderImpl::new(path))
+        }
+
+        fn file_names(&self) -> Vec<String> {
+            std::fs::read_dir(self.0.path())
+                .expect("Failed to read sync folder")
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .collect()
+        }
+    }
+
+    impl Drop for TestFolder {
+        fn drop(&mut self) {
+            std::fs::remove_dir_all(self.0.path()).ok();
+        }
+    }
+
+    #[tokio::test]
+    async fn passes_the_provider_round_trip() {
+        round_trip(&TestFolder::new().0).await;
+    }
+
+    #[tokio::test]
+    async fn uploads_and_downloads_clipboards() {
+        let folder = TestFolder::new();
+        let clipboard = text_clipboard("hello");
+
+        let uploaded = folder.0.upload_clipboard(&clipboard).await.unwrap();
+        let remote = folder.0.fetch_all_clipboards().await.unwrap();
+
+        assert_eq!(remote.len(), 1);
+        assert_eq!(remote[0].id, clipboard.clipboard.id);
+        assert_eq!(remote[0].provider_id, uploaded.provider_id);
+        assert_eq!(
+            uploaded.content_hash.as_deref(),
+            Some(clipboard_content_hash(&clipboard).as_str())
+        );
+        let downloaded = folder
+            .0
+            .download_by_id(&uploaded.provider_id)
+            .await
+            .unwrap();
+        assert_eq!(downloaded.clipboard, clipboard.clipboard);
+        assert_eq!(downloaded.text, clipboard.text);
+        // Nothing half written stays behind
+        assert_eq!(folder.file_names(), vec![uploaded.provider_id]);
+    }
+
+    #[tokio::test]
+    async fn update_renames_the_file() {
+        let folder = TestFolder::new();
+        let mut clipboard = text_clipboard("hello");
+        let uploaded = folder.0.upload_clipboard(&clipboard).await.unwrap();
+
+        clipboard.clipboard.star = true;
+        clipboard.clipboard.name = Some("greeting".to_string());
+        folder
+            .0
+            .update_clipboard(&clipboard, &uploaded)
+            .await
+            .unwrap();
+
+        let remote = folder.0.fetch_all_clipboards().await.unwrap();
+        assert_eq!(remote.len(), 1);
+        assert!(remote[0].star);
+        assert_ne!(remote[0].provider_id, uploaded.provider_id);
+        assert_eq!(
+            remote[0].content_hash.as_deref(),
+            Some(clipboard_content_hash(&clipboard).as_str())
+        );
+        assert_eq!(folder.file_names(), vec![remote[0].provider_id.clone()]);
+
+        // Another device sees the rename on its next fetch
+        let other_device = LocalFolderProviderImpl::new(folder.0.path().clone());
+        let downloaded = other_device
+            .download_by_id(&other_device.fetch_all_clipboards().await.unwrap()[0].provider_id)
+            .await
+            .unwrap();
+        assert_eq!(downloaded.clipboard, clipboard.clipboard);
+    }
+
+    #[tokio::test]
+    async fn marks_clipboards_for_deletion() {
+        let folder = TestFolder::new();
+        let uploaded = folder
+            .0
+            .upload_clipboard(&text_clipboard("hello"))
+            .await
+            .unwrap();
+
+        folder.0.mark_for_deletion(&uploaded).await;
+
+        let remote = folder.0.fetch_all_clipboards().await.unwrap();
+        assert_eq!(remote.len(), 1);
+        assert_eq!(remote[0].id, uploaded.id);
+        assert!(remote[0].deleted_at.is_some());
+        assert_eq!(remote[0].content_hash, uploaded.content_hash);
+
+        folder.0.delete_clipboard(&remote[0]).await;
+        assert!(folder.0.fetch_all_clipboards().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn round_trips_the_tombstone_document() {
+        let folder = TestFolder::new();
+        assert_eq!(
+            folder
+                .0
+                .read_document(TOMBSTONE_DOCUMENT_NAME)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let mut tombstones = TombstoneDocument::default();
+        tombstones.tombstones.insert(
+            Uuid::now_v7(),
+            Tombstone {
+                deleted_at: Utc::now().naive_utc(),
+                device_id: "laptop".to_string(),
+                acked_by: HashSet::from(["laptop".to_string(), "desktop".to_string()]),
+            },
+        );
+        folder
+            .0
+            .write_document(
+                TOMBSTONE_DOCUMENT_NAME,
+                serde_json::to_string(&tombstones).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let content = folder
+            .0
+            .read_document(TOMBSTONE_DOCUMENT_NAME)
+            .await
+            .unwrap()
+            .expect("Tombstone document missing");
+        assert_eq!(
+            serde_json::from_str::<TombstoneDocument>(&content).unwrap(),
+            tombstones
+        );
+        // Not a clipboard, fetching must not pick it up
+        assert!(folder.0.fetch_all_clipboards().await.unwrap().is_empty());
+    }
+}
@@ -1,10 +1,22 @@
+use crate::prelude::*;
+use crate::service::settings::get_global_settings;
 use chrono::{DateTime, NaiveDateTime};
 use common::{
-    constants::{BACKDUP_DATE_FORMAT, BACKUP_FILE_PREFIX},
-    types::sync::Clippy,
+    constants::{APP_IDENTIFIER, BACKDUP_DATE_FORMAT, BACKUP_FILE_PREFIX},
+    types::{
+        orm_query::FullClipboardDto,
+        sync::{Clippy, SyncProvider},
+        types::CommandError,
+    },
 };
+use ring::digest;
 use sea_orm::prelude::Uuid;
+use std::collections::HashSet;
 pub mod google_drive;
+pub mod lan;
+pub mod local_folder;
+pub mod s3;
+pub mod webdav;
 
 pub fn uuid_to_datetime(uuid: &Uuid) -> NaiveDateTime {
     let ts = uuid.get_timestamp().expect("Not a time-based UUID");
@@ -14,13 +26,37 @@ pub fn uuid_to_datetime(uuid: &Uuid) -> NaiveDateTime {
         .naive_utc()
 }
 
+/// Short hash of the uploaded JSON, it goes into the file name so a changed clipboard
+/// (renamed, re-encrypted, ...) shows up in a plain listing.
+pub fn clipboard_content_hash(clipboard: &FullClipboardDto) -> String {
+    let json = serde_json::to_vec(clipboard).expect("Failed to serialize clipboard");
+    digest::digest(&digest::SHA256, &json).as_ref()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
 pub fn parse_clipboard_info(filename: &str, provider_id: &String) -> Option<Clippy> {
-    let [_, uuid, star, encrypted, created_at, deleted_at]: [&str; 6] = filename
+    let parts = filename
         .trim_end_matches(".json")
         .split('_')
-        .collect::<Vec<_>>()
-        .try_into()
-        .ok()?;
+        .collect::<Vec<_>>();
+
+    // Files from before the content hash have one part less
+    let (uuid, star, encrypted, created_at, deleted_at, content_hash) = match parts[..] {
+        [_, uuid, star, encrypted, created_at, deleted_at] => {
+            (uuid, star, encrypted, created_at, deleted_at, None)
+        }
+        [_, uuid, star, encrypted, created_at, deleted_at, hash] => (
+            uuid,
+            star,
+            encrypted,
+            created_at,
+            deleted_at,
+            Some(hash.to_string()),
+        ),
+        _ => return None,
+    };
 
     let id = Uuid::parse_str(uuid).ok()?;
     let starred = star.parse().ok()?;
@@ -39,6 +75,7 @@ pub fn parse_clipboard_info(filename: &str, provider_id: &String) -> Option<Clip
         created_at,
         deleted_at,
         provider_id: provider_id.clone(),
+        content_hash,
     })
 }
 
@@ -48,9 +85,10 @@ pub fn create_clipboard_filename(
     encrypted: &bool,
     created_at: &NaiveDateTime,
     deleted_at: Option<NaiveDateTime>,
+    content_hash: Option<&str>,
 ) -> String {
     format!(
-        "{}_{}_{}_{}_{}_{}.json",
+        "{}_{}_{}_{}_{}_{}{}.json",
         BACKUP_FILE_PREFIX,
         id,
         starred,
@@ -58,6 +96,267 @@ pub fn create_clipboard_filename(
         created_at.format(BACKDUP_DATE_FORMAT),
         deleted_at
             .map(|date| date.format(BACKDUP_DATE_FORMAT).to_string())
-            .unwrap_or_else(|| "None".to_string())
+            .unwrap_or_else(|| "None".to_string()),
+        content_hash
+            .map(|hash| format!("_{}", hash))
+            .unwrap_or_default()
     )
 }
+
+/// Builds the remote list from `(file name, provider id)` pairs, newest first. Files
+/// that aren't clipboards are skipped, duplicates of an id are deleted remotely.
+pub async fn collect_remote_clipboards(
+    provider: &dyn SyncProvider,
+    files: impl IntoIterator<Item = (String, String)>,
+) -> Vec<Clippy> {
+    let mut clipboards = Vec::new();
+    let mut seen_ids = HashSet::new();
+
+    for (name, provider_id) in files {
+        if let Some(remote) = parse_clipboard_info(&name, &provider_id) {
+            // Only add if we haven't seen this ID before
+            if seen_ids.insert(remote.id) {
+                clipboards.push(remote);
+            } else {
+                printlog!(
+                    "Found duplicate clipboard: {} from {}, deleting duplicate",
+                    remote.id,
+                    uuid_to_datetime(&remote.id)
+                );
+                // Delete the duplicate file
+                provider.delete_clipboard(&remote).await;
+            }
+        }
+    }
+
+    // Sort by created_at
+    clipboards.sort_by(|a, b| b.id.cmp(&a.id));
+
+    if !clipboards.is_empty() {
+        let newest = &clipboards[0];
+        let oldest = &clipboards[clipboards.len() - 1];
+        printlog!(
+            "(remote) found {} clipboards from {} to {}",
+            clipboards.len(),
+            uuid_to_datetime(&oldest.id),
+            uuid_to_datetime(&newest.id)
+        );
+    } else {
+        printlog!("(remote) found no clipboards");
+    }
+
+    clipboards
+}
+
+/// Deletes the oldest unstarred remote clipboards beyond the sync limit.
+pub async fn cleanup_remote_clipboards(
+    provider: &dyn SyncProvider,
+    remote_clipboards: &[Clippy],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sync_limit = get_global_settings().sync_limit as usize;
+
+    // Get all non-starred clipboards
+    let mut all_clipboards: Vec<_> = remote_clipboards.iter().filter(|clip| !clip.star).collect();
+
+    // Sort by creation date
+    all_clipboards.sort_by(|a, b| a.id.cmp(&b.id));
+
+    // Find all marked-for-deletion indices
+    let marked_indices: Vec<usize> = all_clipboards
+        .iter()
+        .enumerate()
+        .filter(|(_, clip)| clip.deleted_at.is_some())
+        .map(|(idx, _)| idx)
+        .collect();
+
+    // Only delete if we have clipboards beyond the sync limit
+    if all_clipboards.len() <= sync_limit {
+        return Ok(());
+    }
+
+    if let Some(&last_marked_idx) = marked_indices.last() {
+        // Calculate how many clipboards we need to delete
+
+        let total_to_delete = all_clipboards.len() - sync_limit + marked_indices.len();
+
+        printlog!(
+            "total clipboards: {}, marked clipboards: {}, total to delete: {} (last marked: {})",
+            all_clipboards.len(),
+            marked_indices.len(),
+            total_to_delete,
+            last_marked_idx
+        );
+        // Only delete if we can remove everything up to and including the last marked clipboard
+        if total_to_delete > last_marked_idx {
+            // Delete oldest clipboards including the marked ones
+            for clippy in all_clipboards.iter().take(total_to_delete) {
+                printlog!(
+                    "deleting clipboard: {} from {} (marked: {})",
+                    clippy.id,
+                    uuid_to_datetime(&clippy.id),
+                    clippy.deleted_at.is_some()
+                );
+                provider.delete_clipboard(clippy).await;
+            }
+        }
+    } else {
+        // No marked clipboards - normal cleanup
+        let to_delete = all_clipboards.len() - sync_limit;
+        for clippy in all_clipboards.iter().take(to_delete) {
+            printlog!(
+                "deleting clipboard: {} from {}",
+                clippy.id,
+                uuid_to_datetime(&clippy.id),
+            );
+            provider.delete_clipboard(clippy).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Updates the remote copy of a (un)starred clipboard, uploads it if it's missing.
+pub async fn star_remote_clipboard(provider: &dyn SyncProvider, clippy: &FullClipboardDto) {
+    let clipboards = provider
+        .fetch_all_clipboards()
+        .await
+        .expect("Failed to star fetch clipboards");
+
+    let remote_clipboards = clipboards
+        .iter()
+        .find(|clip| clip.id == clippy.clipboard.id);
+
+    if let Some(remote_clipboard) = remote_clipboards {
+        printlog!(
+            "starring clipboard: {} from {} star: {} encrypted: {}",
+            remote_clipboard.id,
+            uuid_to_datetime(&remote_clipboard.id),
+            clippy.clipboard.star,
+            clippy.clipboard.encrypted
+        );
+
+        if let Err(e) = provider.update_clipboard(clippy, remote_clipboard).await {
+            printlog!(
+                "(remote) failed to star clipboard {}: {}",
+                clippy.clipboard.id,
+                e
+            );
+        }
+    } else if let Err(e) = provider.upload_clipboard(clippy).await {
+        printlog!(
+            "(remote) failed to star clipboard {}: {}",
+            clippy.clipboard.id,
+            e
+        );
+    }
+}
+
+/// Secrets of a sync provider (passwords, access keys) live in the OS keychain,
+/// never in the database or the synced settings.
+pub fn get_credential(name: &str) -> Result<Option<String>, CommandError> {
+    match credential_entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(CommandError::Error(e.to_string())),
+    }
+}
+
+pub fn set_credential(name: &str, secret: &str) -> Result<(), CommandError> {
+    credential_entry(name)?
+        .set_password(secret)
+        .map_err(|e| CommandError::Error(e.to_string()))
+}
+
+fn credential_entry(name: &str) -> Result<keyring::Entry, CommandError> {
+    keyring::Entry::new(APP_IDENTIFIER, name).map_err(|e| CommandError::Error(e.to_string()))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::Utc;
+    use common::constants::TOMBSTONE_DOCUMENT_NAME;
+    use entity::{clipboard, clipboard_text};
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    pub fn text_clipboard(text: &str) -> FullClipboardDto {
+        let id = Uuid::now_v7();
+        let now = Utc::now().naive_utc();
+
+        FullClipboardDto {
+            clipboard: clipboard::Model {
+                id,
+                types: serde_json::json!(["text"]),
+                created_at: now,
+                updated_at: now,
+                ..Default::default()
+            },
+            text: Some(clipboard_text::Model {
+                id: Uuid::now_v7(),
+                clipboard_id: id,
+                r#type: "text".to_string(),
+                data: text.to_string(),
+            }),
+            html: None,
+            image: None,
+            rtf: None,
+            files: Vec::new(),
+            search_match: None,
+        }
+    }
+
+    /// Goes through every call the sync makes on an empty `provider` and removes what it
+    /// uploaded again.
+    pub async fn round_trip(provider: &dyn SyncProvider) {
+        assert!(provider.is_authenticated().await);
+
+        let settings = HashMap::from([("sync_limit".to_string(), Value::from(3))]);
+        provider.upsert_settings(&settings).await.unwrap();
+        assert_eq!(provider.get_settings().await.unwrap(), settings);
+
+        let mut first = text_clipboard("first");
+        let second = text_clipboard("second");
+        let uploaded = provider.upload_clipboard(&first).await.unwrap();
+        provider.upload_clipboard(&second).await.unwrap();
+
+        let remote = provider.fetch_all_clipboards().await.unwrap();
+        assert_eq!(
+            remote.iter().map(|clippy| clippy.id).collect::<Vec<_>>(),
+            vec![second.clipboard.id, first.clipboard.id]
+        );
+        let downloaded = provider
+            .download_by_id(&uploaded.provider_id)
+            .await
+            .unwrap();
+        assert_eq!(downloaded.clipboard, first.clipboard);
+        assert_eq!(downloaded.text, first.text);
+
+        first.clipboard.star = true;
+        provider.update_clipboard(&first, &uploaded).await.unwrap();
+        provider.mark_for_deletion(&remote[0]).await;
+
+        let remote = provider.fetch_all_clipboards().await.unwrap();
+        assert_eq!(remote.len(), 2);
+        assert!(remote[0].deleted_at.is_some());
+        assert!(remote[1].star);
+        assert_ne!(remote[1].provider_id, uploaded.provider_id);
+
+        provider
+            .write_document(TOMBSTONE_DOCUMENT_NAME, "{}".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            provider
+                .read_document(TOMBSTONE_DOCUMENT_NAME)
+                .await
+                .unwrap(),
+            Some("{}".to_string())
+        );
+
+        for clippy in &remote {
+            provider.delete_clipboard(clippy).await;
+        }
+        assert!(provider.fetch_all_clipboards().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+use super::{
+    cleanup_remote_clipboards, clipboard_content_hash, collect_remote_clipboards,
+    create_clipboard_filename, get_credential, star_remote_clipboard, uuid_to_datetime,
+};
+use crate::prelude::*;
+use crate::service::settings::get_global_settings;
+use chrono::{NaiveDateTime, Utc};
+use common::{
+    constants::{BACKUP_FILE_PREFIX, BACKUP_SETTINGS_PREFIX, WEBDAV_CREDENTIAL},
+    types::{
+        orm_query::FullClipboardDto,
+        sync::{Clippy, SyncProvider},
+        types::CommandError,
+    },
+};
+use migration::async_trait;
+use regex::Regex;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use sea_orm::prelude::Uuid;
+use serde_json::Value;
+use std::{collections::HashMap, sync::LazyLock, time::Duration};
+
+const REQUEST_TIMEOUT_SECS: u64 = 60;
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/></d:prop></d:propfind>"#;
+
+/// `<d:href>`, `<D:href>`, `<href>`, ... servers pick their own namespace prefix
+static RE_HREF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)<(?:[a-z0-9_-]+:)?href[^>]*>([^<]+)</(?:[a-z0-9_-]+:)?href>").unwrap()
+});
+
+/// Syncs into a folder on a WebDAV server (Nextcloud, ownCloud, `rclone serve webdav`,
+/// ...). Files are named like on Google Drive, the file name is the provider id.
+pub struct WebDavProviderImpl {
+    client: reqwest::Client,
+    /// Folder url, always ends with a slash
+    url: String,
+    username: String,
+    password: String,
+}
+
+impl WebDavProviderImpl {
+    pub fn new(url: &str, username: &str, password: &str) -> Result<Self, CommandError> {
+        let url = url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(CommandError::new(
+                "WebDAV url must start with http:// or https://",
+            ));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| CommandError::Error(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            url: format!("{}/", url.trim_end_matches('/')),
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    /// Provider for the url/username in the settings and the password in the keychain.
+    pub fn from_settings() -> Result<Self, CommandError> {
+        let settings = get_global_settings();
+        if settings.webdav_url.is_empty() {
+            return Err(CommandError::new("WebDAV sync is not configured"));
+        }
+
+        let password = get_credential(WEBDAV_CREDENTIAL)?.unwrap_or_default();
+
+        Self::new(&settings.webdav_url, &settings.webdav_username, &password)
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn request(&self, method: Method, name: &str) -> RequestBuilder {
+        let request = self.client.request(method, self.file_url(name));
+
+        if self.username.is_empty() {
+            request
+        } else {
+            request.basic_auth(&self.username, Some(&self.password))
+        }
+    }
+
+    fn file_url(&self, name: &str) -> String {
+        format!("{}{}", self.url, urlencoding::encode(name))
+    }
+
+    /// Creates the folder if it doesn't exist yet, fails on wrong credentials.
+    async fn ensure_folder(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self
+            .request(dav_method("PROPFIND"), "")
+            .header("Depth", "0")
+            .body(PROPFIND_BODY)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::MULTI_STATUS | StatusCode::OK => Ok(()),
+            StatusCode::NOT_FOUND => {
+                self.request(dav_method("MKCOL"), "")
+                    .send()
+                    .await?
+                    .error_for_status()?;
+
+                printlog!("(remote) created webdav folder {}", self.url);
+                Ok(())
+            }
+            status => Err(format!("WebDAV server answered {}", status).into()),
+        }
+    }
+
+    /// Names of all files in the folder.
+    async fn list_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let response = self
+            .request(dav_method("PROPFIND"), "")
+            .header("Depth", "1")
+            .body(PROPFIND_BODY)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let body = response.text().await?;
+
+        Ok(RE_HREF
+            .captures_iter(&body)
+            .filter_map(|captures| {
+                let href = captures.get(1)?.as_str().trim().trim_end_matches('/');
+                let name = href.rsplit('/').next()?;
+                urlencoding::decode(name).ok().map(|name| name.into_owned())
+            })
+            .filter(|name| name.starts_with(BACKUP_FILE_PREFIX) && name.ends_with(".json"))
+            .collect())
+    }
+
+    /// Returns `None` if the file doesn't exist.
+    async fn get_file(&self, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let response = self.request(Method::GET, name).send().await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(response.error_for_status()?.text().await?))
+    }
+
+    async fn put_file(
+        &self,
+        name: &str,
+        content: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.request(Method::PUT, name)
+            .header("Content-Type", "application/json")
+            .body(content)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn move_file(&self, from: &str, to: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.request(dav_method("MOVE"), from)
+            .header("Destination", self.file_url(to))
+            .header("Overwrite", "T")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+fn dav_method(name: &str) -> Method {
+    Method::from_bytes(name.as_bytes()).expect("Invalid WebDAV method")
+}
+
+#[async_trait::async_trait]
+impl SyncProvider for WebDavProviderImpl {
+    async fn fetch_all_clipboards(&self) -> Result<Vec<Clippy>, Box<dyn std::error::Error>> {
+        let files = self
+            .list_files()
+            .await?
+            .into_iter()
+            .map(|name| (name.clone(), name));
+
+        Ok(collect_remote_clipboards(self, files).await)
+    }
+
+    async fn mark_for_deletion(&self, clippy: &Clippy) {
+        let new_name = create_clipboard_filename(
+            &clippy.id,
+            &clippy.star,
+            &clippy.encrypted,
+            &clippy.created_at,
+            Some(Utc::now().naive_utc()),
+            clippy.content_hash.as_deref(),
+        );
+
+        match self.move_file(&clippy.provider_id, &new_name).await {
+            Ok(()) => printlog!("(remote) marked clipboard for deletion: {}", clippy.id),
+            Err(e) => printlog!("(remote) failed to mark {} for deletion: {}", clippy.id, e),
+        }
+    }
+
+    async fn delete_clipboard(&self, clippy: &Clippy) {
+        self.request(Method::DELETE, &clippy.provider_id)
+            .send()
+            .await
+            .ok();
+    }
+
+    async fn download_by_id(
+        &self,
+        id: &String,
+    ) -> Result<FullClipboardDto, Box<dyn std::error::Error>> {
+        let content = self
+            .get_file(id)
+            .await?
+            .ok_or_else(|| format!("Clipboard file {} not found", id))?;
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    async fn upload_clipboard(
+        &self,
+        clipboard: &FullClipboardDto,
+    ) -> Result<Clippy, Box<dyn std::error::Error>> {
+        let content_hash = clipboard_content_hash(clipboard);
+        let file_name = create_clipboard_filename(
+            &clipboard.clipboard.id,
+            &clipboard.clipboard.star,
+            &clipboard.clipboard.encrypted,
+            &clipboard.clipboard.created_at,
+            None,
+            Some(&content_hash),
+        );
+
+        printlog!(
+            "uploading clipboard: {} from {} star: {} encrypted: {}",
+            clipboard.clipboard.id,
+            uuid_to_datetime(&clipboard.clipboard.id),
+            clipboard.clipboard.star,
+            clipboard.clipboard.encrypted
+        );
+
+        self.put_file(&file_name, serde_json::to_string(&clipboard)?)
+            .await?;
+
+        Ok(Clippy {
+            id: clipboard.clipboard.id,
+            provider_id: file_name,
+            star: clipboard.clipboard.star,
+            encrypted: clipboard.clipboard.encrypted,
+            created_at: clipboard.clipboard.created_at,
+            deleted_at: None,
+            content_hash: Some(content_hash),
+        })
+    }
+
+    async fn update_clipboard(
+        &self,
+        local_clipboard: &FullClipboardDto,
+        remote_clipboard: &Clippy,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let new_name = create_clipboard_filename(
+            &remote_clipboard.id,
+            &local_clipboard.clipboard.star, // Use local star status
+            &local_clipboard.clipboard.encrypted,
+            &local_clipboard.clipboard.created_at,
+            None,
+            Some(&clipboard_content_hash(local_clipboard)),
+        );
+
+        printlog!(
+            "updating clipboard: {} from {} star: {} encrypted: {}",
+            remote_clipboard.id,
+            uuid_to_datetime(&remote_clipboard.id),
+            local_clipboard.clipboard.star,
+            local_clipboard.clipboard.encrypted
+        );
+
+        // Content first, the rename is what other devices pick up
+        self.put_file(
+            &remote_clipboard.provider_id,
+            serde_json::to_string(&local_clipboard)?,
+        )
+        .await?;
+
+        if new_name != remote_clipboard.provider_id {
+            self.move_file(&remote_clipboard.provider_id, &new_name)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn star_clipboard(&self, clippy: &FullClipboardDto) {
+        star_remote_clipboard(self, clippy).await
+    }
+
+    async fn cleanup_old_clipboards(
+        &self,
+        remote_clipboards: &Vec<Clippy>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        cleanup_remote_clipboards(self, remote_clipboards).await
+    }
+
+    async fn upsert_settings(
+        &self,
+        settings: &HashMap<String, Value>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.put_file(
+            &format!("{}.json", BACKUP_SETTINGS_PREFIX),
+            serde_json::to_string(settings)?,
+        )
+        .await?;
+
+        printlog!("(remote) uploaded settings");
+
+        Ok(())
+    }
+
+    async fn get_settings(&self) -> Result<HashMap<String, Value>, Box<dyn std::error::Error>> {
+        match self
+            .get_file(&format!("{}.json", BACKUP_SETTINGS_PREFIX))
+            .await?
+        {
+            Some(content) => Ok(serde_json::from_str(&content)?),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    async fn is_authenticated(&self) -> bool {
+        match self.ensure_folder().await {
+            Ok(()) => true,
+            Err(e) => {
+                printlog!("(remote) webdav not reachable: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn read_document(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        self.get_file(name).await
+    }
+
+    async fn write_document(
+        &self,
+        name: &str,
+        content: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.put_file(name, content).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::providers::tests::round_trip;
+
+    /// Against a real server, e.g. `rclone serve webdav --user u --pass p /tmp/dav`:
+    /// `CLIPPY_TEST_WEBDAV_URL=http://localhost:8080 CLIPPY_TEST_WEBDAV_USERNAME=u
+    /// CLIPPY_TEST_WEBDAV_PASSWORD=p cargo test webdav -- --ignored`
+    #[tokio::test]
+    #[ignore = "needs a WebDAV server, see CLIPPY_TEST_WEBDAV_URL"]
+    async fn syncs_with_a_webdav_server() {
+        let env = |name: &str| std::env::var(name).unwrap_or_default();
+        let url = env("CLIPPY_TEST_WEBDAV_URL");
+        assert!(!url.is_empty(), "CLIPPY_TEST_WEBDAV_URL is not set");
+
+        // A folder of its own, created by the provider and removed again afterwards
+        let provider = WebDavProviderImpl::new(
+            &format!(
+                "{}/clippy-test-{}",
+                url.trim_end_matches('/'),
+                Uuid::now_v7()
+            ),
+            &env("CLIPPY_TEST_WEBDAV_USERNAME"),
+            &env("CLIPPY_TEST_WEBDAV_PASSWORD"),
+        )
+        .unwrap();
+
+        round_trip(&provider).await;
+
+        provider
+            .request(dav_method("DELETE"), "")
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+}
@@ -1,13 +1,82 @@
 use crate::prelude::*;
-use crate::service::clipboard::{delete_clipboards_db, new_clipboard_event};
-use crate::service::settings::update_settings_from_sync;
+use crate::service::clipboard::{
+    delete_clipboards_db, get_clipboard_db, init_clipboards, new_clipboard_event,
+};
+use crate::service::settings::{
+    get_global_settings, update_settings_from_sync, update_settings_synchronize_db,
+};
 use crate::service::{
-    clipboard::{get_clipboard_uuids_db, get_latest_syncable_cliboards_db, upsert_clipboard_dto},
-    sync::get_sync_provider,
+    clipboard::{get_clipboard_models_db, get_latest_syncable_cliboards_db, upsert_clipboard_dto},
+    sync::{
+        current_sync_device, delete_sync_states_db, delete_tombstones_db, get_sync_manager,
+        get_sync_provider, get_sync_states_db, get_tombstones_db, insert_sync_conflict_db,
+        merge_sync_devices, publish_tombstones_db, read_sync_devices, upsert_sync_state_db,
+    },
+};
+use crate::tao::{
+    config::{get_data_path, get_device_id},
+    global::get_app,
+    tao_constants::SYNC_PUSH_QUEUE,
 };
+use crate::utils::providers::{clipboard_content_hash, uuid_to_datetime};
+use chrono::{NaiveDateTime, Utc};
+use common::constants::{SYNC_CURSOR_NAME, TOMBSTONE_DOCUMENT_NAME};
+use common::types::{
+    enums::{ListenEvent, SyncConflictField, SyncConflictWinner},
+    orm_query::FullClipboardDto,
+    sync::{Clippy, SyncProvider, SyncRule, Tombstone, TombstoneDocument},
+    types::{CommandError, Progress},
+};
+use entity::{clipboard, sync_state};
 use sea_orm::prelude::Uuid;
-use std::time::Duration;
-use tokio::{task::JoinHandle, time};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::Duration,
+};
+use tauri::{Emitter, EventTarget};
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{self, Instant},
+};
+
+/// How often a change feed is asked for news
+const CHANGES_INTERVAL: Duration = Duration::from_secs(10);
+/// Full sync even with a change feed, catches anything the feed missed
+const FALLBACK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+/// How outdated this device's last seen in the device manifest may get before it is
+/// written again
+const DEVICE_SEEN_INTERVAL: chrono::TimeDelta = chrono::TimeDelta::hours(1);
+
+/// Full sync interval for providers without a change feed
+fn poll_interval() -> Duration {
+    if cfg!(debug_assertions) {
+        Duration::from_secs(10)
+    } else {
+        Duration::from_secs(30)
+    }
+}
+
+/// Queues a new clipboard for upload, a no-op while sync isn't running.
+pub fn push_clipboard(id: Uuid) {
+    if let Some(sender) = SYNC_PUSH_QUEUE
+        .lock()
+        .expect("Failed to lock sync push queue")
+        .as_ref()
+    {
+        let _ = sender.send(id);
+    }
+}
+
+/// Remote clipboards the sync rules turned away, by id and content hash, so they aren't
+/// downloaded again on every sync. Forgotten when the rules change.
+#[derive(Default)]
+struct SkippedClipboards {
+    rules: serde_json::Value,
+    ids: HashSet<(Uuid, Option<String>)>,
+}
 
 pub struct SyncManager {
     job_handle: Option<JoinHandle<()>>,
@@ -22,43 +91,484 @@ impl SyncManager {
         }
     }
 
-    async fn sync_job() -> Result<(), Box<dyn std::error::Error>> {
-        let provider = get_sync_provider().await;
-        if provider.is_authenticated().await {
-            let settings = provider.get_settings().await?;
-            update_settings_from_sync(settings).await?;
+    /// Full sync against the sync state: downloads what changed remotely, updates what
+    /// changed locally (star, name, encryption), merges what changed on both sides and
+    /// uploads what the remote never had. Deletions go both ways through the provider's
+    /// tombstone document. Returns the ids the remote has afterwards.
+    async fn sync_job(
+        skipped: &mut SkippedClipboards,
+    ) -> Result<HashSet<Uuid>, Box<dyn std::error::Error>> {
+        let provider = get_sync_provider()
+            .await
+            .map_err(|CommandError::Error(e)| e)?;
+        if !provider.is_authenticated().await {
+            return Err("Sync provider is not authenticated".into());
+        }
+
+        let settings = provider.get_settings().await?;
+        update_settings_from_sync(settings).await?;
 
-            let local_clipboards = get_clipboard_uuids_db().await?;
-            let mut remote_clipboards = provider.fetch_all_clipboards().await?;
+        let device_id = get_device_id();
+        let devices = Self::device_job(provider.as_ref(), &device_id).await?;
 
-            let deleted_clipboards: Vec<Uuid> = remote_clipboards
-                .iter()
-                .filter(|clipboard| clipboard.deleted_at.is_some())
-                .map(|clipboard| clipboard.id)
-                .collect();
+        let local_clipboards = get_clipboard_models_db().await?;
+        let mut remote_clipboards = provider.fetch_all_clipboards().await?;
+        let states = get_sync_states_db().await?;
+
+        let mut tombstones: TombstoneDocument =
+            match provider.read_document(TOMBSTONE_DOCUMENT_NAME).await? {
+                Some(content) => serde_json::from_str(&content)?,
+                None => TombstoneDocument::default(),
+            };
+        let original_tombstones = tombstones.clone();
+        let local_tombstones = get_tombstones_db().await?;
+
+        // Deleted on this device since the last sync
+        for tombstone in local_tombstones
+            .iter()
+            .filter(|tombstone| !tombstone.published)
+        {
+            tombstones
+                .tombstones
+                .entry(tombstone.clipboard_id)
+                .or_insert_with(|| Tombstone {
+                    deleted_at: tombstone.deleted_at,
+                    device_id: device_id.clone(),
+                    acked_by: HashSet::new(),
+                });
+        }
+
+        // Files marked by devices from before tombstones count as deletions too
+        let mut deletions: HashMap<Uuid, NaiveDateTime> = tombstones
+            .tombstones
+            .iter()
+            .map(|(id, tombstone)| (*id, tombstone.deleted_at))
+            .collect();
+        for remote in &remote_clipboards {
+            if let Some(deleted_at) = remote.deleted_at {
+                deletions.entry(remote.id).or_insert(deleted_at);
+            }
+        }
+
+        let mut restored_clipboards = HashSet::new();
+        for (&id, &deleted_at) in &deletions {
+            // Deleted on another device but edited here since the last sync, newer one wins
+            let (Some(local), Some(state)) = (local_clipboards.get(&id), states.get(&id)) else {
+                continue;
+            };
+            if !local_changed(local, state) {
+                continue;
+            }
+
+            let local_wins = local.updated_at > deleted_at;
+            insert_sync_conflict_db(
+                id,
+                SyncConflictField::Deleted,
+                Some(local.updated_at.to_string()),
+                Some(deleted_at.to_string()),
+                if local_wins {
+                    SyncConflictWinner::Local
+                } else {
+                    SyncConflictWinner::Remote
+                },
+            )
+            .await?;
+
+            if !local_wins {
+                continue;
+            }
+
+            // Written without a deletion date, which brings it back remotely
+            let clipboard = get_clipboard_db(id).await?;
+            match remote_clipboards.iter().position(|remote| remote.id == id) {
+                Some(index) => {
+                    provider
+                        .update_clipboard(&clipboard, &remote_clipboards[index])
+                        .await?;
+
+                    let content_hash = clipboard_content_hash(&clipboard);
+                    let remote = &mut remote_clipboards[index];
+                    upsert_sync_state_db(&clipboard.clipboard, &remote.provider_id, &content_hash)
+                        .await?;
+
+                    // Not marked anymore, cleanup must not take it with the marked ones
+                    remote.deleted_at = None;
+                }
+                None => {
+                    let remote = provider.upload_clipboard(&clipboard).await?;
+                    let content_hash = remote.content_hash.clone().unwrap_or_default();
+                    upsert_sync_state_db(&clipboard.clipboard, &remote.provider_id, &content_hash)
+                        .await?;
+
+                    remote_clipboards.push(remote);
+                }
+            }
+
+            restored_clipboards.insert(id);
+        }
+
+        for id in &restored_clipboards {
+            deletions.remove(id);
+            tombstones.tombstones.remove(id);
+        }
+
+        let deleted_clipboards: Vec<Uuid> = deletions
+            .keys()
+            .filter(|id| local_clipboards.contains_key(id))
+            .copied()
+            .collect();
 
+        if !deleted_clipboards.is_empty() {
             delete_clipboards_db(deleted_clipboards, None)
                 .await
-                .expect("Error deleting clipboards");
+                .map_err(|CommandError::Error(e)| e)?;
+            init_clipboards();
+        }
 
-            let new_clipboards = provider
-                .compare_and_fetch_new_clipboards(&local_clipboards, &remote_clipboards)
-                .await?;
+        // Marked as well for devices from before tombstones, cleanup removes the files
+        for remote in &remote_clipboards {
+            if remote.deleted_at.is_none() && deletions.contains_key(&remote.id) {
+                provider.mark_for_deletion(remote).await;
+            }
+        }
+
+        // Existing clipboards that changed reload the list instead of showing up as new
+        let mut updated = false;
+        let mut downloads = Vec::new();
+        for remote in remote_clipboards.iter().filter(|clipboard| {
+            !deletions.contains_key(&clipboard.id) && !restored_clipboards.contains(&clipboard.id)
+        }) {
+            let Some(local) = local_clipboards.get(&remote.id) else {
+                // Synced before and gone locally means it was deleted on this device, unless
+                // another device changed it since, then the change wins over the deletion
+                match states.get(&remote.id) {
+                    Some(state) if remote_changed(remote, state) => {
+                        insert_sync_conflict_db(
+                            remote.id,
+                            SyncConflictField::Deleted,
+                            None,
+                            remote.content_hash.clone(),
+                            SyncConflictWinner::Remote,
+                        )
+                        .await?;
+                        downloads.push(remote);
+                    }
+                    Some(_) => provider.mark_for_deletion(remote).await,
+                    None => downloads.push(remote),
+                }
+                continue;
+            };
 
-            for clipboard in new_clipboards {
-                upsert_clipboard_dto(clipboard.clone()).await?;
+            let Some(state) = states.get(&remote.id) else {
+                // Never compared (first sync, new provider), the star decides like it used to
+                if local.star != remote.star {
+                    downloads.push(remote);
+                } else {
+                    let content_hash = remote.content_hash.as_deref().unwrap_or_default();
+                    upsert_sync_state_db(local, &remote.provider_id, content_hash).await?;
+                }
+                continue;
+            };
+
+            match (remote_changed(remote, state), local_changed(local, state)) {
+                (false, false) => {
+                    if remote.provider_id != state.provider_id {
+                        upsert_sync_state_db(local, &remote.provider_id, &state.content_hash)
+                            .await?;
+                    }
+                }
+                (true, false) => downloads.push(remote),
+                (false, true) => {
+                    let clipboard = get_clipboard_db(remote.id).await?;
+                    provider.update_clipboard(&clipboard, remote).await?;
+
+                    let content_hash = clipboard_content_hash(&clipboard);
+                    upsert_sync_state_db(&clipboard.clipboard, &remote.provider_id, &content_hash)
+                        .await?;
+                }
+                (true, true) => {
+                    let clipboard = get_clipboard_db(remote.id).await?;
+                    let content_hash = clipboard_content_hash(&clipboard);
+
+                    // Changed on both sides but ended up the same
+                    if remote.content_hash.as_deref() == Some(content_hash.as_str()) {
+                        upsert_sync_state_db(
+                            &clipboard.clipboard,
+                            &remote.provider_id,
+                            &content_hash,
+                        )
+                        .await?;
+                        continue;
+                    }
+
+                    let remote_clipboard = provider.download_by_id(&remote.provider_id).await?;
+                    let merged = merge_clipboards(&clipboard, &remote_clipboard, state).await?;
+                    let merged_hash = clipboard_content_hash(&merged);
+
+                    if merged_hash != content_hash {
+                        upsert_clipboard_dto(merged.clone()).await?;
+                        updated = true;
+                    }
+                    if remote.content_hash.as_deref() != Some(merged_hash.as_str()) {
+                        provider.update_clipboard(&merged, remote).await?;
+                    }
+
+                    upsert_sync_state_db(&merged.clipboard, &remote.provider_id, &merged_hash)
+                        .await?;
+                }
+            }
+        }
+
+        let sync_rules = get_global_settings().sync_rules;
+        if skipped.rules != sync_rules {
+            *skipped = SkippedClipboards {
+                rules: sync_rules,
+                ids: HashSet::new(),
+            };
+        }
+        let rules = SyncRule::from_json_value(&skipped.rules);
+        downloads.retain(|remote| {
+            !skipped
+                .ids
+                .contains(&(remote.id, remote.content_hash.clone()))
+        });
+
+        let total = downloads.len();
+        for (index, remote) in downloads.into_iter().enumerate() {
+            printlog!(
+                "downloading clipboard: {} from {} star: {} encrypted: {}",
+                remote.id,
+                uuid_to_datetime(&remote.id),
+                remote.star,
+                remote.encrypted
+            );
+
+            get_app().emit_to(
+                EventTarget::any(),
+                ListenEvent::Progress.to_string().as_str(),
+                Progress {
+                    label: "SETTINGS.ENCRYPT.DOWNLOADING_REMOTE_CLIPBOARDS".to_string(),
+                    total,
+                    current: index + 1,
+                    ..Default::default()
+                },
+            )?;
+
+            let clipboard = provider.download_by_id(&remote.provider_id).await?;
+            if !SyncRule::allows(&rules, &clipboard) {
+                printlog!(
+                    "skipping clipboard {}, excluded by the sync rules",
+                    remote.id
+                );
+                skipped.ids.insert((remote.id, remote.content_hash.clone()));
+                continue;
+            }
+            upsert_clipboard_dto(clipboard.clone()).await?;
+
+            let content_hash = clipboard_content_hash(&clipboard);
+            upsert_sync_state_db(&clipboard.clipboard, &remote.provider_id, &content_hash).await?;
+
+            if local_clipboards.contains_key(&remote.id) {
+                updated = true;
+            } else {
                 new_clipboard_event(clipboard);
             }
+        }
+
+        if updated {
+            init_clipboards();
+        }
+
+        let mut remote_ids: HashSet<Uuid> = remote_clipboards
+            .iter()
+            .map(|clipboard| clipboard.id)
+            .collect();
+
+        for clipboard in get_latest_syncable_cliboards_db().await? {
+            // Synced before but gone remotely (cleaned up or deleted elsewhere), stays gone
+            let id = clipboard.clipboard.id;
+            if remote_ids.contains(&id) || states.contains_key(&id) {
+                continue;
+            }
+
+            let remote = provider.upload_clipboard(&clipboard).await?;
+            let content_hash = remote.content_hash.clone().unwrap_or_default();
+            upsert_sync_state_db(&clipboard.clipboard, &remote.provider_id, &content_hash).await?;
+
+            remote_ids.insert(id);
+            remote_clipboards.push(remote);
+        }
 
-            let new_local_clipboards = get_latest_syncable_cliboards_db().await?;
+        // States of clipboards that are gone on both sides or deleted remotely
+        let stale_states: Vec<Uuid> = states
+            .keys()
+            .filter(|id| {
+                deletions.contains_key(id)
+                    || (!local_clipboards.contains_key(id) && !remote_ids.contains(id))
+            })
+            .copied()
+            .collect();
+        delete_sync_states_db(stale_states).await?;
+
+        // Every tombstone in the document is applied on this device by now
+        let now = Utc::now().naive_utc();
+        for tombstone in tombstones.tombstones.values_mut() {
+            tombstone.acked_by.insert(device_id.clone());
+        }
+
+        // Past the TTL and acknowledged by every device, nothing can bring these back
+        let ttl = chrono::Duration::days(i64::from(get_global_settings().tombstone_ttl_days));
+        let expired: Vec<Uuid> = tombstones
+            .tombstones
+            .iter()
+            .filter(|(_, tombstone)| {
+                now - tombstone.deleted_at > ttl
+                    && devices
+                        .iter()
+                        .all(|device| tombstone.acked_by.contains(device))
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &expired {
+            tombstones.tombstones.remove(id);
+
+            if let Some(index) = remote_clipboards.iter().position(|remote| remote.id == *id) {
+                provider
+                    .delete_clipboard(&remote_clipboards.remove(index))
+                    .await;
+            }
+        }
 
-            let new_remote_clipboards = provider
-                .upload_new_clipboards(&new_local_clipboards, &remote_clipboards)
+        // Unchanged documents aren't written, every write wakes up the other devices
+        if tombstones != original_tombstones {
+            provider
+                .write_document(TOMBSTONE_DOCUMENT_NAME, serde_json::to_string(&tombstones)?)
                 .await?;
+        }
+
+        publish_tombstones_db(
+            local_tombstones
+                .iter()
+                .filter(|tombstone| !tombstone.published)
+                .map(|tombstone| tombstone.clipboard_id)
+                .collect(),
+        )
+        .await?;
+        delete_tombstones_db(
+            local_tombstones
+                .iter()
+                .filter(|tombstone| {
+                    tombstone.published
+                        && !tombstones.tombstones.contains_key(&tombstone.clipboard_id)
+                })
+                .map(|tombstone| tombstone.clipboard_id)
+                .collect(),
+        )
+        .await?;
 
-            remote_clipboards.extend(new_remote_clipboards.into_iter());
+        provider.cleanup_old_clipboards(&remote_clipboards).await?;
 
-            provider.cleanup_old_clipboards(&remote_clipboards).await?;
+        Ok(remote_ids
+            .into_iter()
+            .filter(|id| !deletions.contains_key(id))
+            .collect())
+    }
+
+    /// Keeps this device's entry in the provider's device manifest current. Returns the
+    /// devices that aren't revoked, turns sync off if this one is.
+    async fn device_job(
+        provider: &dyn SyncProvider,
+        device_id: &str,
+    ) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+        let mut devices = read_sync_devices(provider).await?;
+        let current = current_sync_device();
+
+        match devices.iter().find(|device| device.id == device_id) {
+            Some(device) if device.revoked => {
+                printlog!("device was revoked, turning sync off");
+                update_settings_synchronize_db(false).await?;
+                tauri::async_runtime::spawn(async {
+                    get_sync_manager().lock().await.stop().await;
+                });
+
+                return Err("This device was revoked from sync".into());
+            }
+            Some(device) => {
+                // Only written when something changed, every write wakes up the other devices
+                if device.name != current.name
+                    || device.os != current.os
+                    || device.app_version != current.app_version
+                    || current.last_seen - device.last_seen > DEVICE_SEEN_INTERVAL
+                {
+                    devices = merge_sync_devices(provider, &[current]).await?;
+                }
+            }
+            None => devices = merge_sync_devices(provider, &[current]).await?,
+        }
+
+        Ok(devices
+            .into_iter()
+            .filter(|device| !device.revoked)
+            .map(|device| device.id)
+            .collect())
+    }
+
+    /// Remote side of a tick: asks the change feed and only runs the full sync when it
+    /// reports changes, the fallback is due or there is no feed. Returns whether the
+    /// provider has a change feed.
+    async fn remote_job(
+        cursor: &mut Option<String>,
+        full_sync: bool,
+        remote_ids: &mut HashSet<Uuid>,
+        skipped: &mut SkippedClipboards,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let provider = get_sync_provider()
+            .await
+            .map_err(|CommandError::Error(e)| e)?;
+        let changes = provider.fetch_changes(cursor.as_deref()).await?;
+
+        if full_sync || changes.as_ref().is_none_or(|changes| changes.changed) {
+            *remote_ids = Self::sync_job(skipped).await?;
+        }
+
+        // Only moved on after a successful sync, a failed one is retried from the old cursor
+        let has_feed = changes.is_some();
+        if let Some(changes) = changes {
+            if cursor.as_deref() != Some(changes.cursor.as_str()) {
+                save_cursor(&changes.cursor).await;
+            }
+            *cursor = Some(changes.cursor);
+        }
+
+        Ok(has_feed)
+    }
+
+    /// Uploads new local clipboards right away instead of waiting for the next sync.
+    async fn push_job(
+        ids: Vec<Uuid>,
+        remote_ids: &mut HashSet<Uuid>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let provider = get_sync_provider()
+            .await
+            .map_err(|CommandError::Error(e)| e)?;
+        let rules = SyncRule::from_json_value(&get_global_settings().sync_rules);
+
+        for id in ids {
+            if remote_ids.contains(&id) {
+                continue;
+            }
+
+            // Read back from the db, it holds the encrypted version when encryption is on
+            let clipboard = get_clipboard_db(id).await?;
+            if !SyncRule::allows(&rules, &clipboard) {
+                continue;
+            }
+
+            let remote = provider.upload_clipboard(&clipboard).await?;
+
+            let content_hash = remote.content_hash.unwrap_or_default();
+            upsert_sync_state_db(&clipboard.clipboard, &remote.provider_id, &content_hash).await?;
+            remote_ids.insert(id);
         }
 
         Ok(())
@@ -70,21 +580,71 @@ impl SyncManager {
             return;
         }
 
-        let interval = if cfg!(debug_assertions) {
-            Duration::from_secs(10)
-        } else {
-            Duration::from_secs(30)
-        };
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        *SYNC_PUSH_QUEUE
+            .lock()
+            .expect("Failed to lock sync push queue") = Some(sender);
 
         // Create a new sync task
         let handle = tokio::spawn(async move {
+            let mut cursor = load_cursor().await;
+            let mut remote_ids = HashSet::new();
+            let mut skipped = SkippedClipboards::default();
+            let mut failures = 0u32;
+            let mut next_check = Instant::now();
+            let mut next_full_sync = Instant::now();
+
             loop {
-                // Run the sync job and wait for it to complete
-                if let Err(e) = Self::sync_job().await {
-                    printlog!("sync job failed: {:?}", e);
+                tokio::select! {
+                    Some(id) = receiver.recv() => {
+                        let mut ids = vec![id];
+                        while let Ok(id) = receiver.try_recv() {
+                            ids.push(id);
+                        }
+
+                        // A failed push is picked up by the next full sync
+                        if let Err(e) = Self::push_job(ids, &mut remote_ids).await {
+                            printlog!("sync push failed: {:?}", e);
+                            next_full_sync = Instant::now();
+                        }
+                        continue;
+                    }
+                    _ = time::sleep_until(next_check) => {}
                 }
 
-                time::sleep(interval).await;
+                let full_sync = Instant::now() >= next_full_sync;
+                match Self::remote_job(&mut cursor, full_sync, &mut remote_ids, &mut skipped).await
+                {
+                    Ok(has_feed) => {
+                        failures = 0;
+                        let interval = if has_feed {
+                            CHANGES_INTERVAL
+                        } else {
+                            poll_interval()
+                        };
+                        if full_sync {
+                            next_full_sync = Instant::now() + FALLBACK_INTERVAL;
+                        }
+                        next_check = Instant::now() + interval;
+                    }
+                    Err(e) => {
+                        // Start over with a fresh cursor and a full sync, backing off exponentially
+                        failures = failures.saturating_add(1);
+                        let backoff = poll_interval()
+                            .saturating_mul(2u32.saturating_pow(failures.min(16) - 1))
+                            .min(MAX_BACKOFF);
+                        printlog!(
+                            "sync job failed ({} in a row), retrying in {:?}: {:?}",
+                            failures,
+                            backoff,
+                            e
+                        );
+
+                        cursor = None;
+                        next_full_sync = Instant::now();
+                        next_check = Instant::now() + backoff;
+                    }
+                }
             }
         });
 
@@ -94,6 +654,10 @@ impl SyncManager {
     }
 
     pub async fn stop(&mut self) {
+        *SYNC_PUSH_QUEUE
+            .lock()
+            .expect("Failed to lock sync push queue") = None;
+
         if let Some(handle) = self.job_handle.take() {
             handle.abort();
             self.is_running = false;
@@ -103,3 +667,219 @@ impl SyncManager {
         }
     }
 }
+
+/// Remote differs from what was last synced.
+fn remote_changed(remote: &Clippy, state: &sync_state::Model) -> bool {
+    remote.star != state.star
+        || remote
+            .content_hash
+            .as_deref()
+            .is_some_and(|hash| hash != state.content_hash)
+}
+
+/// Local differs from what was last synced.
+fn local_changed(local: &clipboard::Model, state: &sync_state::Model) -> bool {
+    local.star != state.star || local.name != state.name || local.encrypted != state.encrypted
+}
+
+/// Same types and data, star and name aside.
+fn same_content(a: &FullClipboardDto, b: &FullClipboardDto) -> bool {
+    a.clipboard.types == b.clipboard.types
+        && a.clipboard.encrypted == b.clipboard.encrypted
+        && a.text == b.text
+        && a.html == b.html
+        && a.image == b.image
+        && a.rtf == b.rtf
+        && a.files == b.files
+}
+
+/// Three way merge of a clipboard changed on both sides, with the last sync as base.
+/// Star and name merge on their own, a field changed on only one side keeps that change.
+/// Everything else comes from the side with the newer `updated_at`, the remote on a tie,
+/// so every device ends up with the same result. Lost changes go to the conflict log.
+async fn merge_clipboards(
+    local: &FullClipboardDto,
+    remote: &FullClipboardDto,
+    state: &sync_state::Model,
+) -> Result<FullClipboardDto, DbErr> {
+    let id = local.clipboard.id;
+    let local_newer = local.clipboard.updated_at > remote.clipboard.updated_at;
+    let winner = || {
+        if local_newer {
+            SyncConflictWinner::Local
+        } else {
+            SyncConflictWinner::Remote
+        }
+    };
+
+    let mut merged = if local_newer {
+        local.clone()
+    } else {
+        remote.clone()
+    };
+
+    if !same_content(local, remote) {
+        insert_sync_conflict_db(
+            id,
+            SyncConflictField::Content,
+            Some(local.clipboard.types.to_string()),
+            Some(remote.clipboard.types.to_string()),
+            winner(),
+        )
+        .await?;
+    }
+
+    let (star, star_conflict) = merge_field(
+        &state.star,
+        &local.clipboard.star,
+        &remote.clipboard.star,
+        local_newer,
+    );
+    if star_conflict {
+        insert_sync_conflict_db(
+            id,
+            SyncConflictField::Star,
+            Some(local.clipboard.star.to_string()),
+            Some(remote.clipboard.star.to_string()),
+            winner(),
+        )
+        .await?;
+    }
+
+    let (name, name_conflict) = merge_field(
+        &state.name,
+        &local.clipboard.name,
+        &remote.clipboard.name,
+        local_newer,
+    );
+    if name_conflict {
+        insert_sync_conflict_db(
+            id,
+            SyncConflictField::Name,
+            local.clipboard.name.clone(),
+            remote.clipboard.name.clone(),
+            winner(),
+        )
+        .await?;
+    }
+
+    merged.clipboard.star = star;
+    merged.clipboard.name = name;
+    merged.clipboard.updated_at = local.clipboard.updated_at.max(remote.clipboard.updated_at);
+
+    Ok(merged)
+}
+
+/// Takes the side that changed `base`, the newer side if both did. Returns the value and
+/// whether both changed it to something different.
+fn merge_field<T: PartialEq + Clone>(
+    base: &T,
+    local: &T,
+    remote: &T,
+    local_newer: bool,
+) -> (T, bool) {
+    if local == remote || local == base {
+        (remote.clone(), false)
+    } else if remote == base {
+        (local.clone(), false)
+    } else if local_newer {
+        (local.clone(), true)
+    } else {
+        (remote.clone(), true)
+    }
+}
+
+fn cursor_path() -> PathBuf {
+    PathBuf::from(get_data_path().config_path).join(SYNC_CURSOR_NAME)
+}
+
+/// One cursor file, a cursor of another provider is ignored.
+async fn load_cursor() -> Option<String> {
+    let content = tokio::fs::read_to_string(cursor_path()).await.ok()?;
+    let (provider, cursor) = content.trim().split_once(':')?;
+
+    (provider == get_global_settings().sync_provider && !cursor.is_empty())
+        .then(|| cursor.to_string())
+}
+
+async fn save_cursor(cursor: &str) {
+    let content = format!("{}:{}", get_global_settings().sync_provider, cursor);
+    if let Err(e) = tokio::fs::write(cursor_path(), content).await {
+        printlog!("failed to save sync cursor: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::providers::local_folder::LocalFolderProviderImpl;
+    use crate::utils::providers::tests::text_clipboard;
+
+    fn synced_state(clipboard: &FullClipboardDto, remote: &Clippy) -> sync_state::Model {
+        sync_state::Model {
+            clipboard_id: clipboard.clipboard.id,
+            provider_id: remote.provider_id.clone(),
+            star: clipboard.clipboard.star,
+            name: clipboard.clipboard.name.clone(),
+            encrypted: clipboard.clipboard.encrypted,
+            content_hash: remote.content_hash.clone().unwrap_or_default(),
+            synced_at: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn merge_field_keeps_the_changed_side() {
+        assert_eq!(merge_field(&1, &1, &1, true), (1, false));
+        assert_eq!(merge_field(&1, &2, &1, false), (2, false));
+        assert_eq!(merge_field(&1, &1, &3, true), (3, false));
+        assert_eq!(merge_field(&1, &2, &2, false), (2, false));
+        assert_eq!(merge_field(&1, &2, &3, true), (2, true));
+        assert_eq!(merge_field(&1, &2, &3, false), (3, true));
+    }
+
+    #[tokio::test]
+    async fn merges_changes_from_two_devices_through_a_folder() {
+        let path = std::env::temp_dir().join(format!("clippy-sync-test-{}", Uuid::now_v7()));
+        std::fs::create_dir_all(&path).unwrap();
+        let laptop = LocalFolderProviderImpl::new(path.clone());
+        let desktop = LocalFolderProviderImpl::new(path.clone());
+
+        let mut local = text_clipboard("hello");
+        let uploaded = laptop.upload_clipboard(&local).await.unwrap();
+        let state = synced_state(&local, &uploaded);
+
+        // Renamed on the desktop, starred on the laptop since the last sync
+        let mut renamed = desktop.download_by_id(&uploaded.provider_id).await.unwrap();
+        renamed.clipboard.name = Some("greeting".to_string());
+        renamed.clipboard.updated_at += chrono::Duration::seconds(1);
+        desktop.update_clipboard(&renamed, &uploaded).await.unwrap();
+        local.clipboard.star = true;
+        local.clipboard.updated_at += chrono::Duration::seconds(2);
+
+        let remote = laptop.fetch_all_clipboards().await.unwrap().remove(0);
+        assert!(remote_changed(&remote, &state));
+        assert!(local_changed(&local.clipboard, &state));
+
+        let remote_clipboard = laptop.download_by_id(&remote.provider_id).await.unwrap();
+        let merged = merge_clipboards(&local, &remote_clipboard, &state)
+            .await
+            .unwrap();
+        assert!(merged.clipboard.star);
+        assert_eq!(merged.clipboard.name.as_deref(), Some("greeting"));
+        assert_eq!(merged.clipboard.updated_at, local.clipboard.updated_at);
+
+        laptop.update_clipboard(&merged, &remote).await.unwrap();
+        let remote = desktop.fetch_all_clipboards().await.unwrap().remove(0);
+        assert!(!remote_changed(&remote, &synced_state(&merged, &remote)));
+        assert_eq!(
+            desktop
+                .download_by_id(&remote.provider_id)
+                .await
+                .unwrap()
+                .clipboard,
+            merged.clipboard
+        );
+
+        std::fs::remove_dir_all(&path).ok();
+    }
+}
@@ -0,0 +1,206 @@
+/// user32 and kernel32 bindings.
+#[cfg(target_os = "windows")]
+pub mod windows {
+    use std::ffi::c_void;
+
+    pub type HWND = *mut c_void;
+    pub type HMONITOR = *mut c_void;
+    pub type HANDLE = *mut c_void;
+
+    pub const MONITOR_DEFAULTTONEAREST: u32 = 0x00000002;
+    pub const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+    #[repr(C)]
+    pub struct RECT {
+        pub left: i32,
+        pub top: i32,
+        pub right: i32,
+        pub bottom: i32,
+    }
+
+    #[repr(C)]
+    pub struct MONITORINFO {
+        pub cb_size: u32,
+        pub rc_monitor: RECT,
+        pub rc_work: RECT,
+        pub dw_flags: u32,
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        pub fn GetForegroundWindow() -> HWND;
+        pub fn GetDesktopWindow() -> HWND;
+        pub fn GetShellWindow() -> HWND;
+        pub fn GetWindowRect(hwnd: HWND, lp_rect: *mut RECT) -> i32;
+        pub fn GetWindowThreadProcessId(hwnd: HWND, process_id: *mut u32) -> u32;
+        pub fn GetWindowTextW(hwnd: HWND, string: *mut u16, max_count: i32) -> i32;
+        pub fn MonitorFromWindow(hwnd: HWND, dw_flags: u32) -> HMONITOR;
+        pub fn GetMonitorInfoW(h_monitor: HMONITOR, lpmi: *mut MONITORINFO) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn OpenProcess(desired_access: u32, inherit_handle: i32, process_id: u32) -> HANDLE;
+        pub fn QueryFullProcessImageNameW(
+            process: HANDLE,
+            flags: u32,
+            exe_name: *mut u16,
+            size: *mut u32,
+        ) -> i32;
+        pub fn CloseHandle(handle: HANDLE) -> i32;
+    }
+}
+
+/// CoreGraphics window list and the CoreFoundation calls to read it.
+#[cfg(target_os = "macos")]
+pub mod macos {
+    use std::ffi::{c_char, c_void};
+
+    pub type CFTypeRef = *const c_void;
+
+    pub const UTF8: u32 = 0x08000100;
+    /// `kCFNumberSInt32Type`
+    pub const SINT32: isize = 9;
+    pub const ON_SCREEN_ONLY: u32 = 1;
+    pub const EXCLUDE_DESKTOP_ELEMENTS: u32 = 16;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        pub fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) -> CFTypeRef;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        pub fn CFArrayGetCount(the_array: CFTypeRef) -> isize;
+        pub fn CFArrayGetValueAtIndex(the_array: CFTypeRef, idx: isize) -> CFTypeRef;
+        pub fn CFDictionaryGetValue(the_dict: CFTypeRef, key: CFTypeRef) -> CFTypeRef;
+        pub fn CFNumberGetValue(number: CFTypeRef, the_type: isize, value_ptr: *mut c_void) -> u8;
+        pub fn CFRelease(cf: CFTypeRef);
+        pub fn CFStringCreateWithCString(
+            alloc: CFTypeRef,
+            c_str: *const c_char,
+            encoding: u32,
+        ) -> CFTypeRef;
+        pub fn CFStringGetCString(
+            the_string: CFTypeRef,
+            buffer: *mut c_char,
+            buffer_size: isize,
+            encoding: u32,
+        ) -> u8;
+    }
+
+    /// Layer of a window of the list, app windows are on layer 0.
+    ///
+    /// # Safety
+    /// `window_info` has to be a dictionary of `CGWindowListCopyWindowInfo` and `layer_key`
+    /// the `kCGWindowLayer` string.
+    pub unsafe fn window_layer(window_info: CFTypeRef, layer_key: CFTypeRef) -> Option<i32> {
+        let value = CFDictionaryGetValue(window_info, layer_key);
+        let mut layer: i32 = 0;
+        (!value.is_null()
+            && CFNumberGetValue(value, SINT32, &mut layer as *mut i32 as *mut c_void) != 0)
+            .then_some(layer)
+    }
+}
+
+/// Xlib bindings and the EWMH properties of the active window.
+#[cfg(target_os = "linux")]
+pub mod linux {
+    use std::ffi::{c_char, c_int, c_long, c_uchar, c_ulong, c_void, CStr};
+    use std::ptr::null_mut;
+
+    pub type Display = c_void;
+    pub type Window = c_ulong;
+    pub type Atom = c_ulong;
+
+    #[link(name = "X11")]
+    extern "C" {
+        pub fn XOpenDisplay(display_name: *const c_char) -> *mut Display;
+        pub fn XCloseDisplay(display: *mut Display) -> c_int;
+        fn XDefaultRootWindow(display: *mut Display) -> Window;
+        pub fn XInternAtom(
+            display: *mut Display,
+            atom_name: *const c_char,
+            only_if_exists: c_int,
+        ) -> Atom;
+        fn XGetWindowProperty(
+            display: *mut Display,
+            w: Window,
+            property: Atom,
+            long_offset: c_long,
+            long_length: c_long,
+            delete: c_int,
+            req_type: Atom,
+            actual_type_return: *mut Atom,
+            actual_format_return: *mut c_int,
+            nitems_return: *mut c_ulong,
+            bytes_after_return: *mut c_ulong,
+            prop_return: *mut *mut c_uchar,
+        ) -> c_int;
+        fn XFree(data: *mut c_void) -> c_int;
+    }
+
+    /// The `_NET_ACTIVE_WINDOW` of the root window.
+    ///
+    /// # Safety
+    /// `display` has to be open.
+    pub unsafe fn active_window(display: *mut Display) -> Option<Window> {
+        let root = XDefaultRootWindow(display);
+        let window = window_property(display, root, c"_NET_ACTIVE_WINDOW")?;
+        let window = Window::from_ne_bytes(window.get(..size_of::<Window>())?.try_into().ok()?);
+        (window != 0).then_some(window)
+    }
+
+    /// Raw bytes of a window property, `None` if the window doesn't have it.
+    ///
+    /// # Safety
+    /// `display` has to be open.
+    pub unsafe fn window_property(
+        display: *mut Display,
+        window: Window,
+        name: &CStr,
+    ) -> Option<Vec<u8>> {
+        let property = XInternAtom(display, name.as_ptr(), 1);
+        if property == 0 {
+            return None;
+        }
+
+        let mut actual_type: Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut nitems: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut prop: *mut c_uchar = null_mut();
+
+        let status = XGetWindowProperty(
+            display,
+            window,
+            property,
+            0,
+            1024,
+            0,
+            0,
+            &mut actual_type,
+            &mut actual_format,
+            &mut nitems,
+            &mut bytes_after,
+            &mut prop,
+        );
+
+        if prop.is_null() {
+            return None;
+        }
+
+        // Xlib hands out 32 bit items as longs
+        let item_size = match actual_format {
+            8 => 1,
+            16 => 2,
+            32 => size_of::<c_ulong>(),
+            _ => 0,
+        };
+        let data = (status == 0 && nitems > 0)
+            .then(|| std::slice::from_raw_parts(prop, nitems as usize * item_size).to_vec());
+
+        XFree(prop as *mut c_void);
+        data.filter(|data| !data.is_empty())
+    }
+}
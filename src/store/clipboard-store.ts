@@ -65,6 +65,7 @@ function createClipboardStore() {
     search?: string,
     star?: boolean,
     img?: boolean,
+    fuzzy?: boolean,
   ) => {
     // Supersede any in-flight search: a newer call bumps the id, so stale Channel
     // batches (the backend can't hard-abort the promise) are ignored on arrival.
@@ -90,6 +91,7 @@ function createClipboardStore() {
         search: search || undefined,
         star: star || undefined,
         img: img || undefined,
+        fuzzy: fuzzy || undefined,
         onChunk,
       });
     } catch {
@@ -19,8 +19,53 @@ export interface Progress {
   label: DictionaryKey;
   total: number;
   current: number;
+  duplicates?: number;
+  conflicts?: number;
 }
 
+export type ArchiveFilter = {
+  star?: boolean | null;
+  from?: string | null;
+  to?: string | null;
+  type?: ClipboardType | null;
+};
+
+export type ImportSummary = {
+  imported: number;
+  duplicates: number;
+  conflicts: number;
+  failed: number;
+};
+
+export type LanPeer = {
+  name: string;
+  address: string;
+};
+
+export type SyncDevice = {
+  id: string;
+  name: string;
+  os: string;
+  app_version: string;
+  last_seen: string;
+  revoked: boolean;
+};
+
+export type SyncDevices = {
+  device_id: string;
+  devices: SyncDevice[];
+};
+
+export type SyncConflict = {
+  id: number;
+  clipboard_id: string;
+  field: "star" | "name" | "content" | "deleted";
+  local_value: string | null;
+  remote_value: string | null;
+  winner: "local" | "remote";
+  created_at: string;
+};
+
 export interface TauriError {
   Error: DictionaryKey;
 }
@@ -62,7 +107,14 @@ export interface ClipboardModel {
   types: ClipboardType[];
   star: boolean;
   created_at: string;
+  updated_at: string;
   name: string | null;
+  origin_device: string | null;
+  no_sync: boolean;
+  expires_at: string | null;
+  source_app: string | null;
+  source_window_title: string | null;
+  source_url: string | null;
 }
 
 export interface ClipboardTextModel {
@@ -102,6 +154,20 @@ export interface ClipboardWithRelations {
   image?: ClipboardImageModel;
   rtf?: ClipboardRtfModel;
   files?: ClipboardFileModel[];
+  search_match?: SearchMatch;
+}
+
+// UTF-16 offsets into text.data, image.ocr_text or files[index].name
+export interface MatchRange {
+  field: "text" | "ocr" | "file";
+  index: number;
+  start: number;
+  end: number;
+}
+
+export interface SearchMatch {
+  score: number;
+  ranges: MatchRange[];
 }
 
 export interface ClipboardResponse {
@@ -147,7 +213,7 @@ export type Settings = {
   startup: boolean;
   sync: boolean;
   sync_limit: number;
-  sync_provider: "google_drive";
+  sync_provider: "google_drive" | "webdav" | "local_folder" | "s3" | "lan";
   dark_mode: boolean;
   tooltip: boolean;
   display_scale: number;
@@ -164,6 +230,60 @@ export type Settings = {
   glass: boolean;
   glass_opacity: number;
   glass_grain: number;
+  api_enabled: boolean;
+  api_port: number;
+  api_token: string;
+  webdav_url: string;
+  webdav_username: string;
+  local_sync_path: string;
+  s3_endpoint: string;
+  s3_region: string;
+  s3_bucket: string;
+  s3_prefix: string;
+  s3_access_key_id: string;
+  lan_port: number;
+  lan_peer: string;
+  tombstone_ttl_days: number;
+  sync_rules: SyncRule[];
+  external_blob_store: boolean;
+  duplicate_action: "bump" | "ignore";
+  retention_days: number;
+  retention_max_clips: number;
+  retention_image_days: number;
+  sensitive_rules: SensitiveRule[];
+  concealed_action: SensitiveRule["action"];
+  app_rules: AppRule[];
+};
+
+export type SyncRule = {
+  clipboard_type: ClipboardType | null;
+  min_size: number | null;
+  max_size: number | null;
+  star: boolean | null;
+  pattern: string | null;
+  action: "sync" | "skip";
+  enabled: boolean;
+};
+
+export type AppRule = {
+  app: string;
+  action: "allow" | "skip" | "no_sync";
+  enabled: boolean;
+};
+
+export type SensitiveRule = {
+  detector:
+    | "aws_key"
+    | "github_token"
+    | "jwt"
+    | "private_key"
+    | "card_number"
+    | "high_entropy"
+    | null;
+  pattern: string | null;
+  action: "drop" | "no_sync" | "expire";
+  expire_after: number | null;
+  enabled: boolean;
 };
 
 export type TextMatcher = {
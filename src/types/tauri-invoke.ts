@@ -1,12 +1,20 @@
 import {
+  ArchiveFilter,
   ClipboardResponse,
   ClipboardWhere,
   DatabaseInfo,
   DecryptEvent,
   Hotkey,
+  ImportSummary,
+  LanPeer,
   SearchEvent,
   Settings,
+  SyncConflict,
+  SyncDevices,
   TextMatcher,
+  SyncRule,
+  SensitiveRule,
+  AppRule,
 } from ".";
 import { Channel } from "@tauri-apps/api/core";
 import {
@@ -27,6 +35,10 @@ export enum InvokeCommand {
   SaveClipboardImage = "save_clipboard_image",
   SearchClipboards = "search_clipboards",
 
+  // Archive commands
+  ExportClipboards = "export_clipboards",
+  ImportClipboards = "import_clipboards",
+
   // Hotkey commands
   GetHotkeys = "get_hotkeys",
   UpdateHotkey = "update_hotkey",
@@ -36,6 +48,11 @@ export enum InvokeCommand {
   GetSettings = "get_settings",
   UpdateSettings = "update_settings",
   ChangeSettingsTextMatchers = "change_settings_text_matchers",
+  ChangeSettingsSyncRules = "change_settings_sync_rules",
+  ChangeSettingsSensitiveRules = "change_settings_sensitive_rules",
+  ChangeSettingsAppRules = "change_settings_app_rules",
+  MigrateBlobStore = "migrate_blob_store",
+  RegenerateApiToken = "regenerate_api_token",
   ToggleAutostart = "toggle_autostart",
   ChangeClipboardDbLocation = "change_clipboard_db_location",
   ResetClipboardDbLocation = "reset_clipboard_db_location",
@@ -50,6 +67,17 @@ export enum InvokeCommand {
   // Sync commands
   SyncAuthenticateToggle = "sync_authenticate_toggle",
   SyncLimitChange = "sync_limit_change",
+  SyncWebdavConfigure = "sync_webdav_configure",
+  SyncLocalFolderConfigure = "sync_local_folder_configure",
+  SyncS3Configure = "sync_s3_configure",
+  SyncLanHost = "sync_lan_host",
+  SyncLanPairingCode = "sync_lan_pairing_code",
+  SyncLanDiscover = "sync_lan_discover",
+  SyncLanPair = "sync_lan_pair",
+  SyncConflicts = "sync_conflicts",
+  SyncConflictsClear = "sync_conflicts_clear",
+  SyncDevices = "sync_devices",
+  SyncDeviceRevoke = "sync_device_revoke",
 
   // Cipher commands
   EnableEncryption = "enable_encryption",
@@ -100,11 +128,22 @@ export interface TauriInvokeCommands {
       search?: string;
       star?: boolean;
       img?: boolean;
+      fuzzy?: boolean;
       onChunk: Channel<SearchEvent>;
     };
     return: void;
   };
 
+  // Archive commands
+  [InvokeCommand.ExportClipboards]: {
+    args: { path?: string | null; filter?: ArchiveFilter | null; passphrase?: string | null };
+    return: number | null;
+  };
+  [InvokeCommand.ImportClipboards]: {
+    args: { path?: string | null; passphrase?: string | null };
+    return: ImportSummary | null;
+  };
+
   // Hotkey commands
   [InvokeCommand.GetHotkeys]: {
     args: undefined;
@@ -132,6 +171,26 @@ export interface TauriInvokeCommands {
     args: { textMatchers: TextMatcher[] };
     return: TextMatcher[];
   };
+  [InvokeCommand.ChangeSettingsSyncRules]: {
+    args: { syncRules: SyncRule[] };
+    return: SyncRule[];
+  };
+  [InvokeCommand.ChangeSettingsSensitiveRules]: {
+    args: { sensitiveRules: SensitiveRule[] };
+    return: SensitiveRule[];
+  };
+  [InvokeCommand.ChangeSettingsAppRules]: {
+    args: { appRules: AppRule[] };
+    return: AppRule[];
+  };
+  [InvokeCommand.MigrateBlobStore]: {
+    args: { external: boolean };
+    return: number;
+  };
+  [InvokeCommand.RegenerateApiToken]: {
+    args: undefined;
+    return: Settings;
+  };
   [InvokeCommand.ToggleAutostart]: {
     args: undefined;
     return: void;
@@ -176,6 +235,57 @@ export interface TauriInvokeCommands {
     args: { syncLimit: number };
     return: Settings;
   };
+  [InvokeCommand.SyncWebdavConfigure]: {
+    args: { url: string; username: string; password: string };
+    return: Settings;
+  };
+  [InvokeCommand.SyncLocalFolderConfigure]: {
+    args: { path?: string | null };
+    return: Settings;
+  };
+  [InvokeCommand.SyncS3Configure]: {
+    args: {
+      endpoint: string;
+      region: string;
+      bucket: string;
+      prefix: string;
+      accessKeyId: string;
+      secretAccessKey: string;
+    };
+    return: Settings;
+  };
+  [InvokeCommand.SyncLanHost]: {
+    args: undefined;
+    return: Settings;
+  };
+  [InvokeCommand.SyncLanPairingCode]: {
+    args: undefined;
+    return: string;
+  };
+  [InvokeCommand.SyncLanDiscover]: {
+    args: undefined;
+    return: LanPeer[];
+  };
+  [InvokeCommand.SyncLanPair]: {
+    args: { address: string; code: string };
+    return: Settings;
+  };
+  [InvokeCommand.SyncConflicts]: {
+    args: undefined;
+    return: SyncConflict[];
+  };
+  [InvokeCommand.SyncConflictsClear]: {
+    args: undefined;
+    return: void;
+  };
+  [InvokeCommand.SyncDevices]: {
+    args: undefined;
+    return: SyncDevices;
+  };
+  [InvokeCommand.SyncDeviceRevoke]: {
+    args: { deviceId: string };
+    return: SyncDevices;
+  };
 
   // Cipher commands
   [InvokeCommand.EnableEncryption]: {